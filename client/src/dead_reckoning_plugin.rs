@@ -0,0 +1,70 @@
+use std::time::Instant;
+
+use bevy::prelude::*;
+use server::server::components::{
+    movement_state::MovementState, position::Position, shared::vec3d::Vec3d,
+};
+
+use crate::{replication_plugin::apply_replication, udp_plugin::udp_system};
+
+// the last position the server sent, everything in between is extrapolated from it
+#[derive(Component, Debug)]
+pub struct Extrapolation {
+    anchor: Vec3d,
+    since: Instant,
+}
+
+impl Extrapolation {
+    pub fn new(anchor: Vec3d) -> Self {
+        Extrapolation {
+            anchor,
+            since: Instant::now(),
+        }
+    }
+}
+
+// moves entities between snapshots with the same rule the server uses to decide on corrections
+pub struct DeadReckoningPlugin;
+
+impl Plugin for DeadReckoningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (reanchor_on_movement_change, extrapolate_positions)
+                .chain()
+                .after(udp_system)
+                .after(apply_replication),
+        );
+    }
+}
+
+// the server re-anchors on every movement change as well, its correction follows shortly
+fn reanchor_on_movement_change(
+    mut query: Query<(&Position, &mut Extrapolation), Changed<MovementState>>,
+) {
+    for (position, mut extrapolation) in query.iter_mut() {
+        *extrapolation = Extrapolation::new(position.position.clone());
+    }
+}
+
+fn extrapolate_positions(
+    mut query: Query<(
+        &Extrapolation,
+        &MovementState,
+        &mut Position,
+        &mut Transform,
+    )>,
+) {
+    for (extrapolation, movement_state, mut position, mut transform) in query.iter_mut() {
+        position.position = movement_state.extrapolate(
+            &extrapolation.anchor,
+            extrapolation.since.elapsed().as_secs_f64(),
+        );
+
+        transform.translation = Vec3::new(
+            position.position.x as f32,
+            position.position.y as f32,
+            position.position.z as f32,
+        );
+    }
+}
@@ -4,14 +4,22 @@ use bevy::prelude::*;
 use server::server::{
     commands::move_command::MoveCommand,
     components::{
-        movement_state::MovementStateType, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
+        movement_state::{MovementState, MovementStateType},
+        networked::Networked,
+        position::Position,
+        shared::{net_id::NetId, vec3d::Vec3d},
     },
     packets::packet::Packet,
 };
 
-use crate::udp_plugin::{IsMoving, OwnedEntityId, UdpPlugin};
+use crate::{
+    dead_reckoning_plugin::DeadReckoningPlugin,
+    replication_plugin::{ReplicationAppExt, ReplicationPlugin},
+    udp_plugin::{IsMoving, OwnedEntityId, UdpPlugin},
+};
 
+mod dead_reckoning_plugin;
+mod replication_plugin;
 mod udp_plugin;
 
 fn main() {
@@ -35,6 +43,9 @@ fn main() {
         .add_plugins(bevy_tokio_tasks::TokioTasksPlugin::default())
         .insert_resource(CommandContainer::default())
         .add_plugins(udp_plugin)
+        .add_plugins(ReplicationPlugin)
+        .replicate::<MovementState>()
+        .add_plugins(DeadReckoningPlugin)
         .add_systems(Startup, setup)
         .add_systems(Update, (handle_input,))
         .run();
@@ -74,8 +85,12 @@ fn handle_input(
     mut is_moving: ResMut<IsMoving>,
     mut query: Query<(&mut Position, &mut Networked), (With<Position>, With<Networked>)>,
 ) {
-    let mut move_command =
-        MoveCommand::new("".to_string(), 0.0, 0.0, 0.0, MovementStateType::Moving);
+    // nothing to steer until the server told us which entity we own
+    let Some(owned_id): Option<NetId> = *owned_entity_id.0.lock().unwrap() else {
+        return;
+    };
+
+    let mut move_command = MoveCommand::new(owned_id, 0.0, 0.0, 0.0, MovementStateType::Moving);
 
     let mut has_move = false;
 
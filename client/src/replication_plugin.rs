@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use server::server::{
+    protocols::send::replicate_packet::ReplicatePacket,
+    replication::{registry::ReplicationRegistry, replicated::Replicated},
+    systems::networked_index::NetworkedIndex,
+};
+
+use crate::udp_plugin::udp_system;
+
+#[derive(Event, Debug)]
+pub struct ReplicateReceived(pub ReplicatePacket);
+
+// applies replicated components for every type registered through `ReplicationAppExt::replicate`
+pub struct ReplicationPlugin;
+
+impl Plugin for ReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        // filled by the `Networked` component hooks, just like on the server
+        app.insert_resource(NetworkedIndex::new())
+            .init_resource::<ReplicationRegistry>()
+            .add_event::<ReplicateReceived>()
+            .add_systems(Update, apply_replication.after(udp_system));
+    }
+}
+
+pub trait ReplicationAppExt {
+    fn replicate<T: Replicated>(&mut self) -> &mut Self;
+}
+
+impl ReplicationAppExt for App {
+    fn replicate<T: Replicated>(&mut self) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<ReplicationRegistry>()
+            .register::<T>();
+        self
+    }
+}
+
+pub fn apply_replication(world: &mut World) {
+    let packets: Vec<ReplicatePacket> = world
+        .resource_mut::<Events<ReplicateReceived>>()
+        .drain()
+        .map(|received| received.0)
+        .collect();
+
+    world.resource_scope(|world, registry: Mut<ReplicationRegistry>| {
+        for packet in packets {
+            if let Err(e) = registry.apply(world, &packet) {
+                warn!(
+                    "Failed to apply replicated components of {}: {}",
+                    packet.id, e
+                );
+            }
+        }
+    });
+}
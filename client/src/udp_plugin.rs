@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     net::SocketAddr,
     sync::{
         Arc, Mutex,
@@ -11,19 +12,42 @@ use bevy::prelude::*;
 use bevy_tokio_tasks::TokioTasksRuntime;
 use server::server::{
     components::{
-        movement_state::MovementStateType, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
+        networked::Networked,
+        position::Position,
+        shared::{net_id::NetId, vec3d::Vec3d},
     },
+    connections::session_token::SessionToken,
     opcode::OpCode,
     packets::packet::Packet,
     protocols::{
-        recv::{enter_packet::EnterPacket, move_packet::MovePacket},
-        send::{enown_packet::EnownPacket, moved_packet::MovedPacket, spawn_packet::SpawnPacket},
+        recv::{
+            create_character_packet::{Appearance, CharacterClass, CreateCharacterPacket},
+            credentials_packet::CredentialsPacket,
+            enter_packet::EnterPacket,
+            list_characters_packet::ListCharactersPacket,
+            move_packet::MovePacket,
+            snapshot_ack_packet::SnapshotAckPacket,
+        },
+        send::{
+            authenticated_packet::AuthenticatedPacket,
+            character_list_packet::CharacterListPacket,
+            control_packet::ControlPacket,
+            despawn_packet::DespawnPacket,
+            enown_packet::EnownPacket,
+            error_packet::{ErrorCode, ErrorPacket},
+            replicate_packet::ReplicatePacket,
+            snapshot_packet::SnapshotPacket,
+            spawn_packet::SpawnPacket,
+        },
+        version::PROTOCOL_VERSION,
     },
+    replication::snapshot::{SnapshotHistory, WorldSnapshot},
 };
 use tokio::net::UdpSocket;
 
-use crate::CommandContainer;
+use crate::{
+    CommandContainer, dead_reckoning_plugin::Extrapolation, replication_plugin::ReplicateReceived,
+};
 
 #[derive(Resource)]
 pub struct IsMoving(pub Vec3d);
@@ -32,7 +56,36 @@ pub struct IsMoving(pub Vec3d);
 pub struct CurrentPacketId(pub Arc<Mutex<u128>>);
 
 #[derive(Resource)]
-pub struct OwnedEntityId(pub Arc<Mutex<String>>);
+pub struct OwnedEntityId(pub Arc<Mutex<Option<NetId>>>);
+
+// sent with every packet, the server uses it to find us again after our address changed
+#[derive(Resource, Default)]
+pub struct Session(pub Option<SessionToken>);
+
+// the account to play on, registered on first start and logged in to after that
+#[derive(Resource)]
+pub struct Credentials {
+    pub name: String,
+    pub password: String,
+    // created when the account has no characters yet
+    pub character: String,
+}
+
+impl Credentials {
+    pub fn from_env() -> Self {
+        Credentials {
+            name: std::env::var("FORDRAGON_ACCOUNT").unwrap_or_else(|_| "player".to_string()),
+            password: std::env::var("FORDRAGON_PASSWORD")
+                .unwrap_or_else(|_| "password".to_string()),
+            character: std::env::var("FORDRAGON_CHARACTER")
+                .unwrap_or_else(|_| "Player".to_string()),
+        }
+    }
+}
+
+// snapshots already applied, later ones arrive as deltas against them
+#[derive(Resource)]
+pub struct ReceivedSnapshots(pub SnapshotHistory);
 
 #[derive(Resource)]
 pub struct SocketPackets {
@@ -77,9 +130,20 @@ pub fn udp_system(
     curr_packet_id: ResMut<CurrentPacketId>,
     mut command_container: ResMut<CommandContainer>,
     owned_entity_id: ResMut<OwnedEntityId>,
+    mut session: ResMut<Session>,
+    mut replicated: EventWriter<ReplicateReceived>,
+    mut received_snapshots: ResMut<ReceivedSnapshots>,
+    credentials: Res<Credentials>,
 ) {
     let received_packets_receiver = socket_packets.received_packets_receiver.lock().unwrap();
 
+    let mut snapshot_acks = vec![];
+    let mut spawned = HashSet::new();
+    let mut log_in = false;
+    let mut list_characters = false;
+    let mut create_character = false;
+    let mut enter_with = None;
+
     while let Ok(packet) = received_packets_receiver.try_recv() {
         match packet.opcode {
             OpCode::Enown => {
@@ -87,61 +151,238 @@ pub fn udp_system(
 
                 println!("Enown packet received: {:?}", enown_packet);
 
-                *owned_entity_id.0.lock().unwrap() = enown_packet.id.clone();
+                *owned_entity_id.0.lock().unwrap() = Some(enown_packet.id);
+
+                if enown_packet.session.is_some() {
+                    session.0 = enown_packet.session;
+                }
             }
             OpCode::Spawn => {
                 let spawned_packet: SpawnPacket = serde_json::from_str(&packet.data).unwrap();
 
                 println!("Spawn packet received: {:?}", spawned_packet);
 
-                commands.spawn((
-                    Networked {
-                        id: spawned_packet.id.clone(),
-                    },
-                    Position {
-                        position: spawned_packet.location.clone(),
-                    },
-                    Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
-                    MeshMaterial3d(materials.add(Color::srgb_u8(124, 144, 255))),
-                    Transform::from_xyz(
-                        spawned_packet.location.x as f32,
-                        spawned_packet.location.y as f32,
-                        spawned_packet.location.z as f32,
-                    ),
-                ));
+                // a repeated spawn only refreshes the entity we already have
+                if let Some(mut existing) = query
+                    .iter_mut()
+                    .find(|entity| entity.2.id == spawned_packet.id)
+                {
+                    let location = spawned_packet.location;
+
+                    existing.3.translation =
+                        Vec3::new(location.x as f32, location.y as f32, location.z as f32);
+                    existing.1.position = location.clone();
+
+                    commands
+                        .entity(existing.0)
+                        .insert(Extrapolation::new(location));
+                    continue;
+                }
+
+                // spawns are deferred, so duplicates within this frame are not in the query yet
+                if !spawned.insert(spawned_packet.id) {
+                    continue;
+                }
+
+                spawn_networked(
+                    &mut commands,
+                    &mut meshes,
+                    &mut materials,
+                    spawned_packet.id,
+                    spawned_packet.location,
+                );
             }
-            OpCode::Moved => {
-                let move_packet: MovedPacket = serde_json::from_str(&packet.data).unwrap();
+            OpCode::Despawn => {
+                let despawn_packet: DespawnPacket = serde_json::from_str(&packet.data).unwrap();
+
+                println!("Despawn packet received: {:?}", despawn_packet);
+
+                for entity in query.iter() {
+                    if entity.2.id == despawn_packet.id {
+                        println!(
+                            "Despawning {} ({:?})",
+                            despawn_packet.id, despawn_packet.reason
+                        );
+                        commands.entity(entity.0).despawn();
+                    }
+                }
+            }
+            OpCode::Control => {
+                let control_packet: ControlPacket = serde_json::from_str(&packet.data).unwrap();
+
+                println!("Control packet received: {:?}", control_packet);
+            }
+            OpCode::Snapshot => {
+                let snapshot_packet: SnapshotPacket = serde_json::from_str(&packet.data).unwrap();
+
+                // snapshots are unreliable, an older one arriving late is simply stale
+                if received_snapshots
+                    .0
+                    .latest()
+                    .is_some_and(|latest| latest.sequence >= snapshot_packet.sequence)
+                {
+                    continue;
+                }
 
-                println!("Move packet received: {:?}", move_packet);
+                let baseline = snapshot_packet
+                    .baseline
+                    .and_then(|sequence| received_snapshots.0.get(sequence));
+
+                let snapshot = match WorldSnapshot::apply(baseline, &snapshot_packet) {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        println!("Dropping snapshot {}: {}", snapshot_packet.sequence, e);
+                        continue;
+                    }
+                };
 
                 for mut entity in query.iter_mut() {
-                    if entity.2.id != move_packet.networked_id {
+                    let Some(position) = snapshot.position(entity.2.id) else {
+                        continue;
+                    };
+
+                    entity.3.translation =
+                        Vec3::new(position.x as f32, position.y as f32, position.z as f32);
+                    entity.1.position = position.clone();
+
+                    commands
+                        .entity(entity.0)
+                        .insert(Extrapolation::new(position));
+                }
+
+                // spawns are not resent, an entity whose spawn got lost is created from the snapshot
+                for added in &snapshot_packet.added {
+                    if query.iter().any(|entity| entity.2.id == added.id)
+                        || !spawned.insert(added.id)
+                    {
                         continue;
                     }
 
-                    entity.1.position = move_packet.vector.clone();
-                    entity.3.translation = Vec3::new(
-                        move_packet.vector.x as f32,
-                        move_packet.vector.y as f32,
-                        move_packet.vector.z as f32,
+                    let Some(position) = snapshot.position(added.id) else {
+                        continue;
+                    };
+
+                    println!("Spawning {} from snapshot", added.id);
+
+                    spawn_networked(
+                        &mut commands,
+                        &mut meshes,
+                        &mut materials,
+                        added.id,
+                        position,
                     );
                 }
 
-                println!(
-                    "Entity {} moved to position: ({}, {}, {})",
-                    move_packet.networked_id,
-                    move_packet.vector.x as f32,
-                    move_packet.vector.y as f32,
-                    move_packet.vector.z as f32,
-                );
+                snapshot_acks.push(snapshot.sequence);
+                received_snapshots.0.push(snapshot);
+            }
+            OpCode::Authenticated => {
+                let authenticated_packet: AuthenticatedPacket =
+                    serde_json::from_str(&packet.data).unwrap();
+
+                println!("Logged in as {}", authenticated_packet.account);
+
+                list_characters = true;
+            }
+            OpCode::CharacterList => {
+                let character_list_packet: CharacterListPacket =
+                    serde_json::from_str(&packet.data).unwrap();
+
+                println!("Character list received: {:?}", character_list_packet);
+
+                // plays the first character, or creates one to play
+                match character_list_packet.characters.first() {
+                    Some(character) if owned_entity_id.0.lock().unwrap().is_none() => {
+                        enter_with = Some(character.id.clone());
+                    }
+                    Some(_) => {}
+                    None => create_character = true,
+                }
+            }
+            OpCode::Error => {
+                let error_packet: ErrorPacket = serde_json::from_str(&packet.data).unwrap();
+
+                println!("Error packet received: {:?}", error_packet);
+
+                // registered on an earlier start
+                log_in |= error_packet.code == ErrorCode::AccountExists;
+            }
+            OpCode::Replicate | OpCode::ReplicateUnreliable => {
+                let replicate_packet: ReplicatePacket = serde_json::from_str(&packet.data).unwrap();
+
+                replicated.write(ReplicateReceived(replicate_packet));
             }
             _ => {}
         }
     }
 
+    if log_in {
+        send_credentials(
+            &socket_packets,
+            &curr_packet_id,
+            &session,
+            &credentials,
+            OpCode::Login,
+        );
+    }
+
+    if list_characters {
+        send_packet(
+            &socket_packets,
+            &curr_packet_id,
+            &session,
+            OpCode::ListCharacters,
+            serde_json::to_string(&ListCharactersPacket::default()).unwrap(),
+        );
+    }
+
+    if create_character {
+        send_packet(
+            &socket_packets,
+            &curr_packet_id,
+            &session,
+            OpCode::CreateCharacter,
+            serde_json::to_string(&CreateCharacterPacket::new(
+                credentials.character.clone(),
+                CharacterClass::Warrior,
+                Appearance::default(),
+            ))
+            .unwrap(),
+        );
+    }
+
+    if let Some(character) = enter_with {
+        send_packet(
+            &socket_packets,
+            &curr_packet_id,
+            &session,
+            OpCode::Enter,
+            serde_json::to_string(&EnterPacket::new(PROTOCOL_VERSION, character)).unwrap(),
+        );
+    }
+
     let packets_to_send_sender = socket_packets.packets_to_send_sender.lock().unwrap();
 
+    for sequence in snapshot_acks {
+        let packet_id = {
+            let mut id_container = curr_packet_id.0.lock().unwrap();
+
+            let curr_id = *id_container;
+            *id_container += 1;
+
+            curr_id
+        };
+
+        let packet = Packet {
+            id: packet_id,
+            opcode: OpCode::SnapshotAck,
+            data: serde_json::to_string(&SnapshotAckPacket::new(sequence)).unwrap(),
+            session: session.0,
+        };
+
+        packets_to_send_sender.send(packet).unwrap();
+    }
+
     for move_command in command_container.move_commands.iter_mut() {
         let packet_id = {
             let mut id_container = curr_packet_id.0.lock().unwrap();
@@ -160,6 +401,7 @@ pub fn udp_system(
                 move_command.state.clone(),
             ))
             .unwrap(),
+            session: session.0,
         };
 
         packets_to_send_sender.send(packet).unwrap();
@@ -168,7 +410,67 @@ pub fn udp_system(
     command_container.move_commands.clear();
 }
 
-pub fn enter_world(socket_packets: Res<SocketPackets>, curr_packet_id: ResMut<CurrentPacketId>) {
+pub fn register_account(
+    socket_packets: Res<SocketPackets>,
+    curr_packet_id: ResMut<CurrentPacketId>,
+    session: Res<Session>,
+    credentials: Res<Credentials>,
+) {
+    send_credentials(
+        &socket_packets,
+        &curr_packet_id,
+        &session,
+        &credentials,
+        OpCode::Register,
+    );
+}
+
+fn spawn_networked(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    id: NetId,
+    location: Vec3d,
+) {
+    commands.spawn((
+        Networked { id },
+        Position {
+            position: location.clone(),
+        },
+        Extrapolation::new(location.clone()),
+        Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+        MeshMaterial3d(materials.add(Color::srgb_u8(124, 144, 255))),
+        Transform::from_xyz(location.x as f32, location.y as f32, location.z as f32),
+    ));
+}
+
+fn send_credentials(
+    socket_packets: &SocketPackets,
+    curr_packet_id: &CurrentPacketId,
+    session: &Session,
+    credentials: &Credentials,
+    opcode: OpCode,
+) {
+    send_packet(
+        socket_packets,
+        curr_packet_id,
+        session,
+        opcode,
+        serde_json::to_string(&CredentialsPacket::new(
+            credentials.name.clone(),
+            credentials.password.clone(),
+        ))
+        .unwrap(),
+    );
+}
+
+fn send_packet(
+    socket_packets: &SocketPackets,
+    curr_packet_id: &CurrentPacketId,
+    session: &Session,
+    opcode: OpCode,
+    data: String,
+) {
     let packet_to_send_sender = socket_packets.packets_to_send_sender.lock().unwrap();
 
     let packet_id = {
@@ -182,13 +484,14 @@ pub fn enter_world(socket_packets: Res<SocketPackets>, curr_packet_id: ResMut<Cu
 
     let packet = Packet {
         id: packet_id,
-        opcode: OpCode::Enter,
-        data: serde_json::to_string(&EnterPacket {}).unwrap(),
+        opcode,
+        data,
+        session: session.0,
     };
 
     packet_to_send_sender.send(packet).unwrap();
 
-    println!("Enter packet sent");
+    println!("{:?} packet sent", opcode);
 }
 
 pub fn start_listen_connection(
@@ -263,7 +566,7 @@ impl Plugin for UdpPlugin {
         let current_packet_id = CurrentPacketId(Arc::new(Mutex::new(0)));
         let is_moving = IsMoving(Vec3d::zero());
 
-        let owned_entity_id = OwnedEntityId(Arc::new(Mutex::new("".to_string())));
+        let owned_entity_id = OwnedEntityId(Arc::new(Mutex::new(None)));
 
         app.insert_resource(SocketPackets {
             received_packets_receiver: self.received_packets_receiver.clone(),
@@ -274,7 +577,10 @@ impl Plugin for UdpPlugin {
         .insert_resource(current_packet_id)
         .insert_resource(is_moving)
         .insert_resource(owned_entity_id)
-        .add_systems(Startup, (start_listen_connection, enter_world))
+        .insert_resource(Session::default())
+        .insert_resource(Credentials::from_env())
+        .insert_resource(ReceivedSnapshots(SnapshotHistory::new(32)))
+        .add_systems(Startup, (start_listen_connection, register_account))
         .add_systems(Update, udp_system);
     }
 }
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    database_handler::DatabaseHandler,
+    error::{DatabaseError, Error},
+    model::Account,
+};
+
+// where accounts live, `DatabaseHandler` in production and `InMemoryAccountStore` in tests
+#[async_trait]
+pub trait AccountStore: Send + Sync {
+    async fn find(&self, name: &str) -> Result<Option<Account>>;
+    // fails with a `DatabaseError` that `is_taken` when the name is
+    async fn insert(&self, account: Account) -> Result<()>;
+    async fn update(&self, account: Account) -> Result<()>;
+}
+
+// expects a handler connected to the accounts collection
+#[async_trait]
+impl AccountStore for DatabaseHandler {
+    async fn find(&self, name: &str) -> Result<Option<Account>> {
+        self.get::<Account>(name).await
+    }
+
+    async fn insert(&self, account: Account) -> Result<()> {
+        self.add(account).await?;
+        Ok(())
+    }
+
+    async fn update(&self, account: Account) -> Result<()> {
+        let name = account.name.clone();
+
+        self.replace(&name, account).await?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryAccountStore {
+    accounts: Mutex<HashMap<String, Account>>,
+}
+
+impl InMemoryAccountStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AccountStore for InMemoryAccountStore {
+    async fn find(&self, name: &str) -> Result<Option<Account>> {
+        Ok(self.accounts.lock().unwrap().get(name).cloned())
+    }
+
+    async fn insert(&self, account: Account) -> Result<()> {
+        let mut accounts = self.accounts.lock().unwrap();
+
+        if accounts.contains_key(&account.name) {
+            return Err(Error::DatabaseError(DatabaseError::ExistingItem(account.name)).into());
+        }
+
+        accounts.insert(account.name.clone(), account);
+        Ok(())
+    }
+
+    async fn update(&self, account: Account) -> Result<()> {
+        self.accounts
+            .lock()
+            .unwrap()
+            .insert(account.name.clone(), account);
+        Ok(())
+    }
+}
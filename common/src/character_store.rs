@@ -0,0 +1,132 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{
+    database_handler::DatabaseHandler,
+    error::{DatabaseError, Error},
+    model::{Character, CharacterState},
+};
+
+// where characters live, `DatabaseHandler` in production and `InMemoryCharacterStore` in tests
+#[async_trait]
+pub trait CharacterStore: Send + Sync {
+    async fn list(&self, account: &str) -> Result<Vec<Character>>;
+    async fn find(&self, uuid: &str) -> Result<Option<Character>>;
+    // fails with a `DatabaseError` that `is_taken` when the name is
+    async fn insert(&self, character: Character) -> Result<()>;
+    async fn update(&self, character: Character) -> Result<()>;
+    async fn remove(&self, uuid: &str) -> Result<()>;
+    // saves what changed while in the world, leaving the rest of the record alone
+    async fn save_state(&self, uuid: &str, state: CharacterState) -> Result<()>;
+}
+
+// expects a handler connected to the characters collection
+#[async_trait]
+impl CharacterStore for DatabaseHandler {
+    async fn list(&self, account: &str) -> Result<Vec<Character>> {
+        self.find_by::<Character>("account", account).await
+    }
+
+    async fn find(&self, uuid: &str) -> Result<Option<Character>> {
+        Ok(self
+            .find_by::<Character>("uuid", uuid)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    async fn insert(&self, character: Character) -> Result<()> {
+        self.add(character).await?;
+        Ok(())
+    }
+
+    async fn update(&self, character: Character) -> Result<()> {
+        let name = character.name.clone();
+
+        self.replace(&name, character).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, uuid: &str) -> Result<()> {
+        if let Some(character) = CharacterStore::find(self, uuid).await? {
+            DatabaseHandler::remove(self, &character.name).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn save_state(&self, uuid: &str, state: CharacterState) -> Result<()> {
+        self.set_by("uuid", uuid, state).await?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCharacterStore {
+    characters: Mutex<HashMap<String, Character>>,
+}
+
+impl InMemoryCharacterStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CharacterStore for InMemoryCharacterStore {
+    async fn list(&self, account: &str) -> Result<Vec<Character>> {
+        let mut characters: Vec<_> = self
+            .characters
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|character| character.account == account)
+            .cloned()
+            .collect();
+
+        characters.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(characters)
+    }
+
+    async fn find(&self, uuid: &str) -> Result<Option<Character>> {
+        Ok(self.characters.lock().unwrap().get(uuid).cloned())
+    }
+
+    async fn insert(&self, character: Character) -> Result<()> {
+        let mut characters = self.characters.lock().unwrap();
+
+        if characters
+            .values()
+            .any(|other| other.name_key == character.name_key)
+        {
+            return Err(Error::DatabaseError(DatabaseError::ExistingItem(character.uuid)).into());
+        }
+
+        characters.insert(character.uuid.clone(), character);
+        Ok(())
+    }
+
+    async fn update(&self, character: Character) -> Result<()> {
+        self.characters
+            .lock()
+            .unwrap()
+            .insert(character.uuid.clone(), character);
+        Ok(())
+    }
+
+    async fn remove(&self, uuid: &str) -> Result<()> {
+        self.characters.lock().unwrap().remove(uuid);
+        Ok(())
+    }
+
+    async fn save_state(&self, uuid: &str, state: CharacterState) -> Result<()> {
+        if let Some(character) = self.characters.lock().unwrap().get_mut(uuid) {
+            character.apply(state);
+        }
+
+        Ok(())
+    }
+}
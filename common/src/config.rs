@@ -1,6 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::{fs, path::Path};
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ConfigString {
@@ -8,6 +8,23 @@ struct ConfigString {
     pub db_name: String,
     pub tick_count: u8,
     pub log_level: String,
+    pub position_precision: f64,
+    pub snapshot_history: usize,
+    pub interest_radius: f64,
+    pub position_tolerance: f64,
+    pub keyframe_ticks: u32,
+    pub bandwidth_per_tick: usize,
+    pub connection_timeout_secs: u64,
+    pub max_packets_per_second: u32,
+    pub packet_burst: u32,
+    pub session_grace_secs: u64,
+    pub max_login_attempts: u32,
+    pub login_window_secs: u64,
+    pub max_registrations: u32,
+    pub registration_window_secs: u64,
+    pub max_characters_per_account: usize,
+    pub persistence_interval_secs: u64,
+    pub persistence_batch_size: usize,
 }
 
 #[derive(Debug)]
@@ -16,11 +33,32 @@ pub struct Config {
     pub db_name: String,
     pub tick_count: u8,
     pub log_level: log::LevelFilter,
+    pub position_precision: f64,
+    pub snapshot_history: usize,
+    pub interest_radius: f64,
+    pub position_tolerance: f64,
+    pub keyframe_ticks: u32,
+    pub bandwidth_per_tick: usize,
+    pub connection_timeout_secs: u64,
+    pub max_packets_per_second: u32,
+    pub packet_burst: u32,
+    pub session_grace_secs: u64,
+    pub max_login_attempts: u32,
+    pub login_window_secs: u64,
+    pub max_registrations: u32,
+    pub registration_window_secs: u64,
+    pub max_characters_per_account: usize,
+    pub persistence_interval_secs: u64,
+    pub persistence_batch_size: usize,
 }
 
 impl Config {
     pub fn get() -> Result<Config> {
-        Ok(serde_json::from_str::<ConfigString>(&fs::read_to_string("server.json")?)?.into())
+        Self::load(Path::new("server.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<Config> {
+        Ok(serde_json::from_str::<ConfigString>(&fs::read_to_string(path)?)?.into())
     }
 }
 
@@ -31,6 +69,23 @@ impl From<ConfigString> for Config {
             db_name: cfg.db_name,
             tick_count: cfg.tick_count,
             log_level: cfg.log_level.parse().unwrap_or(log::LevelFilter::Info),
+            position_precision: cfg.position_precision,
+            snapshot_history: cfg.snapshot_history,
+            interest_radius: cfg.interest_radius,
+            position_tolerance: cfg.position_tolerance,
+            keyframe_ticks: cfg.keyframe_ticks,
+            bandwidth_per_tick: cfg.bandwidth_per_tick,
+            connection_timeout_secs: cfg.connection_timeout_secs,
+            max_packets_per_second: cfg.max_packets_per_second,
+            packet_burst: cfg.packet_burst,
+            session_grace_secs: cfg.session_grace_secs,
+            max_login_attempts: cfg.max_login_attempts,
+            login_window_secs: cfg.login_window_secs,
+            max_registrations: cfg.max_registrations,
+            registration_window_secs: cfg.registration_window_secs,
+            max_characters_per_account: cfg.max_characters_per_account,
+            persistence_interval_secs: cfg.persistence_interval_secs,
+            persistence_batch_size: cfg.persistence_batch_size,
         }
     }
 }
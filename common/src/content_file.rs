@@ -0,0 +1,151 @@
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::Error;
+
+// how content is written by hand, both hold either a single entry or a list of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    Ron,
+}
+
+impl Format {
+    // by extension, anything but `.ron` is json
+    pub fn of(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ron") => Format::Ron,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Ron => "ron",
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, text: &str) -> Result<Vec<T>> {
+        let list = text.trim_start().starts_with('[');
+
+        let parsed = match (self, list) {
+            (Format::Json, true) => serde_json::from_str(text).map_err(|e| e.to_string()),
+            (Format::Json, false) => serde_json::from_str(text)
+                .map(|entry| vec![entry])
+                .map_err(|e| e.to_string()),
+            (Format::Ron, true) => ron::from_str(text).map_err(|e| e.to_string()),
+            (Format::Ron, false) => ron::from_str(text)
+                .map(|entry| vec![entry])
+                .map_err(|e| e.to_string()),
+        };
+
+        parsed.map_err(|e| Error::ParsingError(e).into())
+    }
+
+    pub fn write<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        let written = match self {
+            Format::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            Format::Ron => ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+                .map_err(|e| e.to_string()),
+        };
+
+        written.map_err(|e| Error::ParsingError(e).into())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(Format::Json),
+            "ron" => Ok(Format::Ron),
+            _ => Err(Error::InvalidArguments(format!(
+                "Unknown format {}, expected json or ron",
+                format
+            ))),
+        }
+    }
+}
+
+// the entries of a file in the format its extension names
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Error::FileError(format!("{}: {}", path.display(), e)))?;
+
+    Format::of(path)
+        .parse(&text)
+        .map_err(|e| Error::FileError(format!("{}: {}", path.display(), e)).into())
+}
+
+pub fn write<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    let text = Format::of(path).write(value)?;
+
+    fs::write(path, text).map_err(|e| Error::FileError(format!("{}: {}", path.display(), e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{EquipableItem, Item, ItemType, Stats};
+
+    use super::*;
+
+    fn item(uuid: &str) -> Item {
+        Item {
+            uuid: uuid.to_string(),
+            name: "Sword".to_string(),
+            item_type: ItemType::TwoHandedSword(EquipableItem {
+                stats: Stats {
+                    stamina: 3,
+                    intellect: 0,
+                    agility: -1,
+                },
+            }),
+        }
+    }
+
+    #[test]
+    fn test_single_entries_and_lists_are_read() {
+        let items: Vec<Item> = Format::Ron
+            .parse(
+                r#"(
+                    uuid: "1",
+                    name: "Sword",
+                    item_type: TwoHandedSword((stats: (stamina: 3, intellect: 0, agility: -1))),
+                )"#,
+            )
+            .unwrap();
+        assert_eq!(items, vec![item("1")]);
+
+        let items: Vec<Item> = Format::Json
+            .parse(&serde_json::to_string(&[item("1"), item("2")]).unwrap())
+            .unwrap();
+        assert_eq!(items, vec![item("1"), item("2")]);
+
+        assert!(Format::Json.parse::<Item>("{ \"uuid\": \"1\" }").is_err());
+    }
+
+    #[test]
+    fn test_files_round_trip_in_the_format_of_their_extension() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in ["items.json", "items.ron"] {
+            let path = dir.path().join(name);
+
+            write(&path, &vec![item("1"), item("2")]).unwrap();
+
+            assert_eq!(read::<Item>(&path).unwrap(), vec![item("1"), item("2")]);
+        }
+
+        assert!(
+            fs::read_to_string(dir.path().join("items.ron"))
+                .unwrap()
+                .contains("TwoHandedSword")
+        );
+        assert!(read::<Item>(&dir.path().join("missing.json")).is_err());
+    }
+}
@@ -0,0 +1,495 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::{
+    content_file::{self, Format},
+    error::{DatabaseError, Error},
+    model::{Item, LootTable, Npc, NpcSpawn},
+    repository::{Content, Model, Repository},
+    storage::{Query, Storage, memory::MemoryStorage},
+    validation::{Reason, ValidationError},
+};
+
+// game content as files, for version control and for loading into any environment
+// a pack directory holds `items`, `npcs`, `npc_spawns` and `loot_tables`, each as a single
+// `<collection>.json` or `.ron` file, a directory of such files, or both
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentPack {
+    pub items: Vec<Item>,
+    pub npcs: Vec<Npc>,
+    pub npc_spawns: Vec<NpcSpawn>,
+    pub loot_tables: Vec<LootTable>,
+}
+
+// an entry of a pack that seeding would write
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub collection: &'static str,
+    pub key: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added,
+    // the dotted fields that differ from the stored entry
+    Changed(Vec<String>),
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.change {
+            Change::Added => write!(f, "+ {} {}", self.collection, self.key),
+            Change::Changed(fields) => {
+                write!(
+                    f,
+                    "~ {} {}: {}",
+                    self.collection,
+                    self.key,
+                    fields.join(", ")
+                )
+            }
+        }
+    }
+}
+
+// an entry of a pack that seeding would refuse
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rejection {
+    pub collection: &'static str,
+    pub key: String,
+    pub errors: Vec<ValidationError>,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<_> = self.errors.iter().map(ToString::to_string).collect();
+
+        write!(f, "{} {}: {}", self.collection, self.key, errors.join(", "))
+    }
+}
+
+impl ContentPack {
+    pub fn read(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(Error::FileError(format!("{} is not a directory", dir.display())).into());
+        }
+
+        Ok(ContentPack {
+            items: read_collection::<Item>(dir)?,
+            npcs: read_collection::<Npc>(dir)?,
+            npc_spawns: read_collection::<NpcSpawn>(dir)?,
+            loot_tables: read_collection::<LootTable>(dir)?,
+        })
+    }
+
+    // one file per collection, the ones already there are overwritten
+    pub fn write(&self, dir: &Path, format: Format) -> Result<()> {
+        fs::create_dir_all(dir)
+            .map_err(|e| Error::FileError(format!("{}: {}", dir.display(), e)))?;
+
+        write_collection(dir, format, &self.items)?;
+        write_collection(dir, format, &self.npcs)?;
+        write_collection(dir, format, &self.npc_spawns)?;
+        write_collection(dir, format, &self.loot_tables)
+    }
+
+    // everything stored, ordered by key so that exports of the same content are the same files
+    pub async fn export(content: &Content) -> Result<Self> {
+        Ok(ContentPack {
+            items: content.items.list(&by_key::<Item>()).await?,
+            npcs: content.npcs.list(&by_key::<Npc>()).await?,
+            npc_spawns: content.npc_spawns.list(&by_key::<NpcSpawn>()).await?,
+            loot_tables: content.loot_tables.list(&by_key::<LootTable>()).await?,
+        })
+    }
+
+    // seeds a copy of what is stored, so references may point into the pack as well as the database
+    // keys also have to be unique within the pack
+    pub async fn validate(&self, content: &Content) -> Result<Vec<Rejection>> {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let staged = Content::new(storage.clone());
+        staged.create_indexes().await?;
+
+        copy(&content.items, storage.as_ref()).await?;
+        copy(&content.npcs, storage.as_ref()).await?;
+        copy(&content.npc_spawns, storage.as_ref()).await?;
+        copy(&content.loot_tables, storage.as_ref()).await?;
+
+        let mut rejections = Vec::new();
+
+        // referenced content first
+        stage(&staged.items, &self.items, &mut rejections).await?;
+        stage(&staged.npcs, &self.npcs, &mut rejections).await?;
+        stage(&staged.npc_spawns, &self.npc_spawns, &mut rejections).await?;
+        stage(&staged.loot_tables, &self.loot_tables, &mut rejections).await?;
+
+        Ok(rejections)
+    }
+
+    // what seeding would write, fails like `seed` when the pack is invalid
+    pub async fn diff(&self, content: &Content) -> Result<Vec<Difference>> {
+        let rejections = self.validate(content).await?;
+
+        if !rejections.is_empty() {
+            let rejections: Vec<_> = rejections.iter().map(ToString::to_string).collect();
+
+            return Err(Error::InvalidArguments(format!(
+                "The content pack is invalid, nothing was written\n{}",
+                rejections.join("\n")
+            ))
+            .into());
+        }
+
+        let mut differences = Vec::new();
+
+        differences.extend(differences_of(&content.items, &self.items).await?);
+        differences.extend(differences_of(&content.npcs, &self.npcs).await?);
+        differences.extend(differences_of(&content.npc_spawns, &self.npc_spawns).await?);
+        differences.extend(differences_of(&content.loot_tables, &self.loot_tables).await?);
+
+        Ok(differences)
+    }
+
+    // upserts whatever differs, so seeding the same pack again writes nothing
+    // entries stored but missing from the pack are left alone
+    pub async fn seed(&self, content: &Content) -> Result<Vec<Difference>> {
+        let differences = self.diff(content).await?;
+
+        let changed: HashSet<_> = differences
+            .iter()
+            .map(|difference| (difference.collection, difference.key.as_str()))
+            .collect();
+
+        apply(&content.items, &self.items, &changed).await?;
+        apply(&content.npcs, &self.npcs, &changed).await?;
+        apply(&content.npc_spawns, &self.npc_spawns, &changed).await?;
+        apply(&content.loot_tables, &self.loot_tables, &changed).await?;
+
+        Ok(differences)
+    }
+}
+
+fn by_key<T: Model>() -> Query {
+    Query::new().sort_by(T::KEY)
+}
+
+// `<collection>.json`, `<collection>.ron` and the files of `<collection>/`, in name order
+fn read_collection<T: Model>(dir: &Path) -> Result<Vec<T>> {
+    let mut paths: Vec<PathBuf> = ["json", "ron"]
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", T::COLLECTION, extension)))
+        .filter(|path| path.is_file())
+        .collect();
+
+    let nested = dir.join(T::COLLECTION);
+
+    if nested.is_dir() {
+        let mut files = Vec::new();
+
+        for entry in fs::read_dir(&nested)
+            .map_err(|e| Error::FileError(format!("{}: {}", nested.display(), e)))?
+        {
+            let path = entry
+                .map_err(|e| Error::FileError(format!("{}: {}", nested.display(), e)))?
+                .path();
+
+            if path.is_file()
+                && matches!(
+                    path.extension().and_then(|extension| extension.to_str()),
+                    Some("json" | "ron")
+                )
+            {
+                files.push(path);
+            }
+        }
+
+        files.sort();
+        paths.extend(files);
+    }
+
+    let mut entries = Vec::new();
+
+    for path in paths {
+        entries.extend(content_file::read::<T>(&path)?);
+    }
+
+    Ok(entries)
+}
+
+fn write_collection<T: Model>(dir: &Path, format: Format, entries: &[T]) -> Result<()> {
+    content_file::write(
+        &dir.join(format!("{}.{}", T::COLLECTION, format.extension())),
+        entries,
+    )
+}
+
+// as stored, without validating it again
+async fn copy<T: Model>(repository: &Repository<T>, storage: &dyn Storage) -> Result<()> {
+    for entry in repository.list(&Query::new()).await? {
+        storage
+            .insert(T::COLLECTION, serde_json::to_value(&entry)?)
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn stage<T: Model>(
+    staged: &Repository<T>,
+    entries: &[T],
+    rejections: &mut Vec<Rejection>,
+) -> Result<()> {
+    let mut keys = HashSet::new();
+
+    for entry in entries {
+        let mut errors = Vec::new();
+
+        if !keys.insert(entry.key()) {
+            errors.push(ValidationError {
+                field: T::KEY.to_string(),
+                reason: Reason::Taken,
+            });
+        }
+
+        if let Err(e) = staged.upsert(entry).await {
+            match e.downcast::<Error>() {
+                Ok(Error::DatabaseError(DatabaseError::Invalid(invalid))) => errors.extend(invalid),
+                Ok(e) => return Err(e.into()),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if !errors.is_empty() {
+            rejections.push(Rejection {
+                collection: T::COLLECTION,
+                key: entry.key().to_string(),
+                errors,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+async fn differences_of<T: Model>(
+    repository: &Repository<T>,
+    entries: &[T],
+) -> Result<Vec<Difference>> {
+    let mut differences = Vec::new();
+
+    for entry in entries {
+        let change = match repository.get(entry.key()).await? {
+            None => Change::Added,
+            Some(stored) => {
+                let mut fields = Vec::new();
+                changed_fields(
+                    "",
+                    &serde_json::to_value(&stored)?,
+                    &serde_json::to_value(entry)?,
+                    &mut fields,
+                );
+
+                if fields.is_empty() {
+                    continue;
+                }
+
+                Change::Changed(fields)
+            }
+        };
+
+        differences.push(Difference {
+            collection: T::COLLECTION,
+            key: entry.key().to_string(),
+            change,
+        });
+    }
+
+    Ok(differences)
+}
+
+// objects are compared field by field, anything else as a whole
+fn changed_fields(prefix: &str, stored: &Value, seeded: &Value, fields: &mut Vec<String>) {
+    match (stored, seeded) {
+        (Value::Object(stored), Value::Object(seeded)) => {
+            let mut names: Vec<_> = stored.keys().chain(seeded.keys()).collect();
+            names.sort();
+            names.dedup();
+
+            for name in names {
+                changed_fields(
+                    &format!("{}{}.", prefix, name),
+                    stored.get(name).unwrap_or(&Value::Null),
+                    seeded.get(name).unwrap_or(&Value::Null),
+                    fields,
+                );
+            }
+        }
+        (stored, seeded) if stored != seeded => {
+            fields.push(prefix.trim_end_matches('.').to_string());
+        }
+        _ => {}
+    }
+}
+
+async fn apply<T: Model>(
+    repository: &Repository<T>,
+    entries: &[T],
+    changed: &HashSet<(&str, &str)>,
+) -> Result<()> {
+    for entry in entries {
+        if changed.contains(&(T::COLLECTION, entry.key())) {
+            repository.upsert(entry).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{Entity, EquipableItem, ItemType, LootEntry, Stats, Vec3d};
+
+    use super::*;
+
+    fn item(uuid: &str, agility: isize) -> Item {
+        Item {
+            uuid: uuid.to_string(),
+            name: "Sword".to_string(),
+            item_type: ItemType::OneHandedSword(EquipableItem {
+                stats: Stats {
+                    stamina: 0,
+                    intellect: 0,
+                    agility,
+                },
+            }),
+        }
+    }
+
+    fn npc(uuid: &str) -> Npc {
+        Npc {
+            uuid: uuid.to_string(),
+            entity: Entity {
+                uuid: uuid.to_string(),
+                name: "Hogger".to_string(),
+            },
+        }
+    }
+
+    fn pack() -> ContentPack {
+        ContentPack {
+            items: vec![item("sword", 1)],
+            npcs: vec![npc("hogger")],
+            npc_spawns: vec![NpcSpawn {
+                uuid: "spawn".to_string(),
+                npc: npc("hogger"),
+                location: Vec3d::default(),
+            }],
+            loot_tables: vec![LootTable {
+                npc_uuid: "hogger".to_string(),
+                entries: vec![LootEntry {
+                    chance: 0.5,
+                    item: item("sword", 1),
+                }],
+            }],
+        }
+    }
+
+    fn content() -> Content {
+        Content::new(Arc::new(MemoryStorage::new()))
+    }
+
+    #[tokio::test]
+    async fn test_seeding_is_idempotent() {
+        let content = content();
+
+        let seeded = pack().seed(&content).await.unwrap();
+        assert_eq!(seeded.len(), 4);
+        assert!(
+            seeded
+                .iter()
+                .all(|difference| difference.change == Change::Added)
+        );
+
+        assert!(pack().seed(&content).await.unwrap().is_empty());
+        assert_eq!(ContentPack::export(&content).await.unwrap(), pack());
+    }
+
+    #[tokio::test]
+    async fn test_diffs_name_the_changed_fields() {
+        let content = content();
+        pack().seed(&content).await.unwrap();
+
+        let mut changed = pack();
+        changed.items[0] = item("sword", 2);
+        changed.items.push(item("axe", 1));
+
+        let differences = changed.diff(&content).await.unwrap();
+        assert_eq!(
+            differences
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "~ items sword: item_type.OneHandedSword.stats.agility",
+                "+ items axe"
+            ]
+        );
+
+        // diffing writes nothing
+        assert_eq!(ContentPack::export(&content).await.unwrap(), pack());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_packs_are_not_seeded() {
+        let content = content();
+
+        let mut invalid = pack();
+        invalid.npcs.clear();
+        invalid.items.push(item("sword", 2));
+
+        let rejections = invalid.validate(&content).await.unwrap();
+        assert_eq!(
+            rejections
+                .iter()
+                .map(|rejection| (rejection.collection, rejection.key.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("items", "sword"),
+                ("npc_spawns", "spawn"),
+                ("loot_tables", "hogger")
+            ]
+        );
+
+        assert!(invalid.seed(&content).await.is_err());
+        assert_eq!(
+            ContentPack::export(&content).await.unwrap(),
+            ContentPack::default()
+        );
+    }
+
+    #[test]
+    fn test_packs_are_read_from_files_and_directories() {
+        let dir = tempfile::tempdir().unwrap();
+
+        pack().write(dir.path(), Format::Ron).unwrap();
+        assert_eq!(ContentPack::read(dir.path()).unwrap(), pack());
+
+        fs::create_dir(dir.path().join("items")).unwrap();
+        content_file::write(&dir.path().join("items/axes.json"), &item("axe", 1)).unwrap();
+
+        assert_eq!(
+            ContentPack::read(dir.path()).unwrap().items,
+            vec![item("sword", 1), item("axe", 1)]
+        );
+        assert!(ContentPack::read(&dir.path().join("missing")).is_err());
+    }
+}
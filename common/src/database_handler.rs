@@ -1,121 +1,217 @@
 use anyhow::Result;
-use mongodb::bson::Document;
-use mongodb::bson::{doc, from_document, to_document};
-use mongodb::options::{ClientOptions, ServerApi, ServerApiVersion};
-use mongodb::results::{DeleteResult, InsertOneResult};
-use mongodb::{Client, Collection, Database};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use crate::error::{DatabaseError, Error};
+use crate::repository::Model;
+use crate::storage::{self, Filter, Query, Storage};
 use crate::validation::Validateable;
 
+// a collection of whichever `Storage` backend the uri picked
+#[derive(Clone)]
 pub struct DatabaseHandler {
-    client: Client,
-    db: Option<Database>,
-    collection: Option<Collection<Document>>,
+    storage: Arc<dyn Storage>,
+    collection: Option<String>,
 }
 
 impl DatabaseHandler {
-    pub async fn new(uri: &str) -> Result<Self> {
-        let mut client_options = ClientOptions::parse(uri).await?;
-
-        let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
-
-        client_options.server_api = Some(server_api);
-
-        let client = Client::with_options(client_options)?;
+    pub async fn connect(uri: &str, db_name: &str) -> Result<Self> {
+        Ok(Self::with_storage(storage::open(uri, db_name).await?))
+    }
 
-        Ok(DatabaseHandler {
-            client,
-            db: None,
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Self {
+        DatabaseHandler {
+            storage,
             collection: None,
-        })
+        }
     }
 
-    pub async fn connect_database(mut self, db_name: &str) -> Self {
-        let db = self.client.database(db_name);
-
-        self.db = Some(db);
-
-        self
+    // handlers of the same database share its connection
+    pub fn connect_collection(&self, collection_name: &str) -> DatabaseHandler {
+        DatabaseHandler {
+            storage: self.storage.clone(),
+            collection: Some(collection_name.to_string()),
+        }
     }
 
-    pub fn connect_collection(mut self, collection_name: &str) -> Result<DatabaseHandler> {
-        let collection = self
-            .db
-            .as_ref()
-            .ok_or("Database is not initialised")
-            .map_err(|e| Error::DatabaseError(DatabaseError::Generic(e.to_string())))?
-            .collection::<Document>(collection_name);
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
 
-        self.collection = Some(collection);
+    fn collection(&self) -> Result<&str, Error> {
+        self.collection.as_deref().ok_or_else(|| {
+            Error::DatabaseError(DatabaseError::Generic(
+                "Collection is not initialised".to_string(),
+            ))
+        })
+    }
 
-        Ok(self)
+    async fn validate<T: Validateable>(&self, item: &T) -> Result<()> {
+        item.validate(self.storage.as_ref())
+            .await
+            .map_err(|errors| Error::DatabaseError(DatabaseError::Invalid(errors)).into())
     }
 
-    pub async fn add<T: Serialize + Validateable<T> + Debug>(
-        &self,
-        item: T,
-    ) -> Result<InsertOneResult> {
-        if item.validate_add(self).await {
-            return Err(Error::DatabaseError(DatabaseError::Generic(
-                "Validation failed".to_string(),
-            ))
-            .into());
+    // fails with `DatabaseError::ExistingItem` when the key is taken
+    // and `DatabaseError::Invalid` when validation fails, as does `replace`
+    pub async fn add<T: Model + Debug>(&self, item: T) -> Result<()> {
+        self.validate(&item).await?;
+
+        let query = Query::new().filter(Filter::by(T::KEY, item.key())).limit(1);
+
+        if !self
+            .storage
+            .find(self.collection()?, &query)
+            .await?
+            .is_empty()
+        {
+            return Err(
+                Error::DatabaseError(DatabaseError::ExistingItem(item.key().to_string())).into(),
+            );
         }
 
-        let collection = self.collection.as_ref().ok_or_else(|| {
-            Error::DatabaseError(DatabaseError::Generic(
-                "Collection is not initialised".to_string(),
-            ))
-        })?;
+        self.storage
+            .insert(self.collection()?, serde_json::to_value(&item)?)
+            .await
+    }
 
-        Ok(collection
-            .insert_one(to_document(&item).unwrap(), None)
-            .await?)
+    // false when there was nothing to remove
+    pub async fn remove(&self, name: &str) -> Result<bool> {
+        self.storage
+            .remove(self.collection()?, &Filter::by("name", name))
+            .await
     }
 
-    pub async fn remove(&self, name: &str) -> Result<DeleteResult, Error> {
-        let filter = doc! { "name": name };
+    pub async fn replace<T: Model + Debug>(&self, name: &str, item: T) -> Result<bool> {
+        self.validate(&item).await?;
 
-        let collection = self.collection.as_ref().ok_or_else(|| {
-            Error::DatabaseError(DatabaseError::Generic(
-                "Collection is not initialised".to_string(),
-            ))
-        })?;
+        self.storage
+            .replace(
+                self.collection()?,
+                &Filter::by("name", name),
+                serde_json::to_value(&item)?,
+            )
+            .await
+    }
 
-        collection
-            .delete_one(filter, None)
+    // overwrites only the given fields of the item whose `field` equals `value`
+    pub async fn set_by<T: Serialize>(&self, field: &str, value: &str, fields: T) -> Result<bool> {
+        self.storage
+            .set(
+                self.collection()?,
+                &Filter::by(field, value),
+                serde_json::to_value(&fields)?,
+            )
             .await
-            .map_err(|e| Error::DatabaseError(DatabaseError::Generic(e.to_string())))
     }
 
     pub async fn get<T: Serialize + for<'a> Deserialize<'a>>(
         &self,
         name: &str,
     ) -> Result<Option<T>> {
-        let filter = doc! { "name": name };
+        Ok(self.find_by::<T>("name", name).await?.into_iter().next())
+    }
 
-        let collection = self.collection.as_ref().ok_or_else(|| {
-            Error::DatabaseError(DatabaseError::Generic(
-                "Collection is not initialised".to_string(),
-            ))
-        })?;
+    pub async fn find_by<T: Serialize + for<'a> Deserialize<'a>>(
+        &self,
+        field: &str,
+        value: &str,
+    ) -> Result<Vec<T>> {
+        let documents = self
+            .storage
+            .find(self.collection()?, &Filter::by(field, value).into())
+            .await?;
+
+        Ok(documents
+            .into_iter()
+            .map(serde_json::from_value::<T>)
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        account_store::AccountStore,
+        model::{Account, AccountStatus},
+        storage::memory::MemoryStorage,
+    };
 
-        let doc = collection.find_one(filter, None).await?;
+    use super::*;
 
-        match doc {
-            None => Ok(None),
-            Some(doc) => {
-                let doc = from_document::<T>(doc).unwrap();
+    fn accounts() -> DatabaseHandler {
+        DatabaseHandler::with_storage(Arc::new(MemoryStorage::new())).connect_collection("accounts")
+    }
 
-                Ok(Some(doc))
-            }
+    fn account(name: &str) -> Account {
+        Account {
+            name: name.to_string(),
+            password_hash: "hash".to_string(),
+            status: AccountStatus::Active,
         }
     }
-}
 
-pub struct ItemDatabaseHandler {
-    dh: DatabaseHandler,
+    #[tokio::test]
+    async fn test_items_are_added_once() {
+        let accounts = accounts();
+
+        accounts.insert(account("player")).await.unwrap();
+
+        let error = accounts.insert(account("player")).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::DatabaseError(e)) if e.is_taken()
+        ));
+
+        assert_eq!(
+            accounts.find("player").await.unwrap().unwrap().name,
+            "player"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_items_are_replaced_and_removed_by_name() {
+        let accounts = accounts();
+
+        accounts.add(account("player")).await.unwrap();
+
+        let mut banned = account("player");
+        banned.status = AccountStatus::Banned;
+        assert!(accounts.replace("player", banned).await.unwrap());
+        assert_eq!(
+            accounts
+                .get::<Account>("player")
+                .await
+                .unwrap()
+                .unwrap()
+                .status,
+            AccountStatus::Banned
+        );
+
+        assert!(accounts.remove("player").await.unwrap());
+        assert!(!accounts.remove("player").await.unwrap());
+        assert!(accounts.get::<Account>("player").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_collections_are_separate() {
+        let database = DatabaseHandler::with_storage(Arc::new(MemoryStorage::new()));
+
+        database
+            .connect_collection("accounts")
+            .add(account("player"))
+            .await
+            .unwrap();
+
+        assert!(
+            database
+                .connect_collection("characters")
+                .get::<Account>("player")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(database.get::<Account>("player").await.is_err());
+    }
 }
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::validation::{Reason, ValidationError};
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Invalid arguments: {0}")]
@@ -22,4 +24,27 @@ pub enum DatabaseError {
     Generic(String),
     #[error("Existing database item: {0}")]
     ExistingItem(String),
+    #[error("Invalid database item: {}", join(.0))]
+    Invalid(Vec<ValidationError>),
+}
+
+impl DatabaseError {
+    // the item, or one of its unique fields, is already stored
+    pub fn is_taken(&self) -> bool {
+        match self {
+            DatabaseError::ExistingItem(_) => true,
+            DatabaseError::Invalid(errors) => {
+                errors.iter().any(|error| error.reason == Reason::Taken)
+            }
+            DatabaseError::Generic(_) => false,
+        }
+    }
+}
+
+fn join(errors: &[ValidationError]) -> String {
+    errors
+        .iter()
+        .map(ValidationError::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
@@ -1,5 +1,11 @@
+pub mod account_store;
+pub mod character_store;
 pub mod config;
+pub mod content_file;
+pub mod content_pack;
 pub mod database_handler;
 pub mod error;
 pub mod model;
+pub mod repository;
+pub mod storage;
 pub mod validation;
@@ -1,72 +1,156 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 // these are used as dbos for now
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Vec3d {
     pub x: f64,
     pub y: f64,
     pub z: f64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ItemType {
     OneHandedSword(EquipableItem),
     TwoHandedSword(EquipableItem),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct EquipableItem {
     pub stats: Stats,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Stats {
     pub stamina: isize,
     pub intellect: isize,
     pub agility: isize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Item {
     pub uuid: String,
     pub name: String,
     pub item_type: ItemType,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ItemInstance {
     pub uuid: String,
     pub item: Item,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Entity {
     pub uuid: String,
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Npc {
     pub uuid: String,
     pub entity: Entity,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct NpcSpawn {
     pub uuid: String,
     pub npc: Npc,
     pub location: Vec3d,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LootTable {
     pub npc_uuid: String,
     pub entries: Vec<LootEntry>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LootEntry {
     pub chance: f64,
     pub item: Item,
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Banned,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Account {
+    pub name: String,
+    // argon2 PHC string, salt and parameters included
+    pub password_hash: String,
+    pub status: AccountStatus,
+}
+
+// also part of the wire protocol, clients pick them when creating a character
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum CharacterClass {
+    Warrior,
+    Mage,
+    Rogue,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, JsonSchema)]
+pub struct Appearance {
+    pub body: u8,
+    pub skin: u8,
+    pub hair: u8,
+}
+
+// how a character moves, it always enters the world standing still
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Movement {
+    pub velocity: f64,
+    pub direction: Vec3d,
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Movement {
+            velocity: 1.0,
+            direction: Vec3d::default(),
+        }
+    }
+}
+
+// the part of a character that changes while it is in the world, its fields mirror `Character`'s
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CharacterState {
+    pub position: Vec3d,
+    pub movement: Movement,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Character {
+    pub uuid: String,
+    pub name: String,
+    // `name` as compared for uniqueness, see `Character::normalize_name`
+    pub name_key: String,
+    // name of the owning account
+    pub account: String,
+    pub class: CharacterClass,
+    pub appearance: Appearance,
+    pub level: u32,
+    // where the character was when it last left the world
+    pub position: Vec3d,
+    #[serde(default)]
+    pub movement: Movement,
+    pub zone: String,
+}
+
+impl Character {
+    // "Arthas" and "arthas" are the same name
+    pub fn normalize_name(name: &str) -> String {
+        name.to_lowercase()
+    }
+
+    pub fn apply(&mut self, state: CharacterState) {
+        self.position = state.position;
+        self.movement = state.movement;
+    }
+}
@@ -0,0 +1,484 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{
+    error::{DatabaseError, Error},
+    model::{Account, Character, Item, ItemInstance, LootTable, Npc, NpcSpawn},
+    storage::{Filter, Index, Query, Storage, Written},
+    validation::Validateable,
+};
+
+// a type stored in its own collection
+pub trait Model: Validateable + Serialize + DeserializeOwned + Send + Sync {
+    const COLLECTION: &'static str;
+    // the unique field the model is looked up, updated and removed by
+    const KEY: &'static str = "uuid";
+    // where the name is, for the models that have one
+    const NAME: Option<&'static str> = None;
+
+    fn key(&self) -> &str;
+
+    fn indexes() -> Vec<Index> {
+        let mut indexes = vec![Index::unique(Self::KEY)];
+        indexes.extend(Self::NAME.map(Index::on));
+        indexes
+    }
+}
+
+impl Model for Item {
+    const COLLECTION: &'static str = "items";
+    const NAME: Option<&'static str> = Some("name");
+
+    fn key(&self) -> &str {
+        &self.uuid
+    }
+}
+
+impl Model for ItemInstance {
+    const COLLECTION: &'static str = "item_instances";
+
+    fn key(&self) -> &str {
+        &self.uuid
+    }
+}
+
+impl Model for Npc {
+    const COLLECTION: &'static str = "npcs";
+    const NAME: Option<&'static str> = Some("entity.name");
+
+    fn key(&self) -> &str {
+        &self.uuid
+    }
+}
+
+impl Model for NpcSpawn {
+    const COLLECTION: &'static str = "npc_spawns";
+
+    fn key(&self) -> &str {
+        &self.uuid
+    }
+}
+
+// one table per npc
+impl Model for LootTable {
+    const COLLECTION: &'static str = "loot_tables";
+    const KEY: &'static str = "npc_uuid";
+
+    fn key(&self) -> &str {
+        &self.npc_uuid
+    }
+}
+
+impl Model for Account {
+    const COLLECTION: &'static str = "accounts";
+    const KEY: &'static str = "name";
+
+    fn key(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Model for Character {
+    const COLLECTION: &'static str = "characters";
+    const NAME: Option<&'static str> = Some("name");
+
+    fn key(&self) -> &str {
+        &self.uuid
+    }
+
+    // names are unique regardless of case, whichever of two concurrent creations writes first wins
+    fn indexes() -> Vec<Index> {
+        vec![
+            Index::unique(Self::KEY),
+            Index::on("name"),
+            Index::unique("name_key"),
+        ]
+    }
+}
+
+pub struct Repository<T> {
+    storage: Arc<dyn Storage>,
+    model: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Repository<T> {
+    fn clone(&self) -> Self {
+        Repository {
+            storage: self.storage.clone(),
+            model: PhantomData,
+        }
+    }
+}
+
+impl<T: Model> Repository<T> {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Repository {
+            storage,
+            model: PhantomData,
+        }
+    }
+
+    pub async fn create_indexes(&self) -> Result<()> {
+        for index in T::indexes() {
+            self.storage.create_index(T::COLLECTION, &index).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<T>> {
+        Ok(self
+            .list(&Query::new().filter(Filter::by(T::KEY, key)).limit(1))
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    // names are not unique, all that share it are returned
+    pub async fn find_by_name(&self, name: &str) -> Result<Vec<T>> {
+        let field = T::NAME.ok_or_else(|| {
+            Error::DatabaseError(DatabaseError::Generic(format!(
+                "{} have no name",
+                T::COLLECTION
+            )))
+        })?;
+
+        self.list(&Filter::by(field, name).into()).await
+    }
+
+    pub async fn list(&self, query: &Query) -> Result<Vec<T>> {
+        self.storage
+            .find(T::COLLECTION, query)
+            .await?
+            .into_iter()
+            .map(|document| Ok(serde_json::from_value(document)?))
+            .collect()
+    }
+
+    // fails with `DatabaseError::ExistingItem` when the key is taken
+    // and `DatabaseError::Invalid` when validation fails, as do updates
+    pub async fn insert(&self, item: &T) -> Result<()> {
+        self.validate(item).await?;
+
+        if self.get(item.key()).await?.is_some() {
+            return Err(
+                Error::DatabaseError(DatabaseError::ExistingItem(item.key().to_string())).into(),
+            );
+        }
+
+        self.storage
+            .insert(T::COLLECTION, serde_json::to_value(item)?)
+            .await
+    }
+
+    // false when there is nothing stored under the key
+    pub async fn update(&self, item: &T) -> Result<bool> {
+        self.validate(item).await?;
+
+        self.storage
+            .replace(
+                T::COLLECTION,
+                &Filter::by(T::KEY, item.key()),
+                serde_json::to_value(item)?,
+            )
+            .await
+    }
+
+    pub async fn upsert(&self, item: &T) -> Result<Written> {
+        self.validate(item).await?;
+
+        self.storage
+            .upsert(
+                T::COLLECTION,
+                &Filter::by(T::KEY, item.key()),
+                serde_json::to_value(item)?,
+            )
+            .await
+    }
+
+    pub async fn remove(&self, key: &str) -> Result<bool> {
+        self.storage
+            .remove(T::COLLECTION, &Filter::by(T::KEY, key))
+            .await
+    }
+
+    // what every write checks first, on its own for dry runs
+    pub async fn validate(&self, item: &T) -> Result<()> {
+        item.validate(self.storage.as_ref())
+            .await
+            .map_err(|errors| Error::DatabaseError(DatabaseError::Invalid(errors)).into())
+    }
+}
+
+// the repositories of all game content, sharing one storage
+#[derive(Clone)]
+pub struct Content {
+    pub items: Repository<Item>,
+    pub item_instances: Repository<ItemInstance>,
+    pub npcs: Repository<Npc>,
+    pub npc_spawns: Repository<NpcSpawn>,
+    pub loot_tables: Repository<LootTable>,
+}
+
+impl Content {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Content {
+            items: Repository::new(storage.clone()),
+            item_instances: Repository::new(storage.clone()),
+            npcs: Repository::new(storage.clone()),
+            npc_spawns: Repository::new(storage.clone()),
+            loot_tables: Repository::new(storage),
+        }
+    }
+
+    // run at startup, existing indexes are left as they are
+    pub async fn create_indexes(&self) -> Result<()> {
+        self.items.create_indexes().await?;
+        self.item_instances.create_indexes().await?;
+        self.npcs.create_indexes().await?;
+        self.npc_spawns.create_indexes().await?;
+        self.loot_tables.create_indexes().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        model::{
+            AccountStatus, Appearance, CharacterClass, Entity, EquipableItem, ItemType, LootEntry,
+            Movement, Stats, Vec3d,
+        },
+        storage::memory::MemoryStorage,
+    };
+
+    use super::*;
+
+    fn content() -> Content {
+        Content::new(Arc::new(MemoryStorage::new()))
+    }
+
+    fn item(uuid: &str, name: &str) -> Item {
+        Item {
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            item_type: ItemType::OneHandedSword(EquipableItem {
+                stats: Stats {
+                    stamina: 1,
+                    intellect: 0,
+                    agility: 2,
+                },
+            }),
+        }
+    }
+
+    fn npc(uuid: &str, name: &str) -> Npc {
+        Npc {
+            uuid: uuid.to_string(),
+            entity: Entity {
+                uuid: uuid.to_string(),
+                name: name.to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_items_are_found_by_uuid_and_name() {
+        let content = content();
+        content.create_indexes().await.unwrap();
+
+        content.items.insert(&item("1", "Sword")).await.unwrap();
+        content.items.insert(&item("2", "Sword")).await.unwrap();
+
+        assert!(matches!(
+            content
+                .items
+                .insert(&item("1", "Shield"))
+                .await
+                .unwrap_err()
+                .downcast_ref::<Error>(),
+            Some(Error::DatabaseError(DatabaseError::ExistingItem(_)))
+        ));
+
+        assert_eq!(
+            content.items.get("1").await.unwrap(),
+            Some(item("1", "Sword"))
+        );
+        assert_eq!(content.items.find_by_name("Sword").await.unwrap().len(), 2);
+        assert!(content.items.get("3").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_nested_names_are_found() {
+        let content = content();
+
+        content.npcs.insert(&npc("1", "Hogger")).await.unwrap();
+
+        assert_eq!(
+            content.npcs.find_by_name("Hogger").await.unwrap(),
+            vec![npc("1", "Hogger")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_models_without_names_are_keyed_by_their_own_field() {
+        let content = content();
+        content.npcs.insert(&npc("1", "Hogger")).await.unwrap();
+        content.items.insert(&item("1", "Sword")).await.unwrap();
+
+        let table = LootTable {
+            npc_uuid: "1".to_string(),
+            entries: vec![LootEntry {
+                chance: 0.5,
+                item: item("1", "Sword"),
+            }],
+        };
+
+        assert_eq!(
+            content.loot_tables.upsert(&table).await.unwrap(),
+            Written::Inserted
+        );
+        assert_eq!(content.loot_tables.get("1").await.unwrap(), Some(table));
+        assert!(content.loot_tables.find_by_name("Hogger").await.is_err());
+
+        assert!(content.loot_tables.remove("1").await.unwrap());
+        assert!(!content.loot_tables.remove("1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_updates_and_upserts_replace_by_key() {
+        let content = content();
+
+        assert!(!content.items.update(&item("1", "Sword")).await.unwrap());
+
+        content.items.insert(&item("1", "Sword")).await.unwrap();
+        assert!(content.items.update(&item("1", "Blade")).await.unwrap());
+        assert_eq!(
+            content.items.upsert(&item("1", "Edge")).await.unwrap(),
+            Written::Replaced
+        );
+
+        assert_eq!(
+            content.items.list(&Query::new()).await.unwrap(),
+            vec![item("1", "Edge")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_items_are_not_written() {
+        let content = content();
+        content.items.insert(&item("1", "Sword")).await.unwrap();
+
+        let error = content.items.insert(&item("2", "")).await.unwrap_err();
+        let Some(Error::DatabaseError(DatabaseError::Invalid(errors))) = error.downcast_ref()
+        else {
+            panic!("expected validation to fail, got {}", error);
+        };
+        assert_eq!(errors[0].field, "name");
+
+        assert!(content.items.update(&item("1", " ")).await.is_err());
+        assert!(content.items.upsert(&item("1", " ")).await.is_err());
+        assert_eq!(
+            content.items.list(&Query::new()).await.unwrap(),
+            vec![item("1", "Sword")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_account_names_stay_unique_past_validation() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        Repository::<Account>::new(storage.clone())
+            .create_indexes()
+            .await
+            .unwrap();
+
+        let account = Account {
+            name: "player".to_string(),
+            password_hash: "hash".to_string(),
+            status: AccountStatus::Active,
+        };
+
+        // as if a second registration had passed its checks before the first was written
+        storage
+            .insert(Account::COLLECTION, serde_json::to_value(&account).unwrap())
+            .await
+            .unwrap();
+        assert!(
+            storage
+                .insert(Account::COLLECTION, serde_json::to_value(&account).unwrap())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_character_names_stay_unique_regardless_of_case() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::new());
+        let characters = Repository::<Character>::new(storage.clone());
+        characters.create_indexes().await.unwrap();
+
+        let character = |uuid: &str, name: &str| Character {
+            uuid: uuid.to_string(),
+            name: name.to_string(),
+            name_key: Character::normalize_name(name),
+            account: "player".to_string(),
+            class: CharacterClass::Warrior,
+            appearance: Appearance::default(),
+            level: 1,
+            position: Vec3d::default(),
+            movement: Movement::default(),
+            zone: "start".to_string(),
+        };
+
+        characters.insert(&character("1", "Arthas")).await.unwrap();
+
+        let error = characters
+            .insert(&character("2", "arthas"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::DatabaseError(e)) if e.is_taken()
+        ));
+
+        // a creation that raced past validation is stopped by the index
+        let raced = serde_json::to_value(character("3", "ARTHAS")).unwrap();
+        let error = storage
+            .insert(Character::COLLECTION, raced)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::DatabaseError(e)) if e.is_taken()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_lists_are_filtered_and_paged() {
+        let content = content();
+
+        for (uuid, name) in [
+            ("3", "Sword"),
+            ("1", "Sword"),
+            ("2", "Shield"),
+            ("4", "Sword"),
+        ] {
+            content.items.insert(&item(uuid, name)).await.unwrap();
+        }
+
+        let page = content
+            .items
+            .list(
+                &Query::new()
+                    .filter(Filter::by("name", "Sword"))
+                    .sort_by("uuid")
+                    .skip(1)
+                    .limit(2),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(page, vec![item("3", "Sword"), item("4", "Sword")]);
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use sled::{Db, IVec, Tree};
+
+use super::{Filter, Index, Query, Storage, Written, lookup, not_an_object, taken};
+
+// a sled database in a local directory, one tree per collection, for single box deployments
+// filters on an indexed field go through the index, anything else scans the whole tree
+pub struct SledStorage {
+    db: Db,
+    // the lock keeps a write from sneaking in between another's unique check and its write
+    // indexes are created again on every start, so they only live here
+    indexes: Arc<Mutex<HashMap<String, Vec<Indexed>>>>,
+}
+
+// the sled ids of the documents holding each value of the field
+struct Indexed {
+    index: Index,
+    ids: HashMap<String, BTreeSet<IVec>>,
+}
+
+impl Indexed {
+    fn add(&mut self, id: &IVec, document: &Value) {
+        if let Some(value) = lookup(document, &self.index.field) {
+            self.ids
+                .entry(value.to_string())
+                .or_default()
+                .insert(id.clone());
+        }
+    }
+
+    fn forget(&mut self, id: &IVec, document: &Value) {
+        let Some(value) = lookup(document, &self.index.field).map(Value::to_string) else {
+            return;
+        };
+
+        if let Some(ids) = self.ids.get_mut(&value) {
+            ids.remove(id);
+
+            if ids.is_empty() {
+                self.ids.remove(&value);
+            }
+        }
+    }
+}
+
+impl SledStorage {
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        Ok(SledStorage {
+            db: sled::open(dir)?,
+            indexes: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    // sled blocks on its io, so the work runs off the runtime with the indexes of `collection` locked
+    async fn blocking<R: Send + 'static>(
+        &self,
+        collection: &str,
+        work: impl FnOnce(&Db, &Tree, &mut Vec<Indexed>) -> Result<R> + Send + 'static,
+    ) -> Result<(R, Tree)> {
+        let db = self.db.clone();
+        let indexes = self.indexes.clone();
+        let collection = collection.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let tree = db.open_tree(&collection)?;
+            let mut indexes = indexes.lock().unwrap();
+            let result = work(&db, &tree, indexes.entry(collection).or_default())?;
+
+            Ok((result, tree))
+        })
+        .await?
+    }
+
+    // like `blocking`, and waits for the write to reach the disk
+    async fn writing<R: Send + 'static>(
+        &self,
+        collection: &str,
+        work: impl FnOnce(&Db, &Tree, &mut Vec<Indexed>) -> Result<R> + Send + 'static,
+    ) -> Result<R> {
+        let (result, tree) = self.blocking(collection, work).await?;
+
+        tree.flush_async().await?;
+        Ok(result)
+    }
+
+    fn decode(bytes: &IVec) -> Result<Value> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    fn documents(tree: &Tree) -> impl Iterator<Item = Result<(IVec, Value)>> + '_ {
+        tree.iter().map(|entry| {
+            let (id, bytes) = entry?;
+            Ok((id, Self::decode(&bytes)?))
+        })
+    }
+
+    // the ids an index on one of the filtered fields holds for its value, none when no field is indexed
+    fn candidates(indexed: &[Indexed], filter: &Filter) -> Option<Vec<IVec>> {
+        filter.fields().iter().find_map(|(field, value)| {
+            let indexed = indexed
+                .iter()
+                .find(|indexed| &indexed.index.field == field)?;
+
+            Some(
+                indexed
+                    .ids
+                    .get(&value.to_string())
+                    .map(|ids| ids.iter().cloned().collect())
+                    .unwrap_or_default(),
+            )
+        })
+    }
+
+    fn matching(tree: &Tree, indexed: &[Indexed], filter: &Filter) -> Result<Vec<(IVec, Value)>> {
+        let Some(ids) = Self::candidates(indexed, filter) else {
+            return Self::documents(tree)
+                .filter(|entry| {
+                    entry
+                        .as_ref()
+                        .map_or(true, |(_, document)| filter.matches(document))
+                })
+                .collect();
+        };
+
+        let mut matching = Vec::new();
+
+        for id in ids {
+            if let Some(bytes) = tree.get(&id)? {
+                let document = Self::decode(&bytes)?;
+
+                if filter.matches(&document) {
+                    matching.push((id, document));
+                }
+            }
+        }
+
+        Ok(matching)
+    }
+
+    fn first(tree: &Tree, indexed: &[Indexed], filter: &Filter) -> Result<Option<(IVec, Value)>> {
+        if Self::candidates(indexed, filter).is_some() {
+            return Ok(Self::matching(tree, indexed, filter)?.into_iter().next());
+        }
+
+        for entry in Self::documents(tree) {
+            let (id, document) = entry?;
+
+            if filter.matches(&document) {
+                return Ok(Some((id, document)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // writes `document` over `existing`, or under a new id when there is none
+    fn write(
+        db: &Db,
+        tree: &Tree,
+        indexed: &mut [Indexed],
+        existing: Option<(IVec, Value)>,
+        document: &Value,
+    ) -> Result<()> {
+        let own = existing.as_ref().map(|(id, _)| id);
+
+        for indexed in indexed.iter().filter(|indexed| indexed.index.unique) {
+            let Some(value) = lookup(document, &indexed.index.field) else {
+                continue;
+            };
+
+            if indexed
+                .ids
+                .get(&value.to_string())
+                .is_some_and(|ids| ids.iter().any(|id| Some(id) != own))
+            {
+                return Err(taken(&indexed.index, value));
+            }
+        }
+
+        let id = match own {
+            Some(id) => id.clone(),
+            None => IVec::from(&db.generate_id()?.to_be_bytes()),
+        };
+
+        tree.insert(&id, serde_json::to_vec(document)?)?;
+
+        for indexed in indexed.iter_mut() {
+            if let Some((_, existing)) = &existing {
+                indexed.forget(&id, existing);
+            }
+
+            indexed.add(&id, document);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for SledStorage {
+    async fn insert(&self, collection: &str, document: Value) -> Result<()> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        self.writing(collection, move |db, tree, indexed| {
+            Self::write(db, tree, indexed, None, &document)
+        })
+        .await
+    }
+
+    async fn find(&self, collection: &str, query: &Query) -> Result<Vec<Value>> {
+        let filter = query.filter.clone();
+
+        let (matching, _) = self
+            .blocking(collection, move |_, tree, indexed| {
+                Self::matching(tree, indexed, &filter)
+            })
+            .await?;
+
+        Ok(query.page(matching.into_iter().map(|(_, document)| document).collect()))
+    }
+
+    async fn replace(&self, collection: &str, filter: &Filter, document: Value) -> Result<bool> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        let filter = filter.clone();
+
+        self.writing(collection, move |db, tree, indexed| {
+            match Self::first(tree, indexed, &filter)? {
+                Some(existing) => {
+                    Self::write(db, tree, indexed, Some(existing), &document)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+        .await
+    }
+
+    async fn upsert(&self, collection: &str, filter: &Filter, document: Value) -> Result<Written> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        let filter = filter.clone();
+
+        self.writing(collection, move |db, tree, indexed| {
+            let existing = Self::first(tree, indexed, &filter)?;
+            let written = match existing {
+                Some(_) => Written::Replaced,
+                None => Written::Inserted,
+            };
+
+            Self::write(db, tree, indexed, existing, &document)?;
+            Ok(written)
+        })
+        .await
+    }
+
+    async fn set(&self, collection: &str, filter: &Filter, fields: Value) -> Result<bool> {
+        let Value::Object(fields) = fields else {
+            return Err(not_an_object());
+        };
+
+        let filter = filter.clone();
+
+        self.writing(collection, move |db, tree, indexed| {
+            let Some((id, existing)) = Self::first(tree, indexed, &filter)? else {
+                return Ok(false);
+            };
+
+            let mut document = existing.clone();
+
+            if let Some(document) = document.as_object_mut() {
+                document.extend(fields);
+            }
+
+            Self::write(db, tree, indexed, Some((id, existing)), &document)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn remove(&self, collection: &str, filter: &Filter) -> Result<bool> {
+        let filter = filter.clone();
+
+        self.writing(collection, move |_, tree, indexed| {
+            let Some((id, existing)) = Self::first(tree, indexed, &filter)? else {
+                return Ok(false);
+            };
+
+            tree.remove(&id)?;
+
+            for indexed in indexed.iter_mut() {
+                indexed.forget(&id, &existing);
+            }
+
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn create_index(&self, collection: &str, index: &Index) -> Result<()> {
+        let index = index.clone();
+
+        self.blocking(collection, move |_, tree, indexed| {
+            if indexed.iter().any(|indexed| indexed.index == index) {
+                return Ok(());
+            }
+
+            let mut built = Indexed {
+                index,
+                ids: HashMap::new(),
+            };
+
+            for entry in Self::documents(tree) {
+                let (id, document) = entry?;
+
+                // like MongoDB, a unique index cannot be created over duplicates
+                if let Some(value) = lookup(&document, &built.index.field)
+                    && built.index.unique
+                    && built.ids.contains_key(&value.to_string())
+                {
+                    return Err(taken(&built.index, value));
+                }
+
+                built.add(&id, &document);
+            }
+
+            indexed.push(built);
+            Ok(())
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sled_storage() {
+        let dir = tempfile::tempdir().unwrap();
+
+        super::super::tests::exercise(&SledStorage::open(dir.path()).unwrap()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+
+        super::super::tests::exercise_queries(&SledStorage::open(dir.path()).unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_documents_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let storage = SledStorage::open(dir.path()).unwrap();
+            storage
+                .insert("items", json!({ "name": "sword" }))
+                .await
+                .unwrap();
+        }
+
+        let storage = SledStorage::open(dir.path()).unwrap();
+        assert_eq!(
+            storage.find("items", &Query::new()).await.unwrap(),
+            vec![json!({ "name": "sword" })]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_indexes_cover_documents_from_before_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let storage = SledStorage::open(dir.path()).unwrap();
+            storage
+                .insert("items", json!({ "key": 1, "name": "sword" }))
+                .await
+                .unwrap();
+        }
+
+        let storage = SledStorage::open(dir.path()).unwrap();
+        storage
+            .create_index("items", &Index::unique("key"))
+            .await
+            .unwrap();
+
+        assert!(
+            storage
+                .insert("items", json!({ "key": 1, "name": "axe" }))
+                .await
+                .is_err()
+        );
+        assert!(
+            storage
+                .set("items", &Filter::by("key", 1), json!({ "name": "axe" }))
+                .await
+                .unwrap()
+        );
+        assert!(
+            storage
+                .replace("items", &Filter::by("key", 1), json!({ "key": 2 }))
+                .await
+                .unwrap()
+        );
+
+        // the old value is free again, the new one is taken
+        storage.insert("items", json!({ "key": 1 })).await.unwrap();
+        assert!(storage.insert("items", json!({ "key": 2 })).await.is_err());
+        assert_eq!(
+            storage
+                .find("items", &Filter::by("key", 2).into())
+                .await
+                .unwrap(),
+            vec![json!({ "key": 2 })]
+        );
+
+        assert!(
+            storage
+                .remove("items", &Filter::by("key", 2))
+                .await
+                .unwrap()
+        );
+        storage.insert("items", json!({ "key": 2 })).await.unwrap();
+    }
+}
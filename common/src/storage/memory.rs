@@ -0,0 +1,183 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::{Filter, Index, Query, Storage, Written, check_unique, indexes_of, not_an_object};
+
+// lives as long as the process, for tests and throwaway servers
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    collections: HashMap<String, Vec<Value>>,
+    indexes: HashMap<String, Vec<Index>>,
+}
+
+impl State {
+    fn position(&self, collection: &str, filter: &Filter) -> Option<usize> {
+        self.collections
+            .get(collection)?
+            .iter()
+            .position(|document| filter.matches(document))
+    }
+
+    // writes `document` over the one at `position`, or appends it
+    fn write(&mut self, collection: &str, position: Option<usize>, document: Value) -> Result<()> {
+        let documents = self.collections.entry(collection.to_string()).or_default();
+
+        let others: Vec<_> = documents
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != position)
+            .map(|(_, other)| other)
+            .collect();
+
+        check_unique(indexes_of(&self.indexes, collection), &others, &document)?;
+
+        match position {
+            Some(i) => documents[i] = document,
+            None => documents.push(document),
+        }
+
+        Ok(())
+    }
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn insert(&self, collection: &str, document: Value) -> Result<()> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        self.state.lock().unwrap().write(collection, None, document)
+    }
+
+    async fn find(&self, collection: &str, query: &Query) -> Result<Vec<Value>> {
+        let matching = self
+            .state
+            .lock()
+            .unwrap()
+            .collections
+            .get(collection)
+            .map(|documents| {
+                documents
+                    .iter()
+                    .filter(|document| query.filter.matches(document))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(query.page(matching))
+    }
+
+    async fn replace(&self, collection: &str, filter: &Filter, document: Value) -> Result<bool> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        match state.position(collection, filter) {
+            Some(i) => state.write(collection, Some(i), document).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    async fn upsert(&self, collection: &str, filter: &Filter, document: Value) -> Result<Written> {
+        if !document.is_object() {
+            return Err(not_an_object());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let position = state.position(collection, filter);
+
+        state.write(collection, position, document)?;
+
+        Ok(match position {
+            Some(_) => Written::Replaced,
+            None => Written::Inserted,
+        })
+    }
+
+    async fn set(&self, collection: &str, filter: &Filter, fields: Value) -> Result<bool> {
+        let Value::Object(fields) = fields else {
+            return Err(not_an_object());
+        };
+
+        let mut state = self.state.lock().unwrap();
+
+        let Some(i) = state.position(collection, filter) else {
+            return Ok(false);
+        };
+
+        let mut document = state.collections[collection][i].clone();
+
+        if let Some(existing) = document.as_object_mut() {
+            existing.extend(fields);
+        }
+
+        state.write(collection, Some(i), document).map(|_| true)
+    }
+
+    async fn remove(&self, collection: &str, filter: &Filter) -> Result<bool> {
+        let mut state = self.state.lock().unwrap();
+
+        let Some(i) = state.position(collection, filter) else {
+            return Ok(false);
+        };
+
+        if let Some(documents) = state.collections.get_mut(collection) {
+            documents.remove(i);
+        }
+
+        Ok(true)
+    }
+
+    async fn create_index(&self, collection: &str, index: &Index) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let documents = state
+            .collections
+            .get(collection)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        // like MongoDB, a unique index cannot be created over duplicates
+        for (i, document) in documents.iter().enumerate() {
+            let earlier: Vec<_> = documents[..i].iter().collect();
+            check_unique(std::slice::from_ref(index), &earlier, document)?;
+        }
+
+        let indexes = state.indexes.entry(collection.to_string()).or_default();
+
+        if !indexes.contains(index) {
+            indexes.push(index.clone());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_storage() {
+        super::super::tests::exercise(&MemoryStorage::new()).await;
+        super::super::tests::exercise_queries(&MemoryStorage::new()).await;
+    }
+}
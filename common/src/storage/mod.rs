@@ -0,0 +1,445 @@
+use std::{cmp::Ordering, path::Path, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::{DatabaseError, Error};
+
+pub mod embedded;
+pub mod memory;
+#[cfg(feature = "mongodb")]
+pub mod mongo;
+
+// documents are plain json objects, the backends translate them to whatever they store
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // writes fail with `DatabaseError::ExistingItem` when they would break a unique index
+    async fn insert(&self, collection: &str, document: Value) -> Result<()>;
+    async fn find(&self, collection: &str, query: &Query) -> Result<Vec<Value>>;
+    // the rest only touch the first document matching `filter`, and report whether there was one
+    async fn replace(&self, collection: &str, filter: &Filter, document: Value) -> Result<bool>;
+    // replaces the first match, or inserts the document when there is none
+    async fn upsert(&self, collection: &str, filter: &Filter, document: Value) -> Result<Written>;
+    // overwrites the given top level fields, leaving the others alone
+    async fn set(&self, collection: &str, filter: &Filter, fields: Value) -> Result<bool>;
+    async fn remove(&self, collection: &str, filter: &Filter) -> Result<bool>;
+    // creating an index that already exists does nothing
+    async fn create_index(&self, collection: &str, index: &Index) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Written {
+    Inserted,
+    Replaced,
+}
+
+// fields that all have to equal the given values, nested fields are dotted as in `position.x`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    fields: Vec<(String, Value)>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn by(field: &str, value: impl Into<Value>) -> Self {
+        Self::new().and(field, value)
+    }
+
+    pub fn and(mut self, field: &str, value: impl Into<Value>) -> Self {
+        self.fields.push((field.to_string(), value.into()));
+        self
+    }
+
+    pub fn fields(&self) -> &[(String, Value)] {
+        &self.fields
+    }
+
+    pub fn matches(&self, document: &Value) -> bool {
+        self.fields
+            .iter()
+            .all(|(field, value)| lookup(document, field) == Some(value))
+    }
+}
+
+// a filter with an optional order and page, without an order pages follow the backend's own
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub filter: Filter,
+    // ascending
+    pub sort: Option<String>,
+    pub skip: usize,
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn sort_by(mut self, field: &str) -> Self {
+        self.sort = Some(field.to_string());
+        self
+    }
+
+    pub fn skip(mut self, skip: usize) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // orders and pages documents that already passed the filter, for the backends that scan
+    pub(crate) fn page(&self, mut documents: Vec<Value>) -> Vec<Value> {
+        if let Some(field) = &self.sort {
+            documents.sort_by(|a, b| compare(lookup(a, field), lookup(b, field)));
+        }
+
+        documents
+            .into_iter()
+            .skip(self.skip)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+impl From<Filter> for Query {
+    fn from(filter: Filter) -> Self {
+        Query::new().filter(filter)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Index {
+    pub field: String,
+    pub unique: bool,
+}
+
+impl Index {
+    pub fn on(field: &str) -> Self {
+        Index {
+            field: field.to_string(),
+            unique: false,
+        }
+    }
+
+    pub fn unique(field: &str) -> Self {
+        Index {
+            field: field.to_string(),
+            unique: true,
+        }
+    }
+}
+
+// dotted paths reach into nested objects
+pub(crate) fn lookup<'a>(document: &'a Value, field: &str) -> Option<&'a Value> {
+    document.pointer(&format!("/{}", field.replace('.', "/")))
+}
+
+// missing fields first, then numbers or strings in their natural order
+fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(Value::String(a)), Some(Value::String(b))) => a.cmp(b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+pub(crate) fn indexes_of<'a>(
+    indexes: &'a std::collections::HashMap<String, Vec<Index>>,
+    collection: &str,
+) -> &'a [Index] {
+    indexes
+        .get(collection)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+// what the scanning backends check before a write, MongoDB enforces its indexes itself
+// `others` are the documents of the collection besides the one being written
+pub(crate) fn check_unique(indexes: &[Index], others: &[&Value], document: &Value) -> Result<()> {
+    for index in indexes.iter().filter(|index| index.unique) {
+        let Some(value) = lookup(document, &index.field) else {
+            continue;
+        };
+
+        if others
+            .iter()
+            .any(|other| lookup(other, &index.field) == Some(value))
+        {
+            return Err(taken(index, value));
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn taken(index: &Index, value: &Value) -> anyhow::Error {
+    Error::DatabaseError(DatabaseError::ExistingItem(format!(
+        "{} {}",
+        index.field, value
+    )))
+    .into()
+}
+
+// picks the backend from the scheme of `uri`
+// `mongodb://` and `mongodb+srv://` for MongoDB, `sled://<dir>` for a local file store, `memory://` for nothing at all
+pub async fn open(uri: &str, db_name: &str) -> Result<Arc<dyn Storage>> {
+    if uri.starts_with("memory://") {
+        return Ok(Arc::new(memory::MemoryStorage::new()));
+    }
+
+    if let Some(dir) = uri.strip_prefix("sled://") {
+        return Ok(Arc::new(embedded::SledStorage::open(
+            Path::new(dir).join(db_name),
+        )?));
+    }
+
+    if uri.starts_with("mongodb://") || uri.starts_with("mongodb+srv://") {
+        #[cfg(feature = "mongodb")]
+        return Ok(Arc::new(mongo::MongoStorage::connect(uri, db_name).await?));
+
+        #[cfg(not(feature = "mongodb"))]
+        return Err(Error::DatabaseError(DatabaseError::Generic(
+            "Built without MongoDB support, enable the `mongodb` feature".to_string(),
+        ))
+        .into());
+    }
+
+    Err(Error::DatabaseError(DatabaseError::Generic(format!(
+        "Unsupported database uri: {}",
+        uri
+    )))
+    .into())
+}
+
+pub(crate) fn not_an_object() -> anyhow::Error {
+    Error::DatabaseError(DatabaseError::Generic(
+        "Documents have to be objects".to_string(),
+    ))
+    .into()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // every backend has to behave the same, each runs this against itself
+    pub(crate) async fn exercise(storage: &dyn Storage) {
+        storage
+            .insert(
+                "items",
+                json!({ "name": "sword", "stats": { "agility": 1 } }),
+            )
+            .await
+            .unwrap();
+        storage
+            .insert(
+                "items",
+                json!({ "name": "shield", "stats": { "agility": 1 } }),
+            )
+            .await
+            .unwrap();
+        storage
+            .insert("other", json!({ "name": "sword" }))
+            .await
+            .unwrap();
+
+        let found = storage
+            .find("items", &Filter::by("stats.agility", 1).into())
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert!(
+            storage
+                .set(
+                    "items",
+                    &Filter::by("name", "sword"),
+                    json!({ "stats": { "agility": 2 } })
+                )
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            storage
+                .find("items", &Filter::by("name", "sword").into())
+                .await
+                .unwrap(),
+            vec![json!({ "name": "sword", "stats": { "agility": 2 } })]
+        );
+
+        assert!(
+            storage
+                .replace(
+                    "items",
+                    &Filter::by("name", "shield"),
+                    json!({ "name": "buckler" })
+                )
+                .await
+                .unwrap()
+        );
+        assert!(
+            !storage
+                .replace("items", &Filter::by("name", "shield"), json!({}))
+                .await
+                .unwrap()
+        );
+
+        assert!(
+            storage
+                .remove("items", &Filter::by("name", "sword"))
+                .await
+                .unwrap()
+        );
+        assert!(
+            !storage
+                .remove("items", &Filter::by("name", "sword"))
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            storage.find("items", &Query::new()).await.unwrap(),
+            vec![json!({ "name": "buckler" })]
+        );
+        assert_eq!(storage.find("other", &Query::new()).await.unwrap().len(), 1);
+    }
+
+    // run on a fresh storage, as `exercise` leaves documents behind
+    pub(crate) async fn exercise_queries(storage: &dyn Storage) {
+        storage
+            .create_index("npcs", &Index::unique("uuid"))
+            .await
+            .unwrap();
+        // again, as on every start
+        storage
+            .create_index("npcs", &Index::unique("uuid"))
+            .await
+            .unwrap();
+
+        for (uuid, level) in [("c", 3), ("a", 1), ("b", 2)] {
+            storage
+                .insert("npcs", json!({ "uuid": uuid, "level": level }))
+                .await
+                .unwrap();
+        }
+
+        let error = storage
+            .insert("npcs", json!({ "uuid": "a", "level": 4 }))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::DatabaseError(DatabaseError::ExistingItem(_)))
+        ));
+        assert!(
+            storage
+                .replace("npcs", &Filter::by("uuid", "b"), json!({ "uuid": "a" }))
+                .await
+                .is_err()
+        );
+
+        let page = storage
+            .find("npcs", &Query::new().sort_by("uuid").skip(1).limit(1))
+            .await
+            .unwrap();
+        assert_eq!(page, vec![json!({ "uuid": "b", "level": 2 })]);
+
+        let by_level = storage
+            .find("npcs", &Query::new().sort_by("level").limit(2))
+            .await
+            .unwrap();
+        assert_eq!(
+            by_level,
+            vec![
+                json!({ "uuid": "a", "level": 1 }),
+                json!({ "uuid": "b", "level": 2 })
+            ]
+        );
+
+        assert_eq!(
+            storage
+                .upsert(
+                    "npcs",
+                    &Filter::by("uuid", "d"),
+                    json!({ "uuid": "d", "level": 4 })
+                )
+                .await
+                .unwrap(),
+            Written::Inserted
+        );
+        assert_eq!(
+            storage
+                .upsert(
+                    "npcs",
+                    &Filter::by("uuid", "d"),
+                    json!({ "uuid": "d", "level": 5 })
+                )
+                .await
+                .unwrap(),
+            Written::Replaced
+        );
+        assert_eq!(
+            storage
+                .find("npcs", &Filter::by("uuid", "d").into())
+                .await
+                .unwrap(),
+            vec![json!({ "uuid": "d", "level": 5 })]
+        );
+
+        // duplicates already stored keep a unique index from being created
+        assert!(
+            storage
+                .create_index("npcs", &Index::on("level"))
+                .await
+                .is_ok()
+        );
+        storage
+            .insert("npcs", json!({ "uuid": "e", "level": 5 }))
+            .await
+            .unwrap();
+        assert!(
+            storage
+                .create_index("npcs", &Index::unique("level"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_filters_match_nested_fields() {
+        let document = json!({ "name": "sword", "stats": { "agility": 1 } });
+
+        assert!(Filter::new().matches(&document));
+        assert!(
+            Filter::by("name", "sword")
+                .and("stats.agility", 1)
+                .matches(&document)
+        );
+        assert!(!Filter::by("stats.agility", 2).matches(&document));
+        assert!(!Filter::by("stats.stamina", 1).matches(&document));
+    }
+
+    #[tokio::test]
+    async fn test_open_picks_the_backend_from_the_uri() {
+        assert!(open("memory://", "fordragon").await.is_ok());
+        assert!(open("postgres://localhost", "fordragon").await.is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    Client, Collection, Database, IndexModel,
+    bson::{Document, doc, from_document, to_bson, to_document},
+    error::{ErrorKind, WriteFailure},
+    options::{
+        ClientOptions, FindOptions, IndexOptions, ReplaceOptions, ServerApi, ServerApiVersion,
+    },
+};
+use serde_json::Value;
+
+use crate::error::{DatabaseError, Error};
+
+use super::{Filter, Index, Query, Storage, Written, not_an_object};
+
+const DUPLICATE_KEY: i32 = 11000;
+
+pub struct MongoStorage {
+    db: Database,
+}
+
+impl MongoStorage {
+    pub async fn connect(uri: &str, db_name: &str) -> Result<Self> {
+        let mut client_options = ClientOptions::parse(uri).await?;
+
+        let server_api = ServerApi::builder().version(ServerApiVersion::V1).build();
+
+        client_options.server_api = Some(server_api);
+
+        let client = Client::with_options(client_options)?;
+
+        Ok(MongoStorage {
+            db: client.database(db_name),
+        })
+    }
+
+    fn collection(&self, name: &str) -> Collection<Document> {
+        self.db.collection::<Document>(name)
+    }
+}
+
+fn to_filter(filter: &Filter) -> Result<Document> {
+    let mut document = Document::new();
+
+    for (field, value) in filter.fields() {
+        document.insert(field, to_bson(value)?);
+    }
+
+    Ok(document)
+}
+
+fn to_mongo(document: &Value) -> Result<Document> {
+    if !document.is_object() {
+        return Err(not_an_object());
+    }
+
+    Ok(to_document(document)?)
+}
+
+// the generated `_id` is MongoDB's own business
+fn from_mongo(mut document: Document) -> Result<Value> {
+    document.remove("_id");
+
+    Ok(from_document(document)?)
+}
+
+// unique index violations look the same as with the other backends
+fn write_error(e: mongodb::error::Error) -> anyhow::Error {
+    match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(failure)) if failure.code == DUPLICATE_KEY => {
+            Error::DatabaseError(DatabaseError::ExistingItem(failure.message.clone())).into()
+        }
+        _ => e.into(),
+    }
+}
+
+#[async_trait]
+impl Storage for MongoStorage {
+    async fn insert(&self, collection: &str, document: Value) -> Result<()> {
+        self.collection(collection)
+            .insert_one(to_mongo(&document)?, None)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+
+    async fn find(&self, collection: &str, query: &Query) -> Result<Vec<Value>> {
+        let options = FindOptions::builder()
+            .sort(query.sort.as_ref().map(|field| doc! { field: 1 }))
+            .skip(query.skip as u64)
+            .limit(query.limit.map(|limit| limit as i64))
+            .build();
+
+        let documents: Vec<Document> = self
+            .collection(collection)
+            .find(to_filter(&query.filter)?, options)
+            .await?
+            .try_collect()
+            .await?;
+
+        documents.into_iter().map(from_mongo).collect()
+    }
+
+    async fn replace(&self, collection: &str, filter: &Filter, document: Value) -> Result<bool> {
+        let result = self
+            .collection(collection)
+            .replace_one(to_filter(filter)?, to_mongo(&document)?, None)
+            .await
+            .map_err(write_error)?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    async fn upsert(&self, collection: &str, filter: &Filter, document: Value) -> Result<Written> {
+        let result = self
+            .collection(collection)
+            .replace_one(
+                to_filter(filter)?,
+                to_mongo(&document)?,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(write_error)?;
+
+        Ok(match result.upserted_id {
+            Some(_) => Written::Inserted,
+            None => Written::Replaced,
+        })
+    }
+
+    async fn set(&self, collection: &str, filter: &Filter, fields: Value) -> Result<bool> {
+        let result = self
+            .collection(collection)
+            .update_one(
+                to_filter(filter)?,
+                doc! { "$set": to_mongo(&fields)? },
+                None,
+            )
+            .await
+            .map_err(write_error)?;
+
+        Ok(result.matched_count > 0)
+    }
+
+    async fn remove(&self, collection: &str, filter: &Filter) -> Result<bool> {
+        let result = self
+            .collection(collection)
+            .delete_one(to_filter(filter)?, None)
+            .await?;
+
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn create_index(&self, collection: &str, index: &Index) -> Result<()> {
+        let model = IndexModel::builder()
+            .keys(doc! { index.field.as_str(): 1 })
+            .options(IndexOptions::builder().unique(index.unique).build())
+            .build();
+
+        self.collection(collection)
+            .create_index(model, None)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,24 +1,462 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
 use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{
+    model::{Account, Character, Item, ItemInstance, ItemType, LootTable, Npc, NpcSpawn},
+    repository::Model,
+    storage::{Filter, Query, Storage, lookup},
+};
+
+// what any single stat of an item may be
+pub const STAT_BOUNDS: RangeInclusive<isize> = -1000..=1000;
+pub const CHANCE_BOUNDS: RangeInclusive<f64> = 0.0..=1.0;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Reason {
+    #[error("is required")]
+    Required,
+    #[error("must be within {0}")]
+    OutOfRange(String),
+    #[error("is already taken")]
+    Taken,
+    // derived from another field, which it no longer agrees with
+    #[error("must match {0}")]
+    Mismatch(String),
+    #[error("refers to a missing {0}")]
+    Missing(&'static str),
+    // the storage could not answer, the item is neither valid nor invalid
+    #[error("could not be checked: {0}")]
+    Unverified(String),
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("{field} {reason}")]
+pub struct ValidationError {
+    // dotted like the stored document, `entries.0.chance`
+    pub field: String,
+    pub reason: Reason,
+}
+
+pub type Validation = Result<(), Vec<ValidationError>>;
+
+// checked before every add and update, `storage` answers uniqueness and references
+#[async_trait]
+pub trait Validateable {
+    async fn validate(&self, storage: &dyn Storage) -> Validation;
+}
+
+// collects the failures of any number of rules
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<ValidationError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fail(&mut self, field: &str, reason: Reason) -> &mut Self {
+        self.errors.push(ValidationError {
+            field: field.to_string(),
+            reason,
+        });
+        self
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.trim().is_empty() {
+            self.fail(field, Reason::Required);
+        }
+        self
+    }
+
+    pub fn range<T: PartialOrd + Display>(
+        &mut self,
+        field: &str,
+        value: T,
+        range: &RangeInclusive<T>,
+    ) -> &mut Self {
+        if !range.contains(&value) {
+            self.fail(
+                field,
+                Reason::OutOfRange(format!("{} and {}", range.start(), range.end())),
+            );
+        }
+        self
+    }
+
+    // no other `M` but the one keyed `key` may have `value` in `field`
+    pub async fn unique<M: Model>(
+        &mut self,
+        storage: &dyn Storage,
+        field: &str,
+        value: &str,
+        key: &str,
+    ) -> &mut Self {
+        match storage
+            .find(M::COLLECTION, &Filter::by(field, value).into())
+            .await
+        {
+            Ok(found) => {
+                if found
+                    .iter()
+                    .any(|other| lookup(other, M::KEY).and_then(|k| k.as_str()) != Some(key))
+                {
+                    self.fail(field, Reason::Taken);
+                }
+            }
+            Err(e) => {
+                self.fail(field, Reason::Unverified(e.to_string()));
+            }
+        }
+        self
+    }
 
-use crate::{database_handler::DatabaseHandler, model::Item};
+    // an `M` keyed `key` has to be stored
+    pub async fn exists<M: Model>(
+        &mut self,
+        storage: &dyn Storage,
+        field: &str,
+        key: &str,
+    ) -> &mut Self {
+        let query = Query::new().filter(Filter::by(M::KEY, key)).limit(1);
+
+        match storage.find(M::COLLECTION, &query).await {
+            Ok(found) if found.is_empty() => {
+                self.fail(field, Reason::Missing(M::COLLECTION));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                self.fail(field, Reason::Unverified(e.to_string()));
+            }
+        }
+        self
+    }
+
+    // the item's own fields, for wherever an item is embedded
+    pub fn item(&mut self, prefix: &str, item: &Item) -> &mut Self {
+        self.required(&format!("{}uuid", prefix), &item.uuid)
+            .required(&format!("{}name", prefix), &item.name);
+
+        // serialized as `{ "OneHandedSword": { "stats": .. } }`
+        let (variant, equipable) = match &item.item_type {
+            ItemType::OneHandedSword(equipable) => ("OneHandedSword", equipable),
+            ItemType::TwoHandedSword(equipable) => ("TwoHandedSword", equipable),
+        };
+        let stats = &equipable.stats;
+
+        for (stat, value) in [
+            ("stamina", stats.stamina),
+            ("intellect", stats.intellect),
+            ("agility", stats.agility),
+        ] {
+            self.range(
+                &format!("{}item_type.{}.stats.{}", prefix, variant, stat),
+                value,
+                &STAT_BOUNDS,
+            );
+        }
+        self
+    }
+
+    pub fn finish(self) -> Validation {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+#[async_trait]
+impl Validateable for Item {
+    async fn validate(&self, _storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        validator.item("", self);
+
+        validator.finish()
+    }
+}
 
 #[async_trait]
-pub trait Validateable<T> {
-    fn uuid(&self) -> String;
-    async fn validate_add(&self, dh: &DatabaseHandler) -> bool;
+impl Validateable for ItemInstance {
+    async fn validate(&self, storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        validator
+            .required("uuid", &self.uuid)
+            .item("item.", &self.item);
+        validator
+            .exists::<Item>(storage, "item.uuid", &self.item.uuid)
+            .await;
+
+        validator.finish()
+    }
 }
 
 #[async_trait]
-impl Validateable<Item> for Item {
-    fn uuid(&self) -> String {
-        self.uuid.to_string()
+impl Validateable for Npc {
+    async fn validate(&self, _storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        validator
+            .required("uuid", &self.uuid)
+            .required("entity.name", &self.entity.name);
+
+        validator.finish()
     }
+}
+
+#[async_trait]
+impl Validateable for NpcSpawn {
+    async fn validate(&self, storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
 
-    async fn validate_add(&self, dh: &DatabaseHandler) -> bool {
-        if let Ok(Some(_)) = dh.get::<Item>(&self.uuid).await {
-            return true;
+        validator.required("uuid", &self.uuid);
+        validator
+            .exists::<Npc>(storage, "npc.uuid", &self.npc.uuid)
+            .await;
+
+        for (axis, value) in [
+            ("x", self.location.x),
+            ("y", self.location.y),
+            ("z", self.location.z),
+        ] {
+            if !value.is_finite() {
+                validator.fail(
+                    &format!("location.{}", axis),
+                    Reason::OutOfRange("finite numbers".to_string()),
+                );
+            }
+        }
+
+        validator.finish()
+    }
+}
+
+#[async_trait]
+impl Validateable for LootTable {
+    async fn validate(&self, storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        validator
+            .exists::<Npc>(storage, "npc_uuid", &self.npc_uuid)
+            .await;
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            validator
+                .range(
+                    &format!("entries.{}.chance", i),
+                    entry.chance,
+                    &CHANCE_BOUNDS,
+                )
+                .item(&format!("entries.{}.item.", i), &entry.item);
+            validator
+                .exists::<Item>(
+                    storage,
+                    &format!("entries.{}.item.uuid", i),
+                    &entry.item.uuid,
+                )
+                .await;
+        }
+
+        validator.finish()
+    }
+}
+
+#[async_trait]
+impl Validateable for Account {
+    async fn validate(&self, _storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        // the name is the key, which adding checks on its own
+        validator.required("name", &self.name);
+
+        validator.finish()
+    }
+}
+
+#[async_trait]
+impl Validateable for Character {
+    // names are unique across all accounts and regardless of case, the unique index on `name_key`
+    // settles creations that race past this check
+    async fn validate(&self, storage: &dyn Storage) -> Validation {
+        let mut validator = Validator::new();
+
+        validator
+            .required("name", &self.name)
+            .required("account", &self.account);
+
+        if self.name_key != Character::normalize_name(&self.name) {
+            validator.fail(
+                "name_key",
+                Reason::Mismatch("the normalized name".to_string()),
+            );
         }
 
-        false
+        validator
+            .unique::<Character>(storage, "name_key", &self.name_key, &self.uuid)
+            .await;
+
+        validator.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        model::{Entity, EquipableItem, LootEntry, Stats, Vec3d},
+        storage::memory::MemoryStorage,
+    };
+
+    use super::*;
+
+    fn item(uuid: &str, agility: isize) -> Item {
+        Item {
+            uuid: uuid.to_string(),
+            name: "Sword".to_string(),
+            item_type: ItemType::OneHandedSword(EquipableItem {
+                stats: Stats {
+                    stamina: 0,
+                    intellect: 0,
+                    agility,
+                },
+            }),
+        }
+    }
+
+    fn fields(validation: Validation) -> Vec<(String, Reason)> {
+        validation
+            .unwrap_err()
+            .into_iter()
+            .map(|error| (error.field, error.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules_collect_every_failure() {
+        let mut validator = Validator::new();
+
+        validator
+            .required("name", " ")
+            .range("level", 0, &(1..=60))
+            .range("chance", 0.5, &CHANCE_BOUNDS);
+
+        assert_eq!(
+            fields(validator.finish()),
+            vec![
+                ("name".to_string(), Reason::Required),
+                (
+                    "level".to_string(),
+                    Reason::OutOfRange("1 and 60".to_string())
+                ),
+            ]
+        );
+        assert_eq!(Validator::new().finish(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_unique_ignores_the_item_itself() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert("accounts", json!({ "name": "player" }))
+            .await
+            .unwrap();
+
+        let mut validator = Validator::new();
+        validator
+            .unique::<Account>(&storage, "name", "player", "player")
+            .await;
+        assert_eq!(validator.finish(), Ok(()));
+
+        let mut validator = Validator::new();
+        validator
+            .unique::<Account>(&storage, "name", "player", "other")
+            .await;
+        assert_eq!(
+            fields(validator.finish()),
+            vec![("name".to_string(), Reason::Taken)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loot_tables_are_checked_for_chances_stats_and_references() {
+        let storage = MemoryStorage::new();
+        storage
+            .insert("items", serde_json::to_value(item("sword", 1)).unwrap())
+            .await
+            .unwrap();
+
+        let table = LootTable {
+            npc_uuid: "hogger".to_string(),
+            entries: vec![
+                LootEntry {
+                    chance: 0.5,
+                    item: item("sword", 1),
+                },
+                LootEntry {
+                    chance: 1.5,
+                    item: item("axe", 5000),
+                },
+            ],
+        };
+
+        assert_eq!(
+            fields(table.validate(&storage).await),
+            vec![
+                ("npc_uuid".to_string(), Reason::Missing("npcs")),
+                (
+                    "entries.1.chance".to_string(),
+                    Reason::OutOfRange("0 and 1".to_string())
+                ),
+                (
+                    "entries.1.item.item_type.OneHandedSword.stats.agility".to_string(),
+                    Reason::OutOfRange("-1000 and 1000".to_string())
+                ),
+                ("entries.1.item.uuid".to_string(), Reason::Missing("items")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawns_need_their_npc() {
+        let storage = MemoryStorage::new();
+
+        let spawn = NpcSpawn {
+            uuid: "spawn".to_string(),
+            npc: Npc {
+                uuid: "hogger".to_string(),
+                entity: Entity {
+                    uuid: "hogger".to_string(),
+                    name: "Hogger".to_string(),
+                },
+            },
+            location: Vec3d {
+                x: f64::NAN,
+                y: 0.0,
+                z: 0.0,
+            },
+        };
+
+        assert_eq!(spawn.validate(&storage).await.unwrap_err().len(), 2);
+
+        storage
+            .insert("npcs", serde_json::to_value(&spawn.npc).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fields(spawn.validate(&storage).await),
+            vec![(
+                "location.x".to_string(),
+                Reason::OutOfRange("finite numbers".to_string())
+            )]
+        );
     }
 }
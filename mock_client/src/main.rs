@@ -1,9 +1,37 @@
-use std::{net::SocketAddr, sync::Arc, thread, time::Duration};
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use server::server::{
+    components::{movement_state::MovementStateType, shared::vec3d::Vec3d},
+    opcode::OpCode,
+    packets::packet::Packet,
+    protocols::{
+        recv::{
+            create_character_packet::{Appearance, CharacterClass, CreateCharacterPacket},
+            credentials_packet::CredentialsPacket,
+            enter_packet::EnterPacket,
+            list_characters_packet::ListCharactersPacket,
+            move_packet::MovePacket,
+        },
+        send::{
+            authenticated_packet::AuthenticatedPacket,
+            character_list_packet::CharacterListPacket,
+            error_packet::{ErrorCode, ErrorPacket},
+        },
+        version::PROTOCOL_VERSION,
+    },
+};
 use tokio::net::UdpSocket;
 
+const SERVER_ADDR: &str = "127.0.0.1:1337";
+
 pub struct MockClient {}
 
 impl MockClient {
@@ -12,51 +40,60 @@ impl MockClient {
 
         println!("Mock client started on {:?}", sock.local_addr()?);
 
+        // every mock client plays on its own account, with a single character
+        let port = sock.local_addr()?.port();
+        let credentials = CredentialsPacket::new(format!("mock_{}", port), "password".to_string());
+        let character = CreateCharacterPacket::new(
+            format!("Mock{}", port),
+            CharacterClass::Warrior,
+            Appearance::default(),
+        );
+        let mut entered = false;
+
         let receiver = Arc::new(sock);
         let sender = receiver.clone();
 
-        let mut packet_id = 0;
+        // shared by both tasks, the server drops packets whose id goes backwards
+        let packet_id = Arc::new(AtomicU64::new(0));
+
+        send(
+            &sender,
+            &packet_id,
+            OpCode::Register,
+            serde_json::to_string(&credentials)?,
+        )
+        .await?;
+
+        let move_sender = sender.clone();
+        let move_packet_id = packet_id.clone();
 
         tokio::spawn(async move {
+            let directions = [
+                Vec3d::new(1.0, 0.0, 0.0),
+                Vec3d::new(0.0, 0.0, 1.0),
+                Vec3d::new(-1.0, 0.0, 0.0),
+                Vec3d::new(0.0, 0.0, -1.0),
+            ];
+
             loop {
-                thread::sleep(Duration::from_millis(1_000));
+                tokio::time::sleep(Duration::from_millis(1_000)).await;
 
                 // sender
-                let spawn_packets: Vec<SpawnPacket> = std::iter::repeat_n(
-                    SpawnPacket {
-                        location: Vec3d {
-                            x: 0.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                        spawned_type: "player".to_string(),
-                    },
-                    10,
-                )
-                .collect();
-
-                println!("Length of packets: {:?}", spawn_packets.len());
-
-                let mut counter = 0;
-
-                for packet in spawn_packets {
-                    let packet = serde_json::to_string(&Packet {
-                        id: Some(packet_id),
-                        opcode: OpCode::Spawn,
-                        data: serde_json::to_string(&packet).unwrap(),
-                    })
+                for direction in directions.iter().cycle().take(10) {
+                    let id = send(
+                        &move_sender,
+                        &move_packet_id,
+                        OpCode::Move,
+                        serde_json::to_string(&MovePacket::new(
+                            direction.clone(),
+                            MovementStateType::Moving,
+                        ))
+                        .unwrap(),
+                    )
+                    .await
                     .unwrap();
 
-                    packet_id += 1;
-
-                    sender
-                        .send_to(packet.as_bytes(), "127.0.0.1:1337")
-                        .await
-                        .unwrap();
-
-                    counter += 1;
-
-                    println!("Sent packet {:?}", counter);
+                    println!("Sent packet {:?}", id);
                 }
             }
         });
@@ -68,71 +105,93 @@ impl MockClient {
             let (len, addr) = receiver.recv_from(&mut buf).await?;
             println!("{:?} bytes received from {:?}", len, addr);
 
-            let packet =
-                serde_json::from_str::<Packet>(std::str::from_utf8(&buf[..len]).unwrap()).unwrap();
-        }
-    }
-}
-
-#[tokio::main]
-async fn main() {
-    let mut client = MockClient {};
+            let packet = serde_json::from_slice::<Packet>(&buf[..len])?;
 
-    client.run().await.unwrap();
-}
+            match packet.opcode {
+                OpCode::Error => {
+                    let error = serde_json::from_str::<ErrorPacket>(&packet.data)?;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Vec3d {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
+                    println!("Server rejected client: {:?}", error);
 
-impl Vec3d {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
-        Vec3d { x, y, z }
-    }
-
-    pub fn zero() -> Self {
-        Vec3d {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
+                    if error.code == ErrorCode::AccountExists {
+                        send(
+                            &sender,
+                            &packet_id,
+                            OpCode::Login,
+                            serde_json::to_string(&credentials)?,
+                        )
+                        .await?;
+                    }
+                }
+                OpCode::Authenticated => {
+                    let authenticated = serde_json::from_str::<AuthenticatedPacket>(&packet.data)?;
+
+                    println!("Logged in as {}", authenticated.account);
+
+                    send(
+                        &sender,
+                        &packet_id,
+                        OpCode::ListCharacters,
+                        serde_json::to_string(&ListCharactersPacket::default())?,
+                    )
+                    .await?;
+                }
+                OpCode::CharacterList => {
+                    let list = serde_json::from_str::<CharacterListPacket>(&packet.data)?;
+
+                    match list.characters.first() {
+                        None => {
+                            send(
+                                &sender,
+                                &packet_id,
+                                OpCode::CreateCharacter,
+                                serde_json::to_string(&character)?,
+                            )
+                            .await?;
+                        }
+                        Some(listed) if !entered => {
+                            entered = true;
+
+                            send(
+                                &sender,
+                                &packet_id,
+                                OpCode::Enter,
+                                serde_json::to_string(&EnterPacket::new(
+                                    PROTOCOL_VERSION,
+                                    listed.id.clone(),
+                                ))?,
+                            )
+                            .await?;
+                        }
+                        Some(_) => {}
+                    }
+                }
+                _ => {}
+            }
         }
     }
-
-    pub fn length(&self) -> f64 {
-        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
-    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
-pub struct Packet {
-    pub id: Option<u128>,
-    pub opcode: OpCode,
-    pub data: String,
+async fn send(
+    sock: &UdpSocket,
+    packet_id: &AtomicU64,
+    opcode: OpCode,
+    data: String,
+) -> Result<u128> {
+    let id = packet_id.fetch_add(1, Ordering::SeqCst) as u128;
+
+    sock.send_to(
+        serde_json::to_string(&Packet::new(id, opcode, data))?.as_bytes(),
+        SERVER_ADDR,
+    )
+    .await?;
+
+    Ok(id)
 }
 
-impl Packet {
-    pub fn new(id: u128, opcode: OpCode, data: String) -> Self {
-        Packet {
-            id: Some(id),
-            opcode,
-            data,
-        }
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct SpawnPacket {
-    pub spawned_type: String,
-    pub location: Vec3d,
-}
+#[tokio::main]
+async fn main() {
+    let mut client = MockClient {};
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash, Default)]
-pub enum OpCode {
-    #[default]
-    Unset,
-    Movement,
-    Spawn,
+    client.run().await.unwrap();
 }
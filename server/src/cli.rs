@@ -1,18 +1,374 @@
-mod common;
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
 
-use std::env;
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use common::{
+    config::Config,
+    content_file::{self, Format},
+    content_pack::ContentPack,
+    database_handler::DatabaseHandler,
+    error::{DatabaseError, Error},
+    repository::{Content, Model, Repository},
+    storage::{Filter, Query},
+};
+use server::server::protocols::schema::ProtocolSchema;
 
-use common::config::Config;
-use common::error::Error;
+#[derive(Parser, Debug)]
+#[command(name = "cli", about = "Fordragon server administration")]
+struct Cli {
+    /// Server config to take the database from
+    #[arg(long, default_value = "server.json")]
+    config: PathBuf,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write the protocol schema to a file, or stdout
+    Schema { path: Option<PathBuf> },
+    /// Manage item templates
+    #[command(subcommand)]
+    Items(ContentCommand),
+    /// Manage npcs
+    #[command(subcommand)]
+    Npcs(ContentCommand),
+    /// Manage npc spawns
+    #[command(subcommand)]
+    Spawns(ContentCommand),
+    /// Manage loot tables, keyed by the uuid of their npc
+    #[command(subcommand)]
+    Loot(ContentCommand),
+    /// Seed or export content packs, directories of JSON or RON files
+    #[command(subcommand)]
+    Pack(PackCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum PackCommand {
+    /// Validate a pack, then add or replace whatever differs from the database
+    Seed {
+        dir: PathBuf,
+        /// Only show what seeding would change, write nothing
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Write all content of the database to a pack
+    Export {
+        dir: PathBuf,
+        /// json or ron
+        #[arg(long, default_value = "json")]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ContentCommand {
+    /// Add the entries of a JSON or RON file, failing on keys that are taken
+    Add {
+        file: PathBuf,
+        /// Only validate, write nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the stored entries
+    List {
+        /// Only the entries with this name
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long, default_value_t = 0)]
+        skip: usize,
+        #[arg(long)]
+        limit: Option<usize>,
+        /// json or ron
+        #[arg(long, default_value = "json")]
+        format: Format,
+    },
+    /// Print a single entry
+    Show {
+        key: String,
+        /// json or ron
+        #[arg(long, default_value = "json")]
+        format: Format,
+    },
+    /// Remove a single entry
+    Remove { key: String },
+    /// Add or replace the entries of a JSON or RON file
+    Import {
+        file: PathBuf,
+        /// Only validate, write nothing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write every entry to a file, in the format of its extension, or stdout
+    Export {
+        path: Option<PathBuf>,
+        /// json or ron, for stdout
+        #[arg(long, default_value = "json")]
+        format: Format,
+    },
+}
 
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli).await {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Schema { path } => export_schema(path.as_deref()),
+        Command::Items(command) => manage(&connect(&cli.config).await?.items, command).await,
+        Command::Npcs(command) => manage(&connect(&cli.config).await?.npcs, command).await,
+        Command::Spawns(command) => manage(&connect(&cli.config).await?.npc_spawns, command).await,
+        Command::Loot(command) => manage(&connect(&cli.config).await?.loot_tables, command).await,
+        Command::Pack(command) => pack(&connect(&cli.config).await?, command).await,
+    }
+}
+
+async fn connect(config: &Path) -> Result<Content> {
+    let config = Config::load(config)
+        .map_err(|e| Error::FileError(format!("{}: {}", config.display(), e)))?;
+
+    let database = DatabaseHandler::connect(&config.db_uri, &config.db_name).await?;
+    let content = Content::new(database.storage().clone());
+
+    content.create_indexes().await?;
+
+    Ok(content)
+}
+
+async fn manage<T: Model + Debug>(
+    repository: &Repository<T>,
+    command: ContentCommand,
+) -> Result<()> {
+    match command {
+        ContentCommand::Add { file, dry_run } => {
+            let entries = content_file::read::<T>(&file)?;
+            check(repository, &entries, true).await?;
+
+            if dry_run {
+                println!("{} entries are valid", entries.len());
+                return Ok(());
+            }
+
+            for entry in &entries {
+                repository.insert(entry).await?;
+                println!("Added {}", entry.key());
+            }
+        }
+        ContentCommand::List {
+            name,
+            skip,
+            limit,
+            format,
+        } => {
+            let mut query = Query::new().sort_by(T::KEY).skip(skip);
+
+            if let Some(limit) = limit {
+                query = query.limit(limit);
+            }
+
+            if let Some(name) = name {
+                let field = T::NAME.ok_or_else(|| {
+                    Error::InvalidArguments(format!("{} have no name", T::COLLECTION))
+                })?;
+
+                query = query.filter(Filter::by(field, name));
+            }
+
+            println!("{}", format.write(&repository.list(&query).await?)?);
+        }
+        ContentCommand::Show { key, format } => match repository.get(&key).await? {
+            Some(entry) => println!("{}", format.write(&entry)?),
+            None => return Err(not_found::<T>(&key)),
+        },
+        ContentCommand::Remove { key } => {
+            if !repository.remove(&key).await? {
+                return Err(not_found::<T>(&key));
+            }
+
+            println!("Removed {}", key);
+        }
+        ContentCommand::Import { file, dry_run } => {
+            let entries = content_file::read::<T>(&file)?;
+            check(repository, &entries, false).await?;
+
+            if dry_run {
+                println!("{} entries are valid", entries.len());
+                return Ok(());
+            }
+
+            for entry in &entries {
+                let written = repository.upsert(entry).await?;
+                println!("{:?} {}", written, entry.key());
+            }
+        }
+        ContentCommand::Export { path, format } => {
+            let entries = repository.list(&Query::new().sort_by(T::KEY)).await?;
+
+            match path {
+                Some(path) => {
+                    content_file::write(&path, &entries)?;
+                    println!("Exported {} entries to {}", entries.len(), path.display());
+                }
+                None => println!("{}", format.write(&entries)?),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn pack(content: &Content, command: PackCommand) -> Result<()> {
+    match command {
+        PackCommand::Seed { dir, diff } => {
+            let pack = ContentPack::read(&dir)?;
+
+            let differences = if diff {
+                pack.diff(content).await?
+            } else {
+                pack.seed(content).await?
+            };
+
+            for difference in &differences {
+                println!("{}", difference);
+            }
+
+            match (diff, differences.len()) {
+                (_, 0) => println!("The database is up to date"),
+                (true, changes) => println!("Seeding would write {} entries", changes),
+                (false, changes) => println!("Seeded {} entries", changes),
+            }
+        }
+        PackCommand::Export { dir, format } => {
+            let pack = ContentPack::export(content).await?;
+            pack.write(&dir, format)?;
+
+            println!(
+                "Exported {} items, {} npcs, {} spawns and {} loot tables to {}",
+                pack.items.len(),
+                pack.npcs.len(),
+                pack.npc_spawns.len(),
+                pack.loot_tables.len(),
+                dir.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// validates every entry before anything is written, so a bad file leaves the database as it was
+// keys have to be unique within the file, and adding also needs them to be free
+async fn check<T: Model>(repository: &Repository<T>, entries: &[T], adding: bool) -> Result<()> {
+    let mut failed = 0;
+    let mut keys = HashSet::new();
+
+    for entry in entries {
+        let mut problems = Vec::new();
+
+        if !keys.insert(entry.key()) {
+            problems.push(format!("{} appears more than once in the file", T::KEY));
+        }
+
+        if let Err(e) = repository.validate(entry).await {
+            match e.downcast_ref::<Error>() {
+                Some(Error::DatabaseError(DatabaseError::Invalid(errors))) => {
+                    problems.extend(errors.iter().map(ToString::to_string))
+                }
+                _ => return Err(e),
+            }
+        }
+
+        if adding && repository.get(entry.key()).await?.is_some() {
+            problems.push(format!("{} already exists", T::KEY));
+        }
+
+        if !problems.is_empty() {
+            failed += 1;
+            eprintln!("{} {}: {}", T::COLLECTION, entry.key(), problems.join(", "));
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::InvalidArguments(format!(
+            "{} of {} entries are invalid, nothing was written",
+            failed,
+            entries.len()
+        ))
+        .into());
+    }
+
+    Ok(())
+}
+
+fn not_found<T: Model>(key: &str) -> anyhow::Error {
+    Error::InvalidArguments(format!("No {} keyed {}", T::COLLECTION, key)).into()
+}
+
+// writes the protocol schema to the given path, or stdout when no path is given
+fn export_schema(path: Option<&Path>) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&ProtocolSchema::generate())
+        .map_err(|e| Error::ParsingError(e.to_string()))?;
+
+    match path {
+        Some(path) => fs::write(path, schema).map_err(|e| Error::FileError(e.to_string()))?,
+        None => println!("{}", schema),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::{
+        model::{EquipableItem, Item, ItemType, Stats},
+        storage::memory::MemoryStorage,
+    };
+
+    use super::*;
+
+    fn item(uuid: &str) -> Item {
+        Item {
+            uuid: uuid.to_string(),
+            name: "Sword".to_string(),
+            item_type: ItemType::OneHandedSword(EquipableItem {
+                stats: Stats {
+                    stamina: 0,
+                    intellect: 0,
+                    agility: 1,
+                },
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_files_with_repeated_keys_are_rejected() {
+        let items = Content::new(Arc::new(MemoryStorage::new())).items;
+
+        assert!(check(&items, &[item("1"), item("2")], true).await.is_ok());
 
-    let command = args
-        .get(1)
-        .ok_or(Error::InvalidArguments("No command received".to_string()))
-        .unwrap();
+        for adding in [true, false] {
+            assert!(check(&items, &[item("1"), item("2"), item("1")], adding)
+                .await
+                .is_err());
+        }
 
-    let config = Config::get().unwrap();
+        items.insert(&item("1")).await.unwrap();
+        assert!(check(&items, &[item("1")], true).await.is_err());
+        assert!(check(&items, &[item("1")], false).await.is_ok());
+    }
 }
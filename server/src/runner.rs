@@ -1,18 +1,35 @@
 #![warn(unused_extern_crates)]
 
-use std::sync::{Arc, Mutex, RwLock};
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
 
-use common::config::Config;
+use common::{
+    account_store::AccountStore,
+    character_store::CharacterStore,
+    config::Config,
+    database_handler::DatabaseHandler,
+    model::{Account, Character},
+    repository::{Content, Model, Repository},
+};
+use log::error;
 use server::server::{
+    accounts::{account_service::AccountService, login_throttle::ThrottleConfig},
+    characters::{character_service::CharacterService, persistence::PersistenceConfig},
+    connections::connection_manager::{ConnectionConfig, ConnectionManager},
     packet_receiver::packet_receiver::ServerPacketReceiver,
-    packet_sender::builder::ServerPacketSenderBuilder,
-    server::Server,
-    state::{
-        authorization_handler::AuthorizationHandler, packet_id_generator::PacketIdGenerator,
-        state_handler::ServerStateHandler, ticker::Ticker,
+    packet_sender::{bandwidth::BandwidthConfig, builder::ServerPacketSenderBuilder},
+    replication::{
+        client_snapshots::SnapshotConfig, dead_reckoning::DeadReckoningConfig,
+        interest::InterestConfig,
     },
+    server::Server,
+    state::{state_handler::ServerStateHandler, ticker::Ticker},
 };
 
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() {
     let config = Config::get().unwrap();
@@ -26,26 +43,111 @@ async fn main() {
 
     let ticker = Arc::new(Mutex::new(ticker));
 
-    let packet_id_generator = PacketIdGenerator::new();
+    let connection_manager = ConnectionManager::new(ConnectionConfig {
+        timeout: Duration::from_secs(config.connection_timeout_secs),
+        packets_per_second: config.max_packets_per_second,
+        burst: config.packet_burst,
+        grace_period: Duration::from_secs(config.session_grace_secs),
+    });
+
+    let connection_manager = Arc::new(RwLock::new(connection_manager));
+
+    let database = DatabaseHandler::connect(&config.db_uri, &config.db_name)
+        .await
+        .unwrap();
+
+    Content::new(database.storage().clone())
+        .create_indexes()
+        .await
+        .unwrap();
+
+    // the unique indexes are what keeps concurrent registrations and creations apart
+    Repository::<Account>::new(database.storage().clone())
+        .create_indexes()
+        .await
+        .unwrap();
+    Repository::<Character>::new(database.storage().clone())
+        .create_indexes()
+        .await
+        .unwrap();
 
-    let packet_id_generator = Arc::new(Mutex::new(packet_id_generator));
+    let account_store: Arc<dyn AccountStore> =
+        Arc::new(database.connect_collection(Account::COLLECTION));
+
+    let accounts = Arc::new(
+        AccountService::new(
+            account_store,
+            ThrottleConfig {
+                max_attempts: config.max_login_attempts,
+                window: Duration::from_secs(config.login_window_secs),
+            },
+        )
+        .with_registration_throttle(ThrottleConfig {
+            max_attempts: config.max_registrations,
+            window: Duration::from_secs(config.registration_window_secs),
+        }),
+    );
+
+    let character_store: Arc<dyn CharacterStore> =
+        Arc::new(database.connect_collection(Character::COLLECTION));
+
+    let characters = Arc::new(
+        CharacterService::new(character_store, config.max_characters_per_account)
+            .with_persistence_config(PersistenceConfig {
+                interval: Duration::from_secs(config.persistence_interval_secs),
+                batch_size: config.persistence_batch_size,
+            }),
+    );
+
+    let writer = characters.writer().clone();
 
     let packet_sender =
-        ServerPacketSenderBuilder::build(ticker.clone(), packet_id_generator.clone());
+        ServerPacketSenderBuilder::build(ticker.clone(), connection_manager.clone())
+            .with_bandwidth_config(BandwidthConfig {
+                bytes_per_tick: config.bandwidth_per_tick,
+                ..BandwidthConfig::default()
+            });
 
     let packet_sender = Arc::new(Mutex::new(packet_sender));
 
-    let state_handler = ServerStateHandler::new(ticker.clone(), packet_sender.clone());
-
-    let authorization_handler = Arc::new(RwLock::new(AuthorizationHandler::new()));
+    let state_handler = ServerStateHandler::new(ticker.clone(), packet_sender.clone())
+        .with_snapshot_config(SnapshotConfig {
+            precision: config.position_precision,
+            history_len: config.snapshot_history,
+            // four chances to be acked before the connection times out
+            keepalive_ticks: (config.tick_count as u64 * config.connection_timeout_secs / 4)
+                .clamp(1, u32::MAX as u64) as u32,
+            ..SnapshotConfig::default()
+        })
+        .with_interest_config(InterestConfig {
+            radius: config.interest_radius,
+        })
+        .with_dead_reckoning_config(DeadReckoningConfig {
+            tolerance: config.position_tolerance,
+            keyframe_ticks: config.keyframe_ticks,
+        });
 
     let packet_receiver = ServerPacketReceiver::new(
         Box::new(state_handler),
         ticker.clone(),
-        authorization_handler.clone(),
+        connection_manager.clone(),
+        accounts,
+        characters,
     );
 
     let mut server = Server::new(Box::new(packet_receiver), packet_sender);
 
-    let _ = server.run().await;
+    // the ticks keep going while the characters in the world are written
+    tokio::select! {
+        _ = server.run() => {}
+        _ = async {
+            let _ = tokio::signal::ctrl_c().await;
+
+            writer.request_shutdown();
+
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, writer.flushed()).await.is_err() {
+                error!("Gave up on saving characters after {:?}", SHUTDOWN_TIMEOUT);
+            }
+        } => {}
+    }
 }
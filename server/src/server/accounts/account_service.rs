@@ -0,0 +1,610 @@
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use bevy_ecs::resource::Resource;
+use common::{
+    account_store::AccountStore,
+    error::Error as CommonError,
+    model::{Account, AccountStatus},
+};
+use log::{info, warn};
+use tokio::sync::{OnceCell, Semaphore};
+
+use crate::server::{
+    accounts::login_throttle::{LoginThrottle, ThrottleConfig},
+    error::Error,
+};
+
+const NAME_LENGTH: std::ops::RangeInclusive<usize> = 3..=24;
+const PASSWORD_LENGTH: std::ops::RangeInclusive<usize> = 8..=128;
+// argon2 runs on the blocking pool, the hashes past these wait for their turn
+const MAX_CONCURRENT_HASHES: usize = 4;
+
+// a finished registration or login, waiting to be applied to the connection it came from
+#[derive(Debug)]
+pub struct AuthOutcome {
+    pub addr: SocketAddr,
+    pub result: Result<Account, Error>,
+}
+
+pub struct AccountService {
+    store: Arc<dyn AccountStore>,
+    params: Params,
+    throttle: Mutex<LoginThrottle>,
+    // registrations per remote ip
+    registrations: Mutex<LoginThrottle>,
+    // names and ips with a login being verified, each gets one at a time
+    logging_in: Mutex<HashSet<String>>,
+    hashing: Semaphore,
+    // verified against for unknown names, so they take as long to reject as a wrong password
+    dummy_hash: OnceCell<String>,
+    outcomes: Mutex<Vec<AuthOutcome>>,
+}
+
+// holds a login's name and ip until it has been decided
+struct LoggingIn<'a> {
+    logging_in: &'a Mutex<HashSet<String>>,
+    keys: [String; 2],
+}
+
+impl Drop for LoggingIn<'_> {
+    fn drop(&mut self) {
+        let mut logging_in = self
+            .logging_in
+            .lock()
+            .expect("Failed to lock logins in progress");
+
+        for key in &self.keys {
+            logging_in.remove(key);
+        }
+    }
+}
+
+// shared with the systems, the packet handlers only start the work
+#[derive(Resource, Clone)]
+pub struct Accounts(pub Arc<AccountService>);
+
+impl AccountService {
+    pub fn new(store: Arc<dyn AccountStore>, throttle: ThrottleConfig) -> Self {
+        AccountService {
+            store,
+            params: Params::default(),
+            throttle: Mutex::new(LoginThrottle::new(throttle)),
+            registrations: Mutex::new(LoginThrottle::new(ThrottleConfig::default())),
+            logging_in: Mutex::new(HashSet::new()),
+            hashing: Semaphore::new(MAX_CONCURRENT_HASHES),
+            dummy_hash: OnceCell::new(),
+            outcomes: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn with_registration_throttle(mut self, throttle: ThrottleConfig) -> Self {
+        self.registrations = Mutex::new(LoginThrottle::new(throttle));
+        self
+    }
+
+    // hashes are only ever as expensive as these, verifying reads the parameters from the hash
+    pub fn with_params(mut self, params: Params) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub async fn register(
+        &self,
+        addr: SocketAddr,
+        name: &str,
+        password: &str,
+    ) -> Result<Account, Error> {
+        let ip = addr.ip().to_string();
+
+        {
+            let mut registrations = self
+                .registrations
+                .lock()
+                .expect("Failed to lock registration throttle");
+            let now = Instant::now();
+
+            if registrations.is_throttled(&ip, now) {
+                return Err(Error::RegistrationThrottled(ip));
+            }
+
+            registrations.record_attempt(&ip, now);
+        }
+
+        validate(name, password)?;
+
+        let account = Account {
+            name: name.to_string(),
+            password_hash: self.hash(password).await?,
+            status: AccountStatus::Active,
+        };
+
+        self.store.insert(account.clone()).await.map_err(|e| {
+            match e.downcast_ref::<CommonError>() {
+                Some(CommonError::DatabaseError(e)) if e.is_taken() => {
+                    Error::AccountExists(name.to_string())
+                }
+                _ => Error::AccountStorage(e.to_string()),
+            }
+        })?;
+
+        info!("Registered account {}", name);
+
+        Ok(account)
+    }
+
+    pub async fn login(
+        &self,
+        addr: SocketAddr,
+        name: &str,
+        password: &str,
+    ) -> Result<Account, Error> {
+        // no account can have such a name, and it must not reach the throttle
+        validate_name(name).map_err(|_| Error::InvalidCredentials(name.to_string()))?;
+
+        let by_name = name_key(name);
+        let by_ip = ip_key(addr);
+
+        let _logging_in = self.start_login(name, [by_name.clone(), by_ip.clone()])?;
+        let attempted_at = Instant::now();
+
+        {
+            let mut throttle = self.throttle.lock().expect("Failed to lock login throttle");
+
+            if throttle.is_throttled(&by_name, attempted_at)
+                || throttle.is_throttled(&by_ip, attempted_at)
+            {
+                return Err(Error::LoginThrottled(name.to_string()));
+            }
+
+            // counted before verifying, it is taken back once the password turns out right
+            throttle.record_attempt(&by_name, attempted_at);
+            throttle.record_attempt(&by_ip, attempted_at);
+        }
+
+        let account = self
+            .store
+            .find(name)
+            .await
+            .map_err(|e| Error::AccountStorage(e.to_string()))?;
+
+        let verified = match &account {
+            Some(account) => self.verify(password, &account.password_hash).await?,
+            None => {
+                self.verify(password, self.dummy_hash().await?).await?;
+                false
+            }
+        };
+
+        let account = match account {
+            Some(account) if verified => account,
+            _ => return Err(Error::InvalidCredentials(name.to_string())),
+        };
+
+        {
+            let mut throttle = self.throttle.lock().expect("Failed to lock login throttle");
+
+            // the address keeps its failures, succeeding on one account must not reset guessing another
+            throttle.reset(&by_name);
+            throttle.forgive(&by_ip, attempted_at);
+        }
+
+        if account.status == AccountStatus::Banned {
+            return Err(Error::AccountBanned(name.to_string()));
+        }
+
+        Ok(account)
+    }
+
+    fn start_login(&self, name: &str, keys: [String; 2]) -> Result<LoggingIn<'_>, Error> {
+        let mut logging_in = self
+            .logging_in
+            .lock()
+            .expect("Failed to lock logins in progress");
+
+        if keys.iter().any(|key| logging_in.contains(key)) {
+            return Err(Error::LoginThrottled(name.to_string()));
+        }
+
+        logging_in.extend(keys.iter().cloned());
+
+        Ok(LoggingIn {
+            logging_in: &self.logging_in,
+            keys,
+        })
+    }
+
+    pub async fn set_status(&self, name: &str, status: AccountStatus) -> Result<(), Error> {
+        let mut account = self
+            .store
+            .find(name)
+            .await
+            .map_err(|e| Error::AccountStorage(e.to_string()))?
+            .ok_or_else(|| Error::InvalidAccountDetails(format!("no account named {}", name)))?;
+
+        account.status = status;
+
+        self.store
+            .update(account)
+            .await
+            .map_err(|e| Error::AccountStorage(e.to_string()))
+    }
+
+    // runs the registration on the runtime, the outcome is picked up by `authentication_system`
+    pub fn spawn_register(self: &Arc<Self>, addr: SocketAddr, name: String, password: String) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = service.register(addr, &name, &password).await;
+            service.complete(addr, result);
+        });
+    }
+
+    pub fn spawn_login(self: &Arc<Self>, addr: SocketAddr, name: String, password: String) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = service.login(addr, &name, &password).await;
+            service.complete(addr, result);
+        });
+    }
+
+    pub fn complete(&self, addr: SocketAddr, result: Result<Account, Error>) {
+        if let Err(e) = &result {
+            warn!("Authentication of {:?} failed: {}", addr, e);
+        }
+
+        self.outcomes
+            .lock()
+            .expect("Failed to lock authentication outcomes")
+            .push(AuthOutcome { addr, result });
+    }
+
+    pub fn take_outcomes(&self) -> Vec<AuthOutcome> {
+        std::mem::take(
+            &mut *self
+                .outcomes
+                .lock()
+                .expect("Failed to lock authentication outcomes"),
+        )
+    }
+
+    async fn hash(&self, password: &str) -> Result<String, Error> {
+        let _permit = self.hashing_permit().await?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone());
+        let password = password.to_string();
+
+        // hashing is deliberately slow, it must not hold up the runtime
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| Error::PasswordHashing(e.to_string()))
+        })
+        .await
+        .map_err(|e| Error::PasswordHashing(e.to_string()))?
+    }
+
+    async fn verify(&self, password: &str, password_hash: &str) -> Result<bool, Error> {
+        let _permit = self.hashing_permit().await?;
+        let password = password.to_string();
+        let password_hash = password_hash.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let parsed = PasswordHash::new(&password_hash)
+                .map_err(|e| Error::PasswordHashing(e.to_string()))?;
+
+            Ok(Argon2::default()
+                .verify_password(password.as_bytes(), &parsed)
+                .is_ok())
+        })
+        .await
+        .map_err(|e| Error::PasswordHashing(e.to_string()))?
+    }
+
+    async fn hashing_permit(&self) -> Result<tokio::sync::SemaphorePermit<'_>, Error> {
+        self.hashing
+            .acquire()
+            .await
+            .map_err(|e| Error::PasswordHashing(e.to_string()))
+    }
+
+    async fn dummy_hash(&self) -> Result<&str, Error> {
+        self.dummy_hash
+            .get_or_try_init(|| self.hash("not a password"))
+            .await
+            .map(String::as_str)
+    }
+}
+
+// names and addresses share the throttle, the prefixes keep a name from posing as an address
+fn name_key(name: &str) -> String {
+    format!("name:{}", name)
+}
+
+fn ip_key(addr: SocketAddr) -> String {
+    format!("ip:{}", addr.ip())
+}
+
+fn validate(name: &str, password: &str) -> Result<(), Error> {
+    validate_name(name)?;
+
+    if !PASSWORD_LENGTH.contains(&password.chars().count()) {
+        return Err(Error::InvalidAccountDetails(format!(
+            "passwords are {} to {} characters",
+            PASSWORD_LENGTH.start(),
+            PASSWORD_LENGTH.end()
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), Error> {
+    if !NAME_LENGTH.contains(&name.chars().count())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(Error::InvalidAccountDetails(format!(
+            "names are {} to {} letters, digits or underscores",
+            NAME_LENGTH.start(),
+            NAME_LENGTH.end()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use common::account_store::InMemoryAccountStore;
+
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:7777".parse().unwrap()
+    }
+
+    // cheap parameters, the defaults take seconds per hash in debug builds
+    fn service() -> AccountService {
+        AccountService::new(
+            Arc::new(InMemoryAccountStore::new()),
+            ThrottleConfig {
+                max_attempts: 2,
+                window: Duration::from_secs(60),
+            },
+        )
+        .with_params(Params::new(256, 1, 1, None).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_registered_accounts_can_log_in() {
+        let service = service();
+
+        let account = service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+        assert_ne!(account.password_hash, "password");
+        assert!(account.password_hash.starts_with("$argon2id$"));
+
+        // logging in is counted until it succeeds, succeeding again and again is never throttled
+        for _ in 0..3 {
+            let logged_in = service.login(addr(), "player", "password").await.unwrap();
+            assert_eq!(logged_in.name, "player");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registrations_per_address_are_throttled() {
+        let service = service().with_registration_throttle(ThrottleConfig {
+            max_attempts: 2,
+            window: Duration::from_secs(60),
+        });
+
+        service.register(addr(), "first", "password").await.unwrap();
+        service.register(addr(), "a", "password").await.unwrap_err();
+
+        assert!(matches!(
+            service.register(addr(), "second", "password").await,
+            Err(Error::RegistrationThrottled(_))
+        ));
+
+        service
+            .register("127.0.0.2:7777".parse().unwrap(), "second", "password")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_names_are_unique() {
+        let service = service();
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.register(addr(), "player", "other password").await,
+            Err(Error::AccountExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_details_are_rejected() {
+        let service = service();
+
+        assert!(matches!(
+            service.register(addr(), "a", "password").await,
+            Err(Error::InvalidAccountDetails(_))
+        ));
+        assert!(matches!(
+            service.register(addr(), "player", "short").await,
+            Err(Error::InvalidAccountDetails(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_rejected_then_throttled() {
+        let service = service();
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                service.login(addr(), "player", "wrong password").await,
+                Err(Error::InvalidCredentials(_))
+            ));
+        }
+
+        // even the right password, the window has to pass first
+        assert!(matches!(
+            service.login(addr(), "player", "password").await,
+            Err(Error::LoginThrottled(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_a_burst_of_logins_gets_no_more_guesses() {
+        let service = Arc::new(service());
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+
+        let logins: Vec<_> = (0..20)
+            .map(|i| {
+                let service = service.clone();
+                let addr = SocketAddr::from(([127, 0, 0, 1], 7000 + i));
+
+                tokio::spawn(async move { service.login(addr, "player", "wrong password").await })
+            })
+            .collect();
+
+        let guesses = futures::future::join_all(logins)
+            .await
+            .into_iter()
+            .filter(|result| matches!(result, Ok(Err(Error::InvalidCredentials(_)))))
+            .count();
+
+        assert!(guesses <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_one_login_per_account_at_a_time() {
+        let service = service();
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+
+        let logging_in = service
+            .start_login(
+                "player",
+                [
+                    name_key("player"),
+                    ip_key("127.0.0.2:7777".parse().unwrap()),
+                ],
+            )
+            .unwrap();
+
+        assert!(matches!(
+            service.login(addr(), "player", "password").await,
+            Err(Error::LoginThrottled(_))
+        ));
+        assert!(matches!(
+            service
+                .login("127.0.0.2:7777".parse().unwrap(), "other", "password")
+                .await,
+            Err(Error::LoginThrottled(_))
+        ));
+
+        drop(logging_in);
+        service.login(addr(), "player", "password").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_names_posing_as_addresses_throttle_nobody() {
+        let service = service();
+        let victim: SocketAddr = "127.0.0.2:7777".parse().unwrap();
+
+        service
+            .register(victim, "player", "password")
+            .await
+            .unwrap();
+
+        for _ in 0..3 {
+            assert!(matches!(
+                service.login(addr(), "127.0.0.2", "password").await,
+                Err(Error::InvalidCredentials(_))
+            ));
+        }
+
+        // even a name that got past validation only ever counts as a name
+        let _posing = service
+            .start_login("ip_127", [name_key("ip:127.0.0.2"), ip_key(addr())])
+            .unwrap();
+
+        service.login(victim, "player", "password").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_accounts_count_against_the_address() {
+        let service = service();
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+
+        service
+            .login(addr(), "nobody", "password")
+            .await
+            .unwrap_err();
+        service
+            .login(addr(), "no_one", "password")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            service.login(addr(), "player", "password").await,
+            Err(Error::LoginThrottled(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_banned_accounts_cannot_log_in() {
+        let service = service();
+
+        service
+            .register(addr(), "player", "password")
+            .await
+            .unwrap();
+        service
+            .set_status("player", AccountStatus::Banned)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.login(addr(), "player", "password").await,
+            Err(Error::AccountBanned(_))
+        ));
+    }
+}
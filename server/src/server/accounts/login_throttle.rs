@@ -0,0 +1,134 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleConfig {
+    // attempts allowed per key within `window`
+    pub max_attempts: u32,
+    pub window: Duration,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        ThrottleConfig {
+            max_attempts: 5,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+// attempts per key, an account name or a remote ip, over a sliding window
+// logins count until they succeed, registrations always count
+#[derive(Debug, Default)]
+pub struct LoginThrottle {
+    config: ThrottleConfig,
+    failures: HashMap<String, VecDeque<Instant>>,
+}
+
+impl LoginThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        LoginThrottle {
+            config,
+            failures: HashMap::new(),
+        }
+    }
+
+    pub fn is_throttled(&mut self, key: &str, now: Instant) -> bool {
+        let Some(failures) = self.failures.get_mut(key) else {
+            return false;
+        };
+
+        while failures
+            .front()
+            .is_some_and(|failed_at| now.saturating_duration_since(*failed_at) > self.config.window)
+        {
+            failures.pop_front();
+        }
+
+        if failures.is_empty() {
+            self.failures.remove(key);
+            return false;
+        }
+
+        failures.len() >= self.config.max_attempts as usize
+    }
+
+    pub fn record_attempt(&mut self, key: &str, now: Instant) {
+        self.failures
+            .entry(key.to_string())
+            .or_default()
+            .push_back(now);
+    }
+
+    pub fn reset(&mut self, key: &str) {
+        self.failures.remove(key);
+    }
+
+    // takes back the single attempt recorded at `at`, leaving the others
+    pub fn forgive(&mut self, key: &str, at: Instant) {
+        if let Some(failures) = self.failures.get_mut(key) {
+            if let Some(index) = failures.iter().position(|failed_at| *failed_at == at) {
+                failures.remove(index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle() -> LoginThrottle {
+        LoginThrottle::new(ThrottleConfig {
+            max_attempts: 2,
+            window: Duration::from_secs(10),
+        })
+    }
+
+    #[test]
+    fn test_throttles_after_max_attempts() {
+        let mut throttle = throttle();
+        let now = Instant::now();
+
+        throttle.record_attempt("name", now);
+        assert!(!throttle.is_throttled("name", now));
+
+        throttle.record_attempt("name", now);
+        assert!(throttle.is_throttled("name", now));
+        assert!(!throttle.is_throttled("other", now));
+
+        throttle.reset("name");
+        assert!(!throttle.is_throttled("name", now));
+    }
+
+    #[test]
+    fn test_forgiving_takes_back_one_attempt() {
+        let mut throttle = throttle();
+        let now = Instant::now();
+        let later = now + Duration::from_secs(1);
+
+        throttle.record_attempt("ip", now);
+        throttle.record_attempt("ip", later);
+        assert!(throttle.is_throttled("ip", later));
+
+        throttle.forgive("ip", later);
+        assert!(!throttle.is_throttled("ip", later));
+
+        throttle.record_attempt("ip", later);
+        assert!(throttle.is_throttled("ip", later));
+    }
+
+    #[test]
+    fn test_failures_fall_out_of_the_window() {
+        let mut throttle = throttle();
+        let now = Instant::now();
+
+        throttle.record_attempt("name", now);
+        throttle.record_attempt("name", now + Duration::from_secs(5));
+        assert!(throttle.is_throttled("name", now + Duration::from_secs(5)));
+
+        assert!(!throttle.is_throttled("name", now + Duration::from_secs(11)));
+    }
+}
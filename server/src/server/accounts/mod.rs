@@ -0,0 +1,2 @@
+pub mod account_service;
+pub mod login_throttle;
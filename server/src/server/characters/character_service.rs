@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use bevy_ecs::resource::Resource;
+use common::{
+    character_store::CharacterStore,
+    error::Error as CommonError,
+    model::{Appearance, Character, CharacterClass, Movement, Vec3d},
+};
+use log::{info, warn};
+use uuid::Uuid;
+
+use crate::server::{
+    characters::persistence::{CharacterWriter, PersistenceConfig},
+    error::Error,
+};
+
+const NAME_LENGTH: std::ops::RangeInclusive<usize> = 3..=16;
+pub const START_ZONE: &str = "start";
+
+// a finished character request, waiting to be answered by `character_system`
+#[derive(Debug)]
+pub enum CharacterOutcome {
+    // the account's characters after listing, creating or deleting
+    Listed {
+        addr: SocketAddr,
+        result: Result<Vec<Character>, Error>,
+    },
+    // the character picked on enter, ready to be spawned
+    Selected {
+        addr: SocketAddr,
+        result: Result<Character, Error>,
+    },
+}
+
+pub struct CharacterService {
+    store: Arc<dyn CharacterStore>,
+    max_characters: usize,
+    writer: Arc<CharacterWriter>,
+    // held while an account creates a character, so two creations cannot both pass the limit
+    creating: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    outcomes: Mutex<Vec<CharacterOutcome>>,
+}
+
+// shared with the systems, the packet handlers only start the work
+#[derive(Resource, Clone)]
+pub struct Characters(pub Arc<CharacterService>);
+
+impl CharacterService {
+    pub fn new(store: Arc<dyn CharacterStore>, max_characters: usize) -> Self {
+        CharacterService {
+            writer: Arc::new(CharacterWriter::new(
+                store.clone(),
+                PersistenceConfig::default(),
+            )),
+            store,
+            max_characters,
+            creating: Mutex::new(HashMap::new()),
+            outcomes: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn with_persistence_config(mut self, config: PersistenceConfig) -> Self {
+        self.writer = Arc::new(CharacterWriter::new(self.store.clone(), config));
+        self
+    }
+
+    pub fn writer(&self) -> &Arc<CharacterWriter> {
+        &self.writer
+    }
+
+    pub async fn list(&self, account: &str) -> Result<Vec<Character>, Error> {
+        self.store
+            .list(account)
+            .await
+            .map_err(|e| Error::CharacterStorage(e.to_string()))
+    }
+
+    pub async fn create(
+        &self,
+        account: &str,
+        name: &str,
+        class: CharacterClass,
+        appearance: Appearance,
+    ) -> Result<Character, Error> {
+        validate(name)?;
+
+        let creating = self.creating(account);
+        let _creating = creating.lock().await;
+
+        if self.list(account).await?.len() >= self.max_characters {
+            return Err(Error::CharacterLimitReached(self.max_characters));
+        }
+
+        let character = Character {
+            uuid: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            name_key: Character::normalize_name(name),
+            account: account.to_string(),
+            class,
+            appearance,
+            level: 1,
+            position: Vec3d::default(),
+            movement: Movement::default(),
+            zone: START_ZONE.to_string(),
+        };
+
+        self.store.insert(character.clone()).await.map_err(|e| {
+            match e.downcast_ref::<CommonError>() {
+                Some(CommonError::DatabaseError(e)) if e.is_taken() => {
+                    Error::CharacterNameTaken(name.to_string())
+                }
+                _ => Error::CharacterStorage(e.to_string()),
+            }
+        })?;
+
+        info!("Created character {} on account {}", name, account);
+
+        Ok(character)
+    }
+
+    fn creating(&self, account: &str) -> Arc<tokio::sync::Mutex<()>> {
+        let mut creating = self
+            .creating
+            .lock()
+            .expect("Failed to lock character creations");
+
+        // only the accounts creating right now keep their lock
+        creating.retain(|_, lock| Arc::strong_count(lock) > 1);
+
+        creating.entry(account.to_string()).or_default().clone()
+    }
+
+    pub async fn delete(&self, account: &str, uuid: &str) -> Result<(), Error> {
+        let character = self.select(account, uuid).await?;
+
+        self.store
+            .remove(&character.uuid)
+            .await
+            .map_err(|e| Error::CharacterStorage(e.to_string()))?;
+
+        info!(
+            "Deleted character {} of account {}",
+            character.name, account
+        );
+
+        Ok(())
+    }
+
+    // only ever finds characters of `account`, others are reported as not found
+    pub async fn select(&self, account: &str, uuid: &str) -> Result<Character, Error> {
+        // a character entering right after leaving must not be loaded from before its last save
+        self.writer.settled().await;
+
+        self.store
+            .find(uuid)
+            .await
+            .map_err(|e| Error::CharacterStorage(e.to_string()))?
+            .filter(|character| character.account == account)
+            .ok_or_else(|| Error::CharacterNotFound(uuid.to_string()))
+    }
+
+    // runs the request on the runtime, the outcome is picked up by `character_system`
+    pub fn spawn_list(self: &Arc<Self>, addr: SocketAddr, account: String) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = service.list(&account).await;
+            service.complete(CharacterOutcome::Listed { addr, result });
+        });
+    }
+
+    pub fn spawn_create(
+        self: &Arc<Self>,
+        addr: SocketAddr,
+        account: String,
+        name: String,
+        class: CharacterClass,
+        appearance: Appearance,
+    ) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = match service.create(&account, &name, class, appearance).await {
+                Ok(_) => service.list(&account).await,
+                Err(e) => Err(e),
+            };
+            service.complete(CharacterOutcome::Listed { addr, result });
+        });
+    }
+
+    pub fn spawn_delete(self: &Arc<Self>, addr: SocketAddr, account: String, uuid: String) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = match service.delete(&account, &uuid).await {
+                Ok(_) => service.list(&account).await,
+                Err(e) => Err(e),
+            };
+            service.complete(CharacterOutcome::Listed { addr, result });
+        });
+    }
+
+    pub fn spawn_select(self: &Arc<Self>, addr: SocketAddr, account: String, uuid: String) {
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let result = service.select(&account, &uuid).await;
+            service.complete(CharacterOutcome::Selected { addr, result });
+        });
+    }
+
+    pub fn complete(&self, outcome: CharacterOutcome) {
+        if let CharacterOutcome::Listed {
+            addr,
+            result: Err(e),
+        }
+        | CharacterOutcome::Selected {
+            addr,
+            result: Err(e),
+        } = &outcome
+        {
+            warn!("Character request of {:?} failed: {}", addr, e);
+        }
+
+        self.outcomes
+            .lock()
+            .expect("Failed to lock character outcomes")
+            .push(outcome);
+    }
+
+    pub fn take_outcomes(&self) -> Vec<CharacterOutcome> {
+        std::mem::take(
+            &mut *self
+                .outcomes
+                .lock()
+                .expect("Failed to lock character outcomes"),
+        )
+    }
+}
+
+fn validate(name: &str) -> Result<(), Error> {
+    let valid = NAME_LENGTH.contains(&name.chars().count())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+        && name.starts_with(|c: char| c.is_ascii_alphabetic());
+
+    if !valid {
+        return Err(Error::InvalidCharacterDetails(format!(
+            "names are {} to {} letters or digits, starting with a letter",
+            NAME_LENGTH.start(),
+            NAME_LENGTH.end()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::character_store::InMemoryCharacterStore;
+
+    use super::*;
+
+    fn service() -> CharacterService {
+        CharacterService::new(Arc::new(InMemoryCharacterStore::new()), 2)
+    }
+
+    async fn create(
+        service: &CharacterService,
+        account: &str,
+        name: &str,
+    ) -> Result<Character, Error> {
+        service
+            .create(
+                account,
+                name,
+                CharacterClass::Warrior,
+                Appearance::default(),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_characters_are_listed_per_account() {
+        let service = service();
+
+        let created = create(&service, "player", "Arthas").await.unwrap();
+        create(&service, "player", "Jaina").await.unwrap();
+        create(&service, "other", "Thrall").await.unwrap();
+
+        assert_eq!(created.level, 1);
+        assert_eq!(created.zone, START_ZONE);
+
+        let names: Vec<_> = service
+            .list("player")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|character| character.name)
+            .collect();
+        assert_eq!(names, vec!["Arthas", "Jaina"]);
+    }
+
+    #[tokio::test]
+    async fn test_names_are_unique_and_valid() {
+        let service = service();
+
+        create(&service, "player", "Arthas").await.unwrap();
+
+        assert!(matches!(
+            create(&service, "other", "Arthas").await,
+            Err(Error::CharacterNameTaken(_))
+        ));
+        assert!(matches!(
+            create(&service, "other", "ARTHAS").await,
+            Err(Error::CharacterNameTaken(_))
+        ));
+        assert!(matches!(
+            create(&service, "player", "1st").await,
+            Err(Error::InvalidCharacterDetails(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_characters_per_account_are_limited() {
+        let service = service();
+
+        create(&service, "player", "Arthas").await.unwrap();
+        create(&service, "player", "Jaina").await.unwrap();
+
+        assert!(matches!(
+            create(&service, "player", "Uther").await,
+            Err(Error::CharacterLimitReached(2))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_creations_respect_the_limit() {
+        let service = Arc::new(service());
+
+        let creations: Vec<_> = ["Arthas", "Jaina", "Uther", "Thrall", "Sylvanas"]
+            .into_iter()
+            .map(|name| {
+                let service = service.clone();
+                tokio::spawn(async move { create(&service, "player", name).await })
+            })
+            .collect();
+
+        let created = futures::future::join_all(creations)
+            .await
+            .into_iter()
+            .filter(|result| matches!(result, Ok(Ok(_))))
+            .count();
+
+        assert_eq!(created, 2);
+        assert_eq!(service.list("player").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_only_own_characters_can_be_selected_or_deleted() {
+        let service = service();
+
+        let character = create(&service, "player", "Arthas").await.unwrap();
+
+        assert_eq!(
+            service.select("player", &character.uuid).await.unwrap(),
+            character
+        );
+        assert!(matches!(
+            service.select("other", &character.uuid).await,
+            Err(Error::CharacterNotFound(_))
+        ));
+        assert!(matches!(
+            service.delete("other", &character.uuid).await,
+            Err(Error::CharacterNotFound(_))
+        ));
+
+        service.delete("player", &character.uuid).await.unwrap();
+        assert!(service.list("player").await.unwrap().is_empty());
+    }
+}
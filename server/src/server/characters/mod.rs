@@ -0,0 +1,2 @@
+pub mod character_service;
+pub mod persistence;
@@ -0,0 +1,352 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::resource::Resource;
+use common::{character_store::CharacterStore, model::CharacterState};
+use log::{debug, error, info};
+use tokio::{sync::Notify, task::JoinSet};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PersistenceConfig {
+    // how often characters that changed are written back
+    pub interval: Duration,
+    // characters per write task, a flush of more is split over several
+    pub batch_size: usize,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        PersistenceConfig {
+            interval: Duration::from_secs(30),
+            batch_size: 100,
+        }
+    }
+}
+
+// writes character state on the runtime, the tick only ever hands batches over
+pub struct CharacterWriter {
+    store: Arc<dyn CharacterStore>,
+    config: PersistenceConfig,
+    queue: Mutex<Queue>,
+    settled: Notify,
+    shutdown: AtomicBool,
+    flushed: AtomicBool,
+}
+
+impl CharacterWriter {
+    pub fn new(store: Arc<dyn CharacterStore>, config: PersistenceConfig) -> Self {
+        CharacterWriter {
+            store,
+            config,
+            queue: Mutex::new(Queue::default()),
+            settled: Notify::new(),
+            shutdown: AtomicBool::new(false),
+            flushed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn config(&self) -> PersistenceConfig {
+        self.config
+    }
+
+    // failures are logged and dropped, the character is written again once it changes
+    pub async fn save(&self, batch: Vec<(Uuid, CharacterState)>) {
+        for (id, state) in batch {
+            if let Err(e) = self.store.save_state(&id.to_string(), state).await {
+                error!("Failed to save character {}: {}", id, e);
+            }
+        }
+    }
+
+    // queues the batch behind whatever is being written, a character queued twice keeps its latest state
+    pub fn spawn_save(self: &Arc<Self>, batch: Vec<(Uuid, CharacterState)>) {
+        debug!("Saving {} characters", batch.len());
+
+        let mut queue = self.lock_queue();
+        queue.states.extend(batch);
+
+        if queue.states.is_empty() || queue.writing {
+            return;
+        }
+
+        queue.writing = true;
+
+        let writer = self.clone();
+        tokio::spawn(async move { writer.drain().await });
+    }
+
+    // the only task writing, so writes of the same character can never overtake each other
+    async fn drain(self: Arc<Self>) {
+        loop {
+            let mut states: Vec<_> = {
+                let mut queue = self.lock_queue();
+
+                if queue.states.is_empty() {
+                    queue.writing = false;
+                    break;
+                }
+
+                queue.states.drain().collect()
+            };
+
+            // every character is in one chunk only, so the chunks may run side by side
+            let mut chunks = JoinSet::new();
+
+            while !states.is_empty() {
+                let chunk: Vec<_> = states
+                    .drain(..self.config.batch_size.max(1).min(states.len()))
+                    .collect();
+                let writer = self.clone();
+
+                chunks.spawn(async move { writer.save(chunk).await });
+            }
+
+            while let Some(result) = chunks.join_next().await {
+                if let Err(e) = result {
+                    error!("Failed to save characters: {}", e);
+                }
+            }
+        }
+
+        self.settled.notify_waiters();
+    }
+
+    fn is_writing(&self) -> bool {
+        self.lock_queue().writing
+    }
+
+    fn lock_queue(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .expect("Failed to lock the character queue")
+    }
+
+    // resolves once every write queued so far has finished
+    pub async fn settled(&self) {
+        loop {
+            let notified = self.settled.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if !self.is_writing() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+
+    // asks the next tick to write every character in the world, see `character_persistence_system`
+    pub fn request_shutdown(&self) {
+        info!("Saving characters before shutting down");
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst)
+    }
+
+    pub fn mark_flushed(&self) {
+        self.flushed.store(true, Ordering::SeqCst);
+        self.settled.notify_waiters();
+    }
+
+    pub fn is_flushed(&self) -> bool {
+        self.flushed.load(Ordering::SeqCst)
+    }
+
+    // resolves once the shutdown flush has been handed over and written
+    pub async fn flushed(&self) {
+        loop {
+            let notified = self.settled.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.is_flushed() && !self.is_writing() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+// states waiting for the writer, `writing` while its task runs
+#[derive(Default)]
+struct Queue {
+    states: HashMap<Uuid, CharacterState>,
+    writing: bool,
+}
+
+// characters that changed since they were last written
+#[derive(Resource)]
+pub struct DirtyCharacters {
+    pub states: HashMap<Uuid, CharacterState>,
+    pub last_flush: Instant,
+}
+
+impl Default for DirtyCharacters {
+    fn default() -> Self {
+        DirtyCharacters {
+            states: HashMap::new(),
+            last_flush: Instant::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use common::{
+        character_store::InMemoryCharacterStore,
+        model::{Appearance, Character, CharacterClass, Movement, Vec3d},
+    };
+
+    use super::*;
+
+    // holds back the first save it is given, so a later one could finish before it
+    #[derive(Default)]
+    struct SlowStore {
+        inner: InMemoryCharacterStore,
+        held: AtomicBool,
+    }
+
+    #[async_trait]
+    impl CharacterStore for SlowStore {
+        async fn list(&self, account: &str) -> Result<Vec<Character>> {
+            self.inner.list(account).await
+        }
+
+        async fn find(&self, uuid: &str) -> Result<Option<Character>> {
+            self.inner.find(uuid).await
+        }
+
+        async fn insert(&self, character: Character) -> Result<()> {
+            self.inner.insert(character).await
+        }
+
+        async fn update(&self, character: Character) -> Result<()> {
+            self.inner.update(character).await
+        }
+
+        async fn remove(&self, uuid: &str) -> Result<()> {
+            self.inner.remove(uuid).await
+        }
+
+        async fn save_state(&self, uuid: &str, state: CharacterState) -> Result<()> {
+            if !self.held.swap(true, Ordering::SeqCst) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+
+            self.inner.save_state(uuid, state).await
+        }
+    }
+
+    fn character(id: Uuid) -> Character {
+        Character {
+            uuid: id.to_string(),
+            name: format!("Name{}", id.simple()),
+            name_key: format!("name{}", id.simple()),
+            account: "player".to_string(),
+            class: CharacterClass::Warrior,
+            appearance: Appearance::default(),
+            level: 1,
+            position: Vec3d::default(),
+            movement: Movement::default(),
+            zone: "start".to_string(),
+        }
+    }
+
+    fn state(x: f64) -> CharacterState {
+        CharacterState {
+            position: Vec3d { x, y: 0.0, z: 0.0 },
+            movement: Movement::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batches_are_written_in_chunks() {
+        let store = Arc::new(InMemoryCharacterStore::new());
+        let writer = Arc::new(CharacterWriter::new(
+            store.clone(),
+            PersistenceConfig {
+                interval: Duration::from_secs(30),
+                batch_size: 2,
+            },
+        ));
+
+        let ids: Vec<_> = (0..5).map(|_| Uuid::new_v4()).collect();
+
+        for id in &ids {
+            store.insert(character(*id)).await.unwrap();
+        }
+
+        writer.spawn_save(
+            ids.iter()
+                .enumerate()
+                .map(|(i, id)| (*id, state(i as f64)))
+                .collect(),
+        );
+
+        writer.settled().await;
+
+        for (i, id) in ids.iter().enumerate() {
+            let saved = store.find(&id.to_string()).await.unwrap().unwrap();
+            assert_eq!(saved.position.x, i as f64);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_later_saves_of_a_character_win() {
+        let store = Arc::new(SlowStore::default());
+        let writer = Arc::new(CharacterWriter::new(
+            store.clone(),
+            PersistenceConfig::default(),
+        ));
+
+        let id = Uuid::new_v4();
+        store.insert(character(id)).await.unwrap();
+
+        // the interval save is still being written when the logout save comes in
+        writer.spawn_save(vec![(id, state(1.0))]);
+        tokio::task::yield_now().await;
+        writer.spawn_save(vec![(id, state(2.0))]);
+
+        writer.settled().await;
+
+        let saved = store.find(&id.to_string()).await.unwrap().unwrap();
+        assert_eq!(saved.position.x, 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_flushed_waits_for_the_shutdown_flush() {
+        let writer = Arc::new(CharacterWriter::new(
+            Arc::new(InMemoryCharacterStore::new()),
+            PersistenceConfig::default(),
+        ));
+
+        writer.request_shutdown();
+        assert!(writer.shutdown_requested());
+
+        let waiting = tokio::spawn({
+            let writer = writer.clone();
+            async move { writer.flushed().await }
+        });
+
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        writer.spawn_save(vec![(Uuid::new_v4(), state(1.0))]);
+        writer.mark_flushed();
+
+        waiting.await.unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::authenticated_packet::AuthenticatedPacket,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// tells `target` it is logged in as `account`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatedCommand {
+    pub account: String,
+    pub target: SocketAddr,
+}
+
+impl AuthenticatedCommand {
+    pub fn new(account: String, target: SocketAddr) -> Self {
+        AuthenticatedCommand { account, target }
+    }
+}
+
+impl MapableCommand for AuthenticatedCommand {
+    type PacketType = AuthenticatedPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(AuthenticatedPacket {
+            account: self.account.clone(),
+        })
+    }
+}
+
+impl StateMappedCommand for AuthenticatedCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<AuthenticatedCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!(
+            "Enqueuing packets from {:?} authenticated commands",
+            commands.len()
+        );
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping authenticated command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(SendPacket::new(
+                serde_json::to_string(&packet).expect("Failed to serialize AuthenticatedPacket"),
+                OpCode::Authenticated,
+                TargetAddress::Targeted(vec![command.target]),
+            ));
+        }
+    }
+}
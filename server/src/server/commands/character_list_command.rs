@@ -0,0 +1,80 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::character_list_packet::{CharacterListPacket, CharacterSummary},
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// the characters `target` can enter the world with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterListCommand {
+    pub characters: Vec<CharacterSummary>,
+    pub target: SocketAddr,
+}
+
+impl CharacterListCommand {
+    pub fn new(characters: Vec<CharacterSummary>, target: SocketAddr) -> Self {
+        CharacterListCommand { characters, target }
+    }
+}
+
+impl MapableCommand for CharacterListCommand {
+    type PacketType = CharacterListPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(CharacterListPacket {
+            characters: self.characters.clone(),
+        })
+    }
+}
+
+impl StateMappedCommand for CharacterListCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<CharacterListCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!(
+            "Enqueuing packets from {:?} character list commands",
+            commands.len()
+        );
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping character list command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(SendPacket::new(
+                serde_json::to_string(&packet).expect("Failed to serialize CharacterListPacket"),
+                OpCode::CharacterList,
+                TargetAddress::Targeted(vec![command.target]),
+            ));
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::shared::net_id::NetId,
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::control_packet::ControlPacket,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// grants or revokes `target`'s control of an entity at runtime, see `control_system`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlCommand {
+    pub id: NetId,
+    pub target: SocketAddr,
+    pub granted: bool,
+    // whether the change went through, filled in once the system got to it
+    pub applied: Option<bool>,
+}
+
+impl ControlCommand {
+    pub fn grant(id: NetId, target: SocketAddr) -> Self {
+        ControlCommand {
+            id,
+            target,
+            granted: true,
+            applied: None,
+        }
+    }
+
+    pub fn revoke(id: NetId, target: SocketAddr) -> Self {
+        ControlCommand {
+            id,
+            target,
+            granted: false,
+            applied: None,
+        }
+    }
+}
+
+impl MapableCommand for ControlCommand {
+    type PacketType = ControlPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(ControlPacket {
+            id: self.id,
+            controlled: self.granted,
+        })
+    }
+}
+
+impl StateMappedCommand for ControlCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        // commands queued after this tick's schedule ran wait for the next one
+        let (commands, pending): (Vec<_>, Vec<_>) = world
+            .resource_mut::<UntargetedCommandContainer<ControlCommand>>()
+            .entries
+            .drain(..)
+            .partition(|command| command.applied.is_some());
+
+        world
+            .resource_mut::<UntargetedCommandContainer<ControlCommand>>()
+            .entries
+            .extend(pending);
+
+        debug!(
+            "Enqueuing packets from {:?} control commands",
+            commands.len()
+        );
+
+        // refused changes are only logged, the client's view of what it controls did not change
+        for command in commands
+            .into_iter()
+            .filter(|command| command.applied == Some(true))
+        {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping control command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(
+                SendPacket::new(
+                    serde_json::to_string(&packet).expect("Failed to serialize ControlPacket"),
+                    OpCode::Control,
+                    TargetAddress::Targeted(vec![command.target]),
+                )
+                .about(command.id),
+            );
+        }
+    }
+}
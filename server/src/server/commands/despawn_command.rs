@@ -0,0 +1,103 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::shared::net_id::NetId,
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::despawn_packet::{DespawnPacket, DespawnReason},
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// removes an entity from the world, see `despawn_system`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DespawnCommand {
+    pub id: NetId,
+    pub reason: DespawnReason,
+    // whoever had the entity in interest, filled in once the entity is actually gone
+    pub targets: Option<Vec<SocketAddr>>,
+}
+
+impl DespawnCommand {
+    pub fn new(id: NetId, reason: DespawnReason) -> Self {
+        DespawnCommand {
+            id,
+            reason,
+            targets: None,
+        }
+    }
+}
+
+impl MapableCommand for DespawnCommand {
+    type PacketType = DespawnPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(DespawnPacket {
+            id: self.id,
+            reason: self.reason,
+        })
+    }
+}
+
+impl StateMappedCommand for DespawnCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        // commands queued after this tick's schedule ran wait for the next one
+        let (commands, pending): (Vec<_>, Vec<_>) = world
+            .resource_mut::<UntargetedCommandContainer<DespawnCommand>>()
+            .entries
+            .drain(..)
+            .partition(|command| command.targets.is_some());
+
+        world
+            .resource_mut::<UntargetedCommandContainer<DespawnCommand>>()
+            .entries
+            .extend(pending);
+
+        debug!(
+            "Enqueuing packets from {:?} despawn commands",
+            commands.len()
+        );
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping despawn command: {}", e);
+                    continue;
+                }
+            };
+
+            let targets = command.targets.unwrap_or_default();
+
+            if targets.is_empty() {
+                continue;
+            }
+
+            sender.enqueue(
+                SendPacket::new(
+                    serde_json::to_string(&packet).expect("Failed to serialize DespawnPacket"),
+                    OpCode::Despawn,
+                    TargetAddress::Targeted(targets),
+                )
+                .about(command.id),
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::shared::net_id::NetId,
+    connections::session_token::SessionToken,
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::enown_packet::EnownPacket,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// hands an entity that already exists back to `target`, e.g. after it reattached to its session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnownCommand {
+    pub id: NetId,
+    pub session: SessionToken,
+    pub target: SocketAddr,
+}
+
+impl EnownCommand {
+    pub fn new(id: NetId, session: SessionToken, target: SocketAddr) -> Self {
+        EnownCommand {
+            id,
+            session,
+            target,
+        }
+    }
+}
+
+impl MapableCommand for EnownCommand {
+    type PacketType = EnownPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(EnownPacket {
+            id: self.id,
+            session: Some(self.session),
+        })
+    }
+}
+
+impl StateMappedCommand for EnownCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<EnownCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!("Enqueuing packets from {:?} enown commands", commands.len());
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping enown command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(
+                SendPacket::new(
+                    serde_json::to_string(&packet).expect("Failed to serialize EnownPacket"),
+                    OpCode::Enown,
+                    TargetAddress::Targeted(vec![command.target]),
+                )
+                .about(command.id),
+            );
+        }
+    }
+}
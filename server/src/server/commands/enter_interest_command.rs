@@ -0,0 +1,96 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::{position::Position, shared::net_id::NetId},
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::spawn_packet::SpawnPacket,
+    replication::interest::relevance,
+    systems::{
+        networked_index::NetworkedIndex, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+// an entity came into range of `target`, which has to be told to spawn it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnterInterestCommand {
+    pub id: NetId,
+    pub target: SocketAddr,
+}
+
+impl EnterInterestCommand {
+    pub fn new(id: NetId, target: SocketAddr) -> Self {
+        EnterInterestCommand { id, target }
+    }
+}
+
+impl MapableCommand for EnterInterestCommand {
+    type PacketType = SpawnPacket;
+
+    fn map_to_packet(&self, world: &mut World) -> Result<Self::PacketType, Error> {
+        let entity = world.resource::<NetworkedIndex>().entity(self.id)?;
+
+        let location = world
+            .get::<Position>(entity)
+            .map(|position| position.position.clone())
+            .ok_or(Error::EntityNotFound(self.id))?;
+
+        Ok(SpawnPacket {
+            id: self.id,
+            location,
+        })
+    }
+}
+
+impl StateMappedCommand for EnterInterestCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<EnterInterestCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!(
+            "Enqueuing packets from {:?} enter interest commands",
+            commands.len()
+        );
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping enter interest command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(
+                SendPacket::new(
+                    serde_json::to_string(&packet).expect("Failed to serialize SpawnPacket"),
+                    OpCode::Spawn,
+                    TargetAddress::Targeted(vec![command.target]),
+                )
+                .about(command.id)
+                .with_relevance(relevance(&world, command.target, command.id)),
+            );
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::error_packet::{ErrorCode, ErrorPacket},
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorCommand {
+    pub code: ErrorCode,
+    pub message: String,
+    pub target: TargetAddress,
+}
+
+impl ErrorCommand {
+    pub fn new(code: ErrorCode, message: String, target: TargetAddress) -> Self {
+        ErrorCommand {
+            code,
+            message,
+            target,
+        }
+    }
+}
+
+impl MapableCommand for ErrorCommand {
+    type PacketType = ErrorPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(ErrorPacket {
+            code: self.code,
+            message: self.message.clone(),
+        })
+    }
+}
+
+impl StateMappedCommand for ErrorCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<ErrorCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!("Enqueuing packets from {:?} error commands", commands.len());
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping error command: {}", e);
+                    continue;
+                }
+            };
+
+            sender.enqueue(SendPacket::new(
+                serde_json::to_string(&packet).expect("Failed to serialize ErrorPacket"),
+                OpCode::Error,
+                command.target,
+            ));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::shared::net_id::NetId,
+    error::Error,
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::despawn_packet::{DespawnPacket, DespawnReason},
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// an entity went out of range of `target`, which should drop it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaveInterestCommand {
+    pub id: NetId,
+    pub target: SocketAddr,
+}
+
+impl LeaveInterestCommand {
+    pub fn new(id: NetId, target: SocketAddr) -> Self {
+        LeaveInterestCommand { id, target }
+    }
+}
+
+impl MapableCommand for LeaveInterestCommand {
+    type PacketType = DespawnPacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(DespawnPacket {
+            id: self.id,
+            reason: DespawnReason::OutOfRange,
+        })
+    }
+}
+
+impl StateMappedCommand for LeaveInterestCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<LeaveInterestCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!(
+            "Enqueuing packets from {:?} leave interest commands",
+            commands.len()
+        );
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping leave interest command: {}", e);
+                    continue;
+                }
+            };
+
+            // not scaled by relevance, so it can never fall behind a later spawn of the same entity
+            sender.enqueue(
+                SendPacket::new(
+                    serde_json::to_string(&packet).expect("Failed to serialize DespawnPacket"),
+                    OpCode::Despawn,
+                    TargetAddress::Targeted(vec![command.target]),
+                )
+                .about(command.id),
+            );
+        }
+    }
+}
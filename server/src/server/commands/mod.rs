@@ -2,16 +2,24 @@ use std::sync::{Arc, Mutex, RwLock};
 
 use bevy_ecs::world::World;
 
-use crate::server::packet_sender::packet_sender::ServerPacketSender;
+use crate::server::{error::Error, packet_sender::packet_sender::ServerPacketSender};
 
+pub mod authenticated_command;
+pub mod character_list_command;
+pub mod control_command;
+pub mod despawn_command;
+pub mod enown_command;
+pub mod enter_interest_command;
+pub mod error_command;
+pub mod leave_interest_command;
 pub mod move_command;
-pub mod moved_command;
+pub mod replicate_command;
 pub mod spawn_command;
 
 pub trait MapableCommand {
     type PacketType;
 
-    fn map_to_packet(&self, world: &mut World) -> Self::PacketType;
+    fn map_to_packet(&self, world: &mut World) -> Result<Self::PacketType, Error>;
 }
 
 pub trait StateMappedCommand {
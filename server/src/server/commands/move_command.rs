@@ -5,16 +5,17 @@ use serde::{Deserialize, Serialize};
 use crate::server::{
     commands::{MapableCommand, StateMappedCommand},
     components::{
-        movement_state::MovementStateType, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
+        movement_state::MovementStateType,
+        shared::{net_id::NetId, vec3d::Vec3d},
     },
-    protocols::send::moved_packet::MovedPacket,
+    error::Error,
+    protocols::recv::move_packet::MovePacket,
     systems::command_container::CommandContainer,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveCommand {
-    pub id: String,
+    pub id: NetId,
     pub x: f64,
     pub y: f64,
     pub z: f64,
@@ -22,26 +23,19 @@ pub struct MoveCommand {
 }
 
 impl MoveCommand {
-    pub fn new(id: String, x: f64, y: f64, z: f64, state: MovementStateType) -> Self {
+    pub fn new(id: NetId, x: f64, y: f64, z: f64, state: MovementStateType) -> Self {
         MoveCommand { id, x, y, z, state }
     }
 }
 
 impl MapableCommand for MoveCommand {
-    type PacketType = MovedPacket;
+    type PacketType = MovePacket;
 
-    fn map_to_packet(&self, world: &mut World) -> Self::PacketType {
-        let current_position = world
-            .query::<(&Position, &Networked)>()
-            .iter(&world)
-            .find(|(_, networked)| networked.id == self.id)
-            .map(|(position, _)| position.position.clone())
-            .expect(&format!("Entity with ID {} not found", self.id));
-
-        MovedPacket {
-            networked_id: self.id.clone(),
-            vector: Vec3d::new(current_position.x, current_position.y, current_position.z),
-        }
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(MovePacket::new(
+            Vec3d::new(self.x, self.y, self.z),
+            self.state.clone(),
+        ))
     }
 }
 
@@ -59,8 +53,8 @@ impl StateMappedCommand for MoveCommand {
             world
                 .resource_mut::<CommandContainer<MoveCommand>>()
                 .entries
-                .iter()
-                .map(|(_, queue)| queue.len())
+                .values()
+                .map(|queue| queue.len())
                 .sum::<usize>()
         );
 
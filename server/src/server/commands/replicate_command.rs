@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex, RwLock};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::server::{
+    commands::{MapableCommand, StateMappedCommand},
+    components::shared::net_id::NetId,
+    error::Error,
+    opcode::{OpCode, Reliability},
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::replicate_packet::{ComponentUpdate, ReplicatePacket},
+    replication::interest::relevance,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicateCommand {
+    pub id: NetId,
+    pub component: ComponentUpdate,
+    pub reliability: Reliability,
+    pub target: TargetAddress,
+}
+
+impl ReplicateCommand {
+    pub fn new(
+        id: NetId,
+        component: ComponentUpdate,
+        reliability: Reliability,
+        target: TargetAddress,
+    ) -> Self {
+        ReplicateCommand {
+            id,
+            component,
+            reliability,
+            target,
+        }
+    }
+}
+
+impl MapableCommand for ReplicateCommand {
+    type PacketType = ReplicatePacket;
+
+    fn map_to_packet(&self, _world: &mut World) -> Result<Self::PacketType, Error> {
+        Ok(ReplicatePacket {
+            id: self.id,
+            components: vec![self.component.clone()],
+        })
+    }
+}
+
+impl StateMappedCommand for ReplicateCommand {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let commands: Vec<_> = world
+            .resource_mut::<UntargetedCommandContainer<ReplicateCommand>>()
+            .entries
+            .drain(..)
+            .collect();
+
+        debug!(
+            "Enqueuing packets from {:?} replicate commands",
+            commands.len()
+        );
+
+        // components of the same entity going the same way share one packet
+        let mut batches: Vec<(ReplicatePacket, Reliability, TargetAddress)> = Vec::new();
+
+        for command in commands {
+            trace!("Processing command: {:?}", command);
+
+            let packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping replicate command: {}", e);
+                    continue;
+                }
+            };
+
+            match batches.iter_mut().find(|(batch, reliability, target)| {
+                batch.id == packet.id
+                    && *reliability == command.reliability
+                    && *target == command.target
+            }) {
+                Some((batch, _, _)) => batch.components.extend(packet.components),
+                None => batches.push((packet, command.reliability, command.target)),
+            }
+        }
+
+        for (packet, reliability, target) in batches {
+            let opcode = match reliability {
+                Reliability::Reliable => OpCode::Replicate,
+                Reliability::Unreliable => OpCode::ReplicateUnreliable,
+            };
+
+            let data = serde_json::to_string(&packet).expect("Failed to serialize ReplicatePacket");
+
+            // split per connection, each one weighs the entity by its own distance to it
+            match target {
+                TargetAddress::Broadcast => sender.enqueue(
+                    SendPacket::new(data, opcode, TargetAddress::Broadcast).about(packet.id),
+                ),
+                TargetAddress::Targeted(addrs) => {
+                    for addr in addrs {
+                        sender.enqueue(
+                            SendPacket::new(
+                                data.clone(),
+                                opcode,
+                                TargetAddress::Targeted(vec![addr]),
+                            )
+                            .about(packet.id)
+                            .with_relevance(relevance(&world, addr, packet.id)),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
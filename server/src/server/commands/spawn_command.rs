@@ -1,36 +1,47 @@
 use std::sync::{Arc, Mutex, RwLock};
 
 use bevy_ecs::world::World;
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::server::{
     commands::{MapableCommand, StateMappedCommand},
     components::{
-        movement_state::MovementStateType, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
+        movement_state::MovementStateType,
+        shared::{net_id::NetId, vec3d::Vec3d},
     },
+    connections::{connection_manager::Connections, session_token::SessionToken},
+    error::Error,
     opcode::OpCode,
     packet_sender::{
         packet_sender::{PacketSender, ServerPacketSender},
         send_packet::SendPacket,
         TargetAddress,
     },
-    protocols::send::{enown_packet::EnownPacket, spawn_packet::SpawnPacket},
-    systems::untargeted_command_container::UntargetedCommandContainer,
+    protocols::send::enown_packet::EnownPacket,
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::{
+        net_id_allocator::NetIdAllocator, networked_index::NetworkedIndex,
+        untargeted_command_container::UntargetedCommandContainer,
+    },
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EntityComponent {
     Position(f64, f64, f64),
-    Networked(String),
-    MovementState(MovementStateType, f64),
+    Networked(NetId),
+    Character(Uuid),
+    // state, velocity and direction
+    MovementState(MovementStateType, f64, Vec3d),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpawnCommand {
     pub components: Vec<EntityComponent>,
     pub owning_connection: TargetAddress,
+    // handed to the owner along with the entity
+    pub session: Option<SessionToken>,
 }
 
 impl SpawnCommand {
@@ -38,30 +49,77 @@ impl SpawnCommand {
         SpawnCommand {
             components,
             owning_connection,
+            session: None,
+        }
+    }
+
+    pub fn with_session(mut self, session: SessionToken) -> Self {
+        self.session = Some(session);
+        self
+    }
+
+    fn networked(&self) -> Option<NetId> {
+        self.components.iter().find_map(|c| {
+            if let EntityComponent::Networked(id) = c {
+                Some(*id)
+            } else {
+                None
+            }
+        })
+    }
+
+    // gives back the id reserved for a spawn that never made it into the world, and takes the
+    // entity away from its owner again
+    pub fn release(&self, world: &mut World) {
+        let Some(id) = self.networked() else {
+            return;
+        };
+
+        // once spawned the id is the entity's, `Networked` frees it when that goes
+        if world
+            .get_resource::<NetworkedIndex>()
+            .is_some_and(|index| index.get(id).is_none())
+        {
+            if let Some(mut allocator) = world.get_resource_mut::<NetIdAllocator>() {
+                allocator.free(id);
+            }
+        }
+
+        let (TargetAddress::Targeted(addrs), Some(connections)) =
+            (&self.owning_connection, world.get_resource::<Connections>())
+        else {
+            return;
+        };
+
+        let mut connections = connections
+            .0
+            .write()
+            .expect("Failed to get write lock on connection manager");
+
+        for addr in addrs {
+            connections.remove_entity(*addr, id);
         }
     }
 }
 
 impl MapableCommand for SpawnCommand {
-    type PacketType = SpawnPacket;
-
-    fn map_to_packet(&self, _world: &mut World) -> Self::PacketType {
-        let networked = self
-            .components
-            .iter()
-            .find_map(|c| {
-                if let EntityComponent::Networked(id) = c {
-                    Some(id.clone())
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_default();
+    type PacketType = EnownPacket;
 
-        SpawnPacket {
-            location: Vec3d::new(0.0, 0.0, 0.0),
-            id: networked,
+    fn map_to_packet(&self, world: &mut World) -> Result<Self::PacketType, Error> {
+        let networked = self.networked().ok_or(Error::MissingNetworkedComponent)?;
+
+        // the owner must not be handed an entity that is not in the world
+        if world
+            .get_resource::<NetworkedIndex>()
+            .is_some_and(|index| index.get(networked).is_none())
+        {
+            return Err(Error::EntityNotFound(networked));
         }
+
+        Ok(EnownPacket {
+            id: networked,
+            session: self.session,
+        })
     }
 }
 
@@ -70,69 +128,90 @@ impl StateMappedCommand for SpawnCommand {
         let mut world = world.write().expect("Failed to get write lock to world");
         let sender = sender.lock().expect("Failed to lock sender");
 
-        debug!(
-            "Enqueuing packets from {:?} spawn commands",
-            world
-                .resource_mut::<UntargetedCommandContainer<SpawnCommand>>()
-                .entries
-                .len()
-        );
-
-        // Also queue already existing packets
-
-        let networked_entities = world
-            .query::<(&Networked, &Position)>()
-            .iter(&world)
-            .map(|(networked, position)| SpawnPacket {
-                id: networked.id.clone(),
-                location: Vec3d::new(
-                    position.position.x,
-                    position.position.y,
-                    position.position.z,
-                ),
-            })
-            .collect::<Vec<SpawnPacket>>();
-
         let commands: Vec<_> = world
             .resource_mut::<UntargetedCommandContainer<SpawnCommand>>()
             .entries
             .drain(..)
             .collect();
 
+        debug!("Enqueuing packets from {:?} spawn commands", commands.len());
+
+        // only the owner hears about it here, everyone in range gets the spawn through interest
         for command in commands {
             trace!("Processing command: {:?}", command);
 
-            let packet = command.map_to_packet(&mut world);
-
-            let packet_data =
-                serde_json::to_string(&packet).expect("Failed to serialize SpawnCommand");
-
-            sender.enqueue(SendPacket::new(
-                packet_data.clone(),
-                OpCode::Spawn,
-                TargetAddress::Broadcast,
-            ));
-
-            networked_entities
-                .iter()
-                .filter(|n| n.id != packet.id)
-                .for_each(|spawn| {
-                    sender.enqueue(SendPacket::new(
-                        serde_json::to_string(&spawn).expect("Failed to serialize SpawnPacket"),
-                        OpCode::Spawn,
-                        TargetAddress::Broadcast,
-                    ));
-                });
-
-            let enown_packet = EnownPacket {
-                id: packet.id.clone(),
+            let enown_packet = match command.map_to_packet(&mut world) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Skipping spawn command: {}", e);
+                    command.release(&mut world);
+                    continue;
+                }
             };
 
             sender.enqueue(SendPacket::new(
                 serde_json::to_string(&enown_packet).expect("Failed to serialize EnownPacket"),
                 OpCode::Enown,
-                command.owning_connection.clone(),
+                command.owning_connection,
             ));
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use crate::server::{
+        components::networked::Networked, connections::connection_manager::ConnectionManager,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_dropped_spawns_release_their_id() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let connections = Arc::new(RwLock::new(ConnectionManager::default()));
+
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(Connections(connections.clone()));
+
+        let lost = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        connections.write().unwrap().add_entity(addr, lost);
+
+        let command = SpawnCommand::new(
+            vec![EntityComponent::Networked(lost)],
+            TargetAddress::Targeted(vec![addr]),
+        );
+
+        assert!(matches!(
+            command.map_to_packet(&mut world),
+            Err(Error::EntityNotFound(_))
+        ));
+
+        command.release(&mut world);
+
+        assert!(!world.resource::<NetIdAllocator>().is_alive(lost));
+        assert!(!connections.read().unwrap().is_authorized(addr, lost));
+        assert!(connections.read().unwrap().get_character_id(addr).is_none());
+
+        // a spawned entity keeps its id, it is freed once the entity goes
+        let spawned = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        world.spawn(Networked { id: spawned });
+
+        let command = SpawnCommand::new(
+            vec![EntityComponent::Networked(spawned)],
+            TargetAddress::Targeted(vec![addr]),
+        );
+
+        assert!(command.map_to_packet(&mut world).is_ok());
+
+        command.release(&mut world);
+        assert!(world.resource::<NetIdAllocator>().is_alive(spawned));
+    }
+}
@@ -0,0 +1,8 @@
+use bevy_ecs::component::Component;
+use uuid::Uuid;
+
+// persistent identity of a player character, the wire only ever sees its `NetId`
+#[derive(Component)]
+pub struct Character {
+    pub id: Uuid,
+}
@@ -1,4 +1,6 @@
+pub mod character;
 pub mod movement_state;
 pub mod networked;
+pub mod owner;
 pub mod position;
 pub mod shared;
@@ -1,17 +1,37 @@
 use bevy_ecs::component::Component;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::server::components::shared::vec3d::Vec3d;
+use crate::server::{components::shared::vec3d::Vec3d, replication::replicated::Replicated};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum MovementStateType {
     Moving,
     Stopped,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct MovementState {
     pub current_state: MovementStateType,
     pub velocity: f64,
     pub direction: Vec3d,
 }
+
+impl MovementState {
+    // where an entity at `from` ends up after `elapsed_secs`, the client extrapolates with the same rule
+    pub fn extrapolate(&self, from: &Vec3d, elapsed_secs: f64) -> Vec3d {
+        if self.current_state == MovementStateType::Stopped {
+            return from.clone();
+        }
+
+        Vec3d::new(
+            from.x + self.direction.x * self.velocity * elapsed_secs,
+            from.y + self.direction.y * self.velocity * elapsed_secs,
+            from.z + self.direction.z * self.velocity * elapsed_secs,
+        )
+    }
+}
+
+impl Replicated for MovementState {
+    const NAME: &'static str = "movement_state";
+}
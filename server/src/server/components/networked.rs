@@ -1,6 +1,107 @@
-use bevy_ecs::component::Component;
+use bevy_ecs::{
+    component::{Component, HookContext},
+    world::DeferredWorld,
+};
+
+use crate::server::{
+    components::shared::net_id::NetId,
+    systems::{net_id_allocator::NetIdAllocator, networked_index::NetworkedIndex},
+};
 
 #[derive(Component)]
+#[component(
+    on_insert = index_networked,
+    on_replace = unindex_networked,
+    on_remove = release_net_id
+)]
 pub struct Networked {
-    pub id: String,
+    pub id: NetId,
+}
+
+fn index_networked(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(id) = world.get::<Networked>(entity).map(|n| n.id) else {
+        return;
+    };
+
+    if let Some(mut index) = world.get_resource_mut::<NetworkedIndex>() {
+        index.insert(id, entity);
+    }
+}
+
+// on_replace also runs on removal and despawn, while the old value is still readable
+fn unindex_networked(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(id) = world.get::<Networked>(entity).map(|n| n.id) else {
+        return;
+    };
+
+    if let Some(mut index) = world.get_resource_mut::<NetworkedIndex>() {
+        index.remove(id, entity);
+    }
+}
+
+// the id only goes back to the allocator once the component is gone, not when it is replaced
+fn release_net_id(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    let Some(id) = world.get::<Networked>(entity).map(|n| n.id) else {
+        return;
+    };
+
+    if let Some(mut allocator) = world.get_resource_mut::<NetIdAllocator>() {
+        allocator.free(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+
+    use super::*;
+
+    fn world() -> World {
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world
+    }
+
+    fn allocate(world: &mut World) -> NetId {
+        world.resource_mut::<NetIdAllocator>().allocate().unwrap()
+    }
+
+    #[test]
+    fn test_spawn_indexes_entity() {
+        let mut world = world();
+        let id = allocate(&mut world);
+
+        let entity = world.spawn(Networked { id }).id();
+
+        assert_eq!(world.resource::<NetworkedIndex>().get(id), Some(entity));
+    }
+
+    #[test]
+    fn test_despawn_unindexes_entity_and_frees_id() {
+        let mut world = world();
+        let id = allocate(&mut world);
+
+        let entity = world.spawn(Networked { id }).id();
+        world.despawn(entity);
+
+        assert!(world.resource::<NetworkedIndex>().get(id).is_none());
+        assert!(world.resource::<NetworkedIndex>().entity(id).is_err());
+        assert!(!world.resource::<NetIdAllocator>().is_alive(id));
+    }
+
+    #[test]
+    fn test_replacing_id_moves_index_entry() {
+        let mut world = world();
+        let a = allocate(&mut world);
+        let b = allocate(&mut world);
+
+        let entity = world.spawn(Networked { id: a }).id();
+        world.entity_mut(entity).insert(Networked { id: b });
+
+        let index = world.resource::<NetworkedIndex>();
+        assert!(index.get(a).is_none());
+        assert_eq!(index.get(b), Some(entity));
+        assert_eq!(index.len(), 1);
+    }
 }
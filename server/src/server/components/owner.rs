@@ -0,0 +1,8 @@
+use std::net::SocketAddr;
+
+use bevy_ecs::component::Component;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Owner {
+    pub addr: SocketAddr,
+}
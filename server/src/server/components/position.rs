@@ -1,8 +1,21 @@
-use bevy_ecs::component::Component;
+use bevy_ecs::{
+    component::{Component, HookContext},
+    world::DeferredWorld,
+};
+
+use crate::server::systems::spatial_grid::SpatialGrid;
 
 use super::shared::vec3d::Vec3d;
 
+// inserts and moves reach the grid through `update_spatial_grid`, removals through the hook
 #[derive(Component)]
+#[component(on_remove = remove_from_grid)]
 pub struct Position {
     pub position: Vec3d,
 }
+
+fn remove_from_grid(mut world: DeferredWorld, HookContext { entity, .. }: HookContext) {
+    if let Some(mut grid) = world.get_resource_mut::<SpatialGrid>() {
+        grid.remove(entity);
+    }
+}
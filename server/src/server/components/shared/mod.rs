@@ -1 +1,3 @@
+pub mod net_id;
+pub mod quantized_vec3;
 pub mod vec3d;
@@ -0,0 +1,36 @@
+use std::fmt::Display;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// lower 24 bits index the slot, upper 8 bits count how many times the slot has been reused
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(transparent)]
+pub struct NetId(pub u32);
+
+impl NetId {
+    pub const INDEX_BITS: u32 = 24;
+    pub const MAX_INDEX: u32 = (1 << Self::INDEX_BITS) - 1;
+
+    pub fn new(index: u32, generation: u8) -> Self {
+        debug_assert!(index <= Self::MAX_INDEX, "NetId index out of range");
+
+        NetId((u32::from(generation) << Self::INDEX_BITS) | (index & Self::MAX_INDEX))
+    }
+
+    pub fn index(self) -> u32 {
+        self.0 & Self::MAX_INDEX
+    }
+
+    pub fn generation(self) -> u8 {
+        (self.0 >> Self::INDEX_BITS) as u8
+    }
+}
+
+impl Display for NetId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index(), self.generation())
+    }
+}
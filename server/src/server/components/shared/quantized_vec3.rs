@@ -0,0 +1,60 @@
+use std::ops::{Add, Sub};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::vec3d::Vec3d;
+
+// fixed-point position, one unit is `precision` world units
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct QuantizedVec3 {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl QuantizedVec3 {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        QuantizedVec3 { x, y, z }
+    }
+
+    pub fn quantize(vector: &Vec3d, precision: f64) -> Self {
+        QuantizedVec3 {
+            x: (vector.x / precision).round() as i32,
+            y: (vector.y / precision).round() as i32,
+            z: (vector.z / precision).round() as i32,
+        }
+    }
+
+    pub fn to_vec3d(self, precision: f64) -> Vec3d {
+        Vec3d::new(
+            f64::from(self.x) * precision,
+            f64::from(self.y) * precision,
+            f64::from(self.z) * precision,
+        )
+    }
+}
+
+impl Add for QuantizedVec3 {
+    type Output = QuantizedVec3;
+
+    fn add(self, other: QuantizedVec3) -> QuantizedVec3 {
+        QuantizedVec3::new(
+            self.x.wrapping_add(other.x),
+            self.y.wrapping_add(other.y),
+            self.z.wrapping_add(other.z),
+        )
+    }
+}
+
+impl Sub for QuantizedVec3 {
+    type Output = QuantizedVec3;
+
+    fn sub(self, other: QuantizedVec3) -> QuantizedVec3 {
+        QuantizedVec3::new(
+            self.x.wrapping_sub(other.x),
+            self.y.wrapping_sub(other.y),
+            self.z.wrapping_sub(other.z),
+        )
+    }
+}
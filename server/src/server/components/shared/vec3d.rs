@@ -1,7 +1,8 @@
 use bevy_ecs::component::Component;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+#[derive(Component, Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct Vec3d {
     pub x: f64,
     pub y: f64,
@@ -24,4 +25,25 @@ impl Vec3d {
     pub fn length(&self) -> f64 {
         (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
     }
+
+    pub fn distance(&self, other: &Vec3d) -> f64 {
+        Vec3d::new(self.x - other.x, self.y - other.y, self.z - other.z).length()
+    }
+}
+
+// the stored form in `common::model`
+impl From<&common::model::Vec3d> for Vec3d {
+    fn from(stored: &common::model::Vec3d) -> Self {
+        Vec3d::new(stored.x, stored.y, stored.z)
+    }
+}
+
+impl From<&Vec3d> for common::model::Vec3d {
+    fn from(vec: &Vec3d) -> Self {
+        common::model::Vec3d {
+            x: vec.x,
+            y: vec.y,
+            z: vec.z,
+        }
+    }
 }
@@ -0,0 +1,120 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use crate::server::{components::shared::net_id::NetId, connections::session_token::SessionToken};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthStatus {
+    // has sent packets, but not logged in
+    Connected,
+    // logged in to an account, no character yet
+    Authenticated,
+    // entered the world and controls a player character
+    InWorld,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionStats {
+    pub packets_received: u64,
+    pub bytes_received: u64,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    // out of order or over the rate limit
+    pub packets_dropped: u64,
+    // actions targeting entities the connection does not control
+    pub actions_rejected: u64,
+}
+
+// token bucket over incoming packets
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32) -> Self {
+        RateLimit {
+            tokens: burst as f64,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    pub fn try_take(&mut self, per_second: u32, burst: u32, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * per_second as f64).min(burst as f64);
+        self.refilled_at = now;
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+// everything the server knows about one peer
+#[derive(Debug, Clone)]
+pub struct Connection {
+    pub addr: SocketAddr,
+    // highest packet id received so far, older ones are dropped as out of order
+    pub last_received_id: Option<u128>,
+    pub next_sent_id: u128,
+    // smoothed round trip time, measured through snapshot acks
+    pub rtt: Option<Duration>,
+    pub auth: AuthStatus,
+    // the account it logged in to, required to enter the world
+    pub account: Option<String>,
+    // issued on enter, a packet carrying it moves the connection to whatever address it came from
+    pub session: Option<SessionToken>,
+    pub player_character: Option<NetId>,
+    // the player character always comes first
+    pub entities: Vec<NetId>,
+    pub rate_limit: RateLimit,
+    pub stats: ConnectionStats,
+    pub connected_at: Instant,
+    pub last_seen: Instant,
+    // fell too far behind on what it is sent, it is dropped on the next expiry without a grace period
+    pub backlogged: bool,
+}
+
+impl Connection {
+    pub fn new(addr: SocketAddr, burst: u32) -> Self {
+        let now = Instant::now();
+
+        Connection {
+            addr,
+            last_received_id: None,
+            next_sent_id: 0,
+            rtt: None,
+            auth: AuthStatus::Connected,
+            account: None,
+            session: None,
+            player_character: None,
+            entities: vec![],
+            rate_limit: RateLimit::new(burst),
+            stats: ConnectionStats::default(),
+            connected_at: now,
+            last_seen: now,
+            backlogged: false,
+        }
+    }
+
+    pub fn owns(&self, id: NetId) -> bool {
+        self.entities.contains(&id)
+    }
+
+    pub fn record_rtt(&mut self, sample: Duration) {
+        // same smoothing as TCP, a single late ack does not swing it
+        self.rtt = Some(match self.rtt {
+            Some(rtt) => rtt.mul_f64(0.875) + sample.mul_f64(0.125),
+            None => sample,
+        });
+    }
+}
@@ -0,0 +1,624 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::resource::Resource;
+use log::{debug, info, warn};
+
+use crate::server::{
+    components::shared::net_id::NetId,
+    connections::{
+        connection::{AuthStatus, Connection},
+        session_token::SessionToken,
+    },
+    error::Error,
+    state::authorization_handler::AuthorizationHandlerTrait,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionConfig {
+    // a peer silent for this long is disconnected
+    pub timeout: Duration,
+    pub packets_per_second: u32,
+    // packets a peer may send in a burst above its steady rate
+    pub burst: u32,
+    // how long a timed out session keeps its entities in the world, waiting for the client to come back
+    pub grace_period: Duration,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig {
+            timeout: Duration::from_secs(30),
+            packets_per_second: 30,
+            burst: 60,
+            grace_period: Duration::from_secs(60),
+        }
+    }
+}
+
+// the one place per-peer state lives, shared by the receiver, the sender, the handlers and systems
+pub struct ConnectionManager {
+    config: ConnectionConfig,
+    connections: HashMap<SocketAddr, Connection>,
+    // timed out sessions within their grace period
+    detached: HashMap<SessionToken, Connection>,
+    // (from, to) addresses of connections that moved since the last `take_migrations`
+    migrations: Vec<(SocketAddr, SocketAddr)>,
+}
+
+// the same manager the receiver and sender use, exposed to systems
+#[derive(Resource, Clone)]
+pub struct Connections(pub Arc<RwLock<ConnectionManager>>);
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new(ConnectionConfig::default())
+    }
+}
+
+impl ConnectionManager {
+    pub fn new(config: ConnectionConfig) -> Self {
+        ConnectionManager {
+            config,
+            connections: HashMap::new(),
+            detached: HashMap::new(),
+            migrations: vec![],
+        }
+    }
+
+    pub fn get(&self, addr: SocketAddr) -> Option<&Connection> {
+        self.connections.get(&addr)
+    }
+
+    pub fn get_mut(&mut self, addr: SocketAddr) -> Option<&mut Connection> {
+        self.connections.get_mut(&addr)
+    }
+
+    pub fn addrs(&self) -> HashSet<SocketAddr> {
+        self.connections.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    pub fn register(&mut self, addr: SocketAddr) -> &mut Connection {
+        let burst = self.config.burst;
+
+        self.connections.entry(addr).or_insert_with(|| {
+            info!("Registering address {:?}", addr);
+            Connection::new(addr, burst)
+        })
+    }
+
+    // accounts for an incoming packet, an error means it must be dropped
+    pub fn receive(
+        &mut self,
+        addr: SocketAddr,
+        packet_id: u128,
+        bytes: usize,
+        session: Option<SessionToken>,
+    ) -> Result<(), Error> {
+        let ConnectionConfig {
+            packets_per_second,
+            burst,
+            ..
+        } = self.config;
+        let now = Instant::now();
+
+        // an unknown session, e.g. from before a server restart, is simply a new connection
+        if let Some(session) = session {
+            if !self.connections.contains_key(&addr) {
+                self.migrate(session, addr);
+            }
+        }
+
+        let connection = self.register(addr);
+
+        connection.last_seen = now;
+        connection.stats.packets_received += 1;
+        connection.stats.bytes_received += bytes as u64;
+
+        if connection
+            .last_received_id
+            .is_some_and(|last| last > packet_id)
+        {
+            connection.stats.packets_dropped += 1;
+            return Err(Error::OutOfOrderPacket(packet_id));
+        }
+
+        if !connection
+            .rate_limit
+            .try_take(packets_per_second, burst, now)
+        {
+            connection.stats.packets_dropped += 1;
+            return Err(Error::RateLimited(addr));
+        }
+
+        connection.last_received_id = Some(packet_id);
+
+        Ok(())
+    }
+
+    // moves the connection holding `session` over to `addr`, whether it is live or detached
+    fn migrate(&mut self, session: SessionToken, addr: SocketAddr) {
+        let previous = self
+            .connections
+            .values()
+            .find(|connection| connection.session == Some(session))
+            .map(|connection| connection.addr);
+
+        let Some(mut connection) = (match previous {
+            Some(previous) => self.connections.remove(&previous),
+            None => self.detached.remove(&session),
+        }) else {
+            warn!("{:?} presented unknown session {}", addr, session);
+            return;
+        };
+
+        info!(
+            "Session {} moved from {:?} to {:?}",
+            session, connection.addr, addr
+        );
+
+        self.migrations.push((connection.addr, addr));
+
+        // a restarted client counts its packets from zero again
+        connection.addr = addr;
+        connection.last_received_id = None;
+        connection.last_seen = Instant::now();
+        self.connections.insert(addr, connection);
+    }
+
+    // connections that moved to another address since the last call, as (from, to)
+    pub fn take_migrations(&mut self) -> Vec<(SocketAddr, SocketAddr)> {
+        std::mem::take(&mut self.migrations)
+    }
+
+    pub fn next_packet_id(&mut self, addr: SocketAddr) -> u128 {
+        let connection = self.register(addr);
+
+        let id = connection.next_sent_id;
+        connection.next_sent_id += 1;
+
+        id
+    }
+
+    pub fn record_sent(&mut self, addr: SocketAddr, bytes: usize) {
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.stats.packets_sent += 1;
+            connection.stats.bytes_sent += bytes as u64;
+        }
+    }
+
+    pub fn record_rtt(&mut self, addr: SocketAddr, rtt: Duration) {
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.record_rtt(rtt);
+        }
+    }
+
+    pub fn disconnect(&mut self, addr: SocketAddr) -> Option<Connection> {
+        let connection = self.connections.remove(&addr)?;
+
+        info!(
+            "Disconnected {:?} after {:?}",
+            addr,
+            connection.connected_at.elapsed()
+        );
+
+        Some(connection)
+    }
+
+    pub fn mark_backlogged(&mut self, addr: SocketAddr) {
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.backlogged = true;
+        }
+    }
+
+    // disconnects every peer that has been silent for longer than the timeout, sessions are
+    // detached first and only returned once their grace period is over as well
+    pub fn expire(&mut self, now: Instant) -> Vec<Connection> {
+        let ConnectionConfig {
+            timeout,
+            grace_period,
+            ..
+        } = self.config;
+
+        let silent_for =
+            |connection: &Connection| now.saturating_duration_since(connection.last_seen);
+
+        let timed_out: Vec<SocketAddr> = self
+            .connections
+            .values()
+            .filter(|connection| connection.backlogged || silent_for(connection) > timeout)
+            .map(|connection| connection.addr)
+            .collect();
+
+        if !timed_out.is_empty() {
+            debug!("Timing out {:?}", timed_out);
+        }
+
+        let mut expired = vec![];
+
+        for addr in timed_out {
+            let Some(connection) = self.disconnect(addr) else {
+                continue;
+            };
+
+            match connection.session {
+                // the packets it was behind on are gone, coming back would not catch it up
+                Some(session) if !connection.backlogged => {
+                    info!("Holding session {} for {:?}", session, grace_period);
+                    self.detached.insert(session, connection);
+                }
+                _ => expired.push(connection),
+            }
+        }
+
+        let lapsed: Vec<SessionToken> = self
+            .detached
+            .iter()
+            .filter(|(_, connection)| silent_for(connection) > timeout + grace_period)
+            .map(|(session, _)| *session)
+            .collect();
+
+        expired.extend(
+            lapsed
+                .into_iter()
+                .filter_map(|session| self.detached.remove(&session)),
+        );
+
+        expired
+    }
+}
+
+impl AuthorizationHandlerTrait for ConnectionManager {
+    fn add_entity(&mut self, addr: SocketAddr, entity_id: NetId) {
+        let connection = self.register(addr);
+
+        if connection.player_character.is_none() {
+            connection.player_character = Some(entity_id);
+            connection.auth = AuthStatus::InWorld;
+        }
+
+        if !connection.owns(entity_id) {
+            connection.entities.push(entity_id);
+        }
+    }
+
+    fn get_character_id(&self, addr: SocketAddr) -> Option<NetId> {
+        self.connections.get(&addr)?.player_character
+    }
+
+    fn remove_entity(&mut self, addr: SocketAddr, entity_id: NetId) {
+        if let Some(connection) = self.connections.get_mut(&addr) {
+            connection.entities.retain(|&id| id != entity_id);
+
+            // without its character the connection is back to picking one
+            if connection.player_character == Some(entity_id) {
+                connection.player_character = None;
+                connection.auth = AuthStatus::Authenticated;
+            }
+        }
+    }
+
+    fn is_authorized(&self, addr: SocketAddr, entity_id: NetId) -> bool {
+        self.connections
+            .get(&addr)
+            .is_some_and(|connection| connection.owns(entity_id))
+    }
+
+    fn player_characters(&self) -> Vec<(SocketAddr, NetId)> {
+        self.connections
+            .values()
+            .filter_map(|connection| Some((connection.addr, connection.player_character?)))
+            .collect()
+    }
+
+    fn release_entity(&mut self, entity_id: NetId) -> Option<SocketAddr> {
+        // a detached session can lose its character too, it then has nothing to come back to
+        let connection = self
+            .connections
+            .values_mut()
+            .chain(self.detached.values_mut())
+            .find(|connection| connection.owns(entity_id))?;
+
+        connection.entities.retain(|&id| id != entity_id);
+
+        // without its player character the connection owns nothing it can act through
+        if connection.player_character == Some(entity_id) {
+            connection.player_character = None;
+            connection.entities.clear();
+            connection.auth = match connection.account {
+                Some(_) => AuthStatus::Authenticated,
+                None => AuthStatus::Connected,
+            };
+        }
+
+        Some(connection.addr)
+    }
+
+    fn grant_control(&mut self, addr: SocketAddr, entity_id: NetId) -> bool {
+        let controlled_elsewhere = self
+            .connections
+            .values()
+            .chain(self.detached.values())
+            .any(|connection| connection.addr != addr && connection.owns(entity_id));
+
+        if controlled_elsewhere {
+            return false;
+        }
+
+        // control is handed to a player in the world, never used to enter it
+        let Some(connection) = self
+            .connections
+            .get_mut(&addr)
+            .filter(|connection| connection.player_character.is_some())
+        else {
+            return false;
+        };
+
+        if !connection.owns(entity_id) {
+            connection.entities.push(entity_id);
+        }
+
+        true
+    }
+
+    fn revoke_control(&mut self, addr: SocketAddr, entity_id: NetId) -> bool {
+        let Some(connection) = self.connections.get_mut(&addr) else {
+            return false;
+        };
+
+        if connection.player_character == Some(entity_id) || !connection.owns(entity_id) {
+            return false;
+        }
+
+        connection.entities.retain(|&id| id != entity_id);
+        true
+    }
+
+    fn record_rejection(&mut self, addr: SocketAddr) -> u64 {
+        let connection = self.register(addr);
+
+        connection.stats.actions_rejected += 1;
+        connection.stats.actions_rejected
+    }
+
+    fn issue_session(&mut self, addr: SocketAddr) -> SessionToken {
+        let connection = self.register(addr);
+
+        *connection
+            .session
+            .get_or_insert_with(SessionToken::generate)
+    }
+
+    fn authenticate(&mut self, addr: SocketAddr, account: &str) -> bool {
+        // a detached session still holds its account until its grace period runs out
+        let logged_in_elsewhere =
+            self.connections
+                .values()
+                .chain(self.detached.values())
+                .any(|connection| {
+                    connection.addr != addr && connection.account.as_deref() == Some(account)
+                });
+
+        let connection = self.register(addr);
+
+        // switching accounts would carry the character over to the other one
+        if logged_in_elsewhere
+            || connection
+                .account
+                .as_deref()
+                .is_some_and(|current| current != account)
+        {
+            return false;
+        }
+
+        connection.account = Some(account.to_string());
+
+        if connection.auth == AuthStatus::Connected {
+            connection.auth = AuthStatus::Authenticated;
+        }
+
+        true
+    }
+
+    fn account(&self, addr: SocketAddr) -> Option<String> {
+        self.connections.get(&addr)?.account.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:7777".parse().unwrap()
+    }
+
+    #[test]
+    fn test_out_of_order_packets_are_rejected() {
+        let mut manager = ConnectionManager::default();
+
+        assert!(manager.receive(addr(), 10, 0, None).is_ok());
+        assert!(manager.receive(addr(), 5, 0, None).is_err());
+        assert!(manager.receive(addr(), 11, 0, None).is_ok());
+
+        let connection = manager.get(addr()).unwrap();
+        assert_eq!(connection.last_received_id, Some(11));
+        assert_eq!(connection.stats.packets_received, 3);
+        assert_eq!(connection.stats.packets_dropped, 1);
+    }
+
+    #[test]
+    fn test_rate_limit_drops_bursts() {
+        let mut manager = ConnectionManager::new(ConnectionConfig {
+            packets_per_second: 1,
+            burst: 3,
+            ..ConnectionConfig::default()
+        });
+
+        let accepted = (0..10)
+            .filter(|id| manager.receive(addr(), *id, 0, None).is_ok())
+            .count();
+
+        assert_eq!(accepted, 3);
+    }
+
+    #[test]
+    fn test_packet_ids_are_per_connection() {
+        let mut manager = ConnectionManager::default();
+        let other = "127.0.0.1:8888".parse().unwrap();
+
+        assert_eq!(manager.next_packet_id(addr()), 0);
+        assert_eq!(manager.next_packet_id(addr()), 1);
+        assert_eq!(manager.next_packet_id(other), 0);
+
+        manager.record_sent(addr(), 10);
+        manager.record_sent(addr(), 10);
+        assert_eq!(manager.get(addr()).unwrap().stats.bytes_sent, 20);
+    }
+
+    #[test]
+    fn test_ownership_follows_the_player_character() {
+        let mut manager = ConnectionManager::default();
+        let character = NetId::new(1, 0);
+        let pet = NetId::new(2, 0);
+
+        manager.add_entity(addr(), character);
+        manager.add_entity(addr(), pet);
+        assert_eq!(manager.get(addr()).unwrap().auth, AuthStatus::InWorld);
+        assert!(manager.is_authorized(addr(), pet));
+
+        assert_eq!(manager.release_entity(character), Some(addr()));
+        assert!(manager.get_character_id(addr()).is_none());
+        assert!(!manager.is_authorized(addr(), pet));
+        assert_eq!(manager.get(addr()).unwrap().auth, AuthStatus::Connected);
+    }
+
+    #[test]
+    fn test_session_follows_the_client_to_a_new_address() {
+        let mut manager = ConnectionManager::default();
+        let moved: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let character = NetId::new(1, 0);
+
+        manager.receive(addr(), 50, 0, None).unwrap();
+        manager.add_entity(addr(), character);
+        let session = manager.issue_session(addr());
+        assert_eq!(manager.issue_session(addr()), session);
+
+        manager.receive(moved, 0, 0, Some(session)).unwrap();
+
+        assert!(manager.get(addr()).is_none());
+        assert_eq!(manager.get_character_id(moved), Some(character));
+        assert_eq!(manager.take_migrations(), vec![(addr(), moved)]);
+
+        let stranger: SocketAddr = "127.0.0.1:9999".parse().unwrap();
+        manager
+            .receive(stranger, 0, 0, Some(SessionToken::generate()))
+            .unwrap();
+        assert!(manager.get_character_id(stranger).is_none());
+    }
+
+    #[test]
+    fn test_timed_out_sessions_can_reattach_within_grace_period() {
+        let mut manager = ConnectionManager::default();
+        let character = NetId::new(1, 0);
+        manager.add_entity(addr(), character);
+        let session = manager.issue_session(addr());
+
+        let config = ConnectionConfig::default();
+        let timed_out = Instant::now() + config.timeout + Duration::from_secs(1);
+        assert!(manager.expire(timed_out).is_empty());
+        assert!(manager.is_empty());
+
+        let restarted: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        manager.receive(restarted, 0, 0, Some(session)).unwrap();
+        assert_eq!(manager.get_character_id(restarted), Some(character));
+
+        manager.disconnect(restarted);
+        manager.add_entity(addr(), NetId::new(2, 0));
+        manager.issue_session(addr());
+        let lapsed = timed_out + config.grace_period + Duration::from_secs(1);
+        assert!(manager.expire(timed_out).is_empty());
+        assert_eq!(manager.expire(lapsed).len(), 1);
+    }
+
+    #[test]
+    fn test_backlogged_connections_expire_without_grace_period() {
+        let mut manager = ConnectionManager::default();
+        manager.add_entity(addr(), NetId::new(1, 0));
+        manager.issue_session(addr());
+
+        manager.mark_backlogged(addr());
+
+        let expired = manager.expire(Instant::now());
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].entities, vec![NetId::new(1, 0)]);
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn test_control_is_exclusive_and_revocable() {
+        let mut manager = ConnectionManager::default();
+        let other: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let pet = NetId::new(3, 0);
+
+        // not in the world yet
+        assert!(!manager.grant_control(addr(), pet));
+
+        manager.add_entity(addr(), NetId::new(1, 0));
+        manager.add_entity(other, NetId::new(2, 0));
+
+        assert!(manager.grant_control(addr(), pet));
+        assert!(manager.is_authorized(addr(), pet));
+        assert!(!manager.grant_control(other, pet));
+        assert!(!manager.grant_control(other, NetId::new(1, 0)));
+
+        assert!(!manager.revoke_control(addr(), NetId::new(1, 0)));
+        assert!(manager.revoke_control(addr(), pet));
+        assert!(!manager.is_authorized(addr(), pet));
+        assert!(manager.grant_control(other, pet));
+
+        assert_eq!(manager.record_rejection(addr()), 1);
+        assert_eq!(manager.record_rejection(addr()), 2);
+    }
+
+    #[test]
+    fn test_an_account_is_logged_in_once() {
+        let mut manager = ConnectionManager::default();
+        let other: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+
+        assert!(manager.authenticate(addr(), "player"));
+        assert_eq!(manager.get(addr()).unwrap().auth, AuthStatus::Authenticated);
+        assert_eq!(manager.account(addr()).as_deref(), Some("player"));
+
+        assert!(!manager.authenticate(other, "player"));
+        assert!(!manager.authenticate(addr(), "someone_else"));
+        assert!(manager.authenticate(addr(), "player"));
+
+        manager.disconnect(addr());
+        assert!(manager.authenticate(other, "player"));
+    }
+
+    #[test]
+    fn test_silent_connections_expire() {
+        let mut manager = ConnectionManager::default();
+        manager.register(addr());
+
+        assert!(manager.expire(Instant::now()).is_empty());
+
+        let later = Instant::now() + ConnectionConfig::default().timeout + Duration::from_secs(1);
+        assert_eq!(manager.expire(later).len(), 1);
+        assert!(manager.is_empty());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod connection;
+pub mod connection_manager;
+pub mod session_token;
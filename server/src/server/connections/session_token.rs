@@ -0,0 +1,22 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// issued to a client when it enters, identifies its connection independently of its address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct SessionToken(pub u128);
+
+impl SessionToken {
+    pub fn generate() -> Self {
+        SessionToken(Uuid::new_v4().as_u128())
+    }
+}
+
+impl fmt::Display for SessionToken {
+    // only a prefix, the full token is as good as the session itself
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08x}…", self.0 >> 96)
+    }
+}
@@ -0,0 +1,57 @@
+use std::net::SocketAddr;
+
+use thiserror::Error;
+
+use crate::server::{components::shared::net_id::NetId, opcode::OpCode};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to deserialize {0:?} packet: {1}")]
+    PacketDeserialization(OpCode, String),
+    #[error("Unknown opcode: {0}")]
+    UnknownOpCode(u16),
+    #[error("Entity with network ID {0} not found")]
+    EntityNotFound(NetId),
+    #[error("No network IDs left to allocate")]
+    NetIdsExhausted,
+    #[error("Spawn command has no Networked component")]
+    MissingNetworkedComponent,
+    #[error("Unknown replicated component: {0}")]
+    UnknownReplicatedComponent(String),
+    #[error("Failed to decode replicated component {0}: {1}")]
+    InvalidReplicatedComponent(String, serde_json::Error),
+    #[error("Snapshot baseline {0} is not available")]
+    MissingSnapshotBaseline(u32),
+    #[error("Packet {0} arrived out of order")]
+    OutOfOrderPacket(u128),
+    #[error("{0} exceeded its packet rate")]
+    RateLimited(SocketAddr),
+    #[error("Account {0} already exists")]
+    AccountExists(String),
+    #[error("Invalid account details: {0}")]
+    InvalidAccountDetails(String),
+    #[error("Wrong name or password for account {0}")]
+    InvalidCredentials(String),
+    #[error("Account {0} is banned")]
+    AccountBanned(String),
+    #[error("Too many failed logins for {0}")]
+    LoginThrottled(String),
+    #[error("Too many registrations from {0}")]
+    RegistrationThrottled(String),
+    #[error("Account {0} is already logged in")]
+    AccountInUse(String),
+    #[error("Account storage failed: {0}")]
+    AccountStorage(String),
+    #[error("Password hashing failed: {0}")]
+    PasswordHashing(String),
+    #[error("Character name {0} is taken")]
+    CharacterNameTaken(String),
+    #[error("Invalid character details: {0}")]
+    InvalidCharacterDetails(String),
+    #[error("Character {0} not found")]
+    CharacterNotFound(String),
+    #[error("Accounts are limited to {0} characters")]
+    CharacterLimitReached(usize),
+    #[error("Character storage failed: {0}")]
+    CharacterStorage(String),
+}
@@ -1,11 +1,16 @@
+pub mod accounts;
+pub mod characters;
 pub mod commands;
 pub mod components;
+pub mod connections;
+pub mod error;
 pub mod opcode;
 pub mod packet_handler;
 pub mod packet_receiver;
 pub mod packet_sender;
 pub mod packets;
 pub mod protocols;
+pub mod replication;
 pub mod server;
 pub mod state;
 pub mod systems;
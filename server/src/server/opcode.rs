@@ -1,12 +1,233 @@
 use serde::{Deserialize, Serialize};
 
+use crate::server::error::Error;
+
+// opcodes travel as their numeric code, existing codes must never be renumbered or reused
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash, Default)]
+#[serde(into = "u16", try_from = "u16")]
 pub enum OpCode {
     #[default]
-    Unset,
-    Moved,
-    Move,
-    Spawn,
-    Enter,
-    Enown,
+    Unset = 0,
+    // 1 was Moved, superseded by Snapshot
+    Move = 2,
+    Spawn = 3,
+    Enter = 4,
+    Enown = 5,
+    Error = 6,
+    Replicate = 7,
+    ReplicateUnreliable = 8,
+    Snapshot = 9,
+    SnapshotAck = 10,
+    Despawn = 11,
+    Control = 12,
+    Register = 13,
+    Login = 14,
+    Authenticated = 15,
+    ListCharacters = 16,
+    CreateCharacter = 17,
+    DeleteCharacter = 18,
+    CharacterList = 19,
+}
+
+impl OpCode {
+    pub const ALL: [OpCode; 19] = [
+        OpCode::Unset,
+        OpCode::Move,
+        OpCode::Spawn,
+        OpCode::Enter,
+        OpCode::Enown,
+        OpCode::Error,
+        OpCode::Replicate,
+        OpCode::ReplicateUnreliable,
+        OpCode::Snapshot,
+        OpCode::SnapshotAck,
+        OpCode::Despawn,
+        OpCode::Control,
+        OpCode::Register,
+        OpCode::Login,
+        OpCode::Authenticated,
+        OpCode::ListCharacters,
+        OpCode::CreateCharacter,
+        OpCode::DeleteCharacter,
+        OpCode::CharacterList,
+    ];
+
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+
+    // kept apart from `ALL` on purpose, the tests check that both list the same opcodes
+    pub fn from_code(code: u16) -> Option<OpCode> {
+        Some(match code {
+            0 => OpCode::Unset,
+            2 => OpCode::Move,
+            3 => OpCode::Spawn,
+            4 => OpCode::Enter,
+            5 => OpCode::Enown,
+            6 => OpCode::Error,
+            7 => OpCode::Replicate,
+            8 => OpCode::ReplicateUnreliable,
+            9 => OpCode::Snapshot,
+            10 => OpCode::SnapshotAck,
+            11 => OpCode::Despawn,
+            12 => OpCode::Control,
+            13 => OpCode::Register,
+            14 => OpCode::Login,
+            15 => OpCode::Authenticated,
+            16 => OpCode::ListCharacters,
+            17 => OpCode::CreateCharacter,
+            18 => OpCode::DeleteCharacter,
+            19 => OpCode::CharacterList,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+    None,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Reliability {
+    // may be dropped, a newer packet supersedes it
+    Unreliable,
+    // must reach the peer for the session to stay consistent
+    Reliable,
+}
+
+impl OpCode {
+    pub fn direction(self) -> Direction {
+        match self {
+            OpCode::Unset => Direction::None,
+            OpCode::Move
+            | OpCode::Enter
+            | OpCode::SnapshotAck
+            | OpCode::Register
+            | OpCode::Login
+            | OpCode::ListCharacters
+            | OpCode::CreateCharacter
+            | OpCode::DeleteCharacter => Direction::ClientToServer,
+            OpCode::Spawn
+            | OpCode::Enown
+            | OpCode::Error
+            | OpCode::Replicate
+            | OpCode::ReplicateUnreliable
+            | OpCode::Snapshot
+            | OpCode::Despawn
+            | OpCode::Control
+            | OpCode::Authenticated
+            | OpCode::CharacterList => Direction::ServerToClient,
+        }
+    }
+
+    pub fn reliability(self) -> Reliability {
+        match self {
+            // a lost snapshot or ack is superseded by the next one
+            OpCode::ReplicateUnreliable | OpCode::Snapshot | OpCode::SnapshotAck => {
+                Reliability::Unreliable
+            }
+            OpCode::Unset
+            | OpCode::Move
+            | OpCode::Spawn
+            | OpCode::Enter
+            | OpCode::Enown
+            | OpCode::Error
+            | OpCode::Replicate
+            | OpCode::Despawn
+            | OpCode::Control
+            | OpCode::Register
+            | OpCode::Login
+            | OpCode::Authenticated
+            | OpCode::ListCharacters
+            | OpCode::CreateCharacter
+            | OpCode::DeleteCharacter
+            | OpCode::CharacterList => Reliability::Reliable,
+        }
+    }
+}
+
+impl OpCode {
+    // base weight for the bandwidth budget, packets others depend on come first
+    pub fn importance(self) -> f32 {
+        match self {
+            OpCode::Enown
+            | OpCode::Error
+            | OpCode::Control
+            | OpCode::Authenticated
+            | OpCode::CharacterList => 8.0,
+            OpCode::Spawn | OpCode::Despawn => 4.0,
+            OpCode::Replicate => 2.0,
+            OpCode::ReplicateUnreliable | OpCode::Snapshot => 1.0,
+            OpCode::Unset
+            | OpCode::Move
+            | OpCode::Enter
+            | OpCode::SnapshotAck
+            | OpCode::Register
+            | OpCode::Login
+            | OpCode::ListCharacters
+            | OpCode::CreateCharacter
+            | OpCode::DeleteCharacter => 1.0,
+        }
+    }
+}
+
+impl From<OpCode> for u16 {
+    fn from(opcode: OpCode) -> Self {
+        opcode.code()
+    }
+}
+
+impl TryFrom<u16> for OpCode {
+    type Error = Error;
+
+    fn try_from(code: u16) -> Result<Self, Error> {
+        OpCode::from_code(code).ok_or(Error::UnknownOpCode(code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique() {
+        for opcode in OpCode::ALL {
+            assert_eq!(
+                OpCode::ALL
+                    .iter()
+                    .filter(|other| other.code() == opcode.code())
+                    .count(),
+                1,
+                "{:?} shares its code with another opcode",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_lists_every_opcode() {
+        let decodable: Vec<OpCode> = (0..=u16::MAX)
+            .filter_map(|code| OpCode::try_from(code).ok())
+            .collect();
+
+        assert_eq!(decodable, OpCode::ALL);
+        for opcode in decodable {
+            assert_eq!(OpCode::from_code(opcode.code()), Some(opcode));
+        }
+    }
+
+    #[test]
+    fn test_serializes_as_number() {
+        assert_eq!(serde_json::to_string(&OpCode::Enter).unwrap(), "4");
+        assert_eq!(serde_json::from_str::<OpCode>("2").unwrap(), OpCode::Move);
+    }
+
+    #[test]
+    fn test_unknown_code_is_rejected() {
+        assert!(serde_json::from_str::<OpCode>("999").is_err());
+        assert!(serde_json::from_str::<OpCode>("\"Enter\"").is_err());
+    }
 }
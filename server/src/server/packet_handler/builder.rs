@@ -1,11 +1,28 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
+
+use serde::de::DeserializeOwned;
 
 use crate::server::{
-    opcode::OpCode, packet_handler::enter_packet_handler::EnterPacketHandler,
+    accounts::account_service::AccountService, characters::character_service::CharacterService,
+    connections::connection_manager::ConnectionManager, opcode::OpCode,
     state::authorization_handler::AuthorizationHandlerTrait,
 };
 
-use super::{move_packet_handler::MovePacketHandler, packet_handler::PacketHandler};
+use super::{
+    character_packet_handler::{
+        create_character_packet_handler, delete_character_packet_handler,
+        list_characters_packet_handler,
+    },
+    credentials_packet_handler::{login_packet_handler, register_packet_handler},
+    enter_packet_handler::enter_packet_handler,
+    move_packet_handler::move_packet_handler,
+    packet_handler::PacketHandler,
+    snapshot_ack_packet_handler::snapshot_ack_packet_handler,
+    typed_packet_handler::TypedPacketHandler,
+};
 
 pub struct PacketHandlerBuilder {
     handler: PacketHandler,
@@ -24,26 +41,64 @@ impl PacketHandlerBuilder {
         }
     }
 
+    pub fn with_handler<P>(mut self, opcode: OpCode, handler: TypedPacketHandler<P>) -> Self
+    where
+        P: DeserializeOwned + Debug + Send + Sync + 'static,
+    {
+        self.handler.handlers.insert(opcode, Box::new(handler));
+        self
+    }
+
     pub fn with_enter_handler(
-        mut self,
+        self,
         authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+        characters: Arc<CharacterService>,
     ) -> Self {
-        self.handler.handlers.insert(
+        self.with_handler(
             OpCode::Enter,
-            Box::new(EnterPacketHandler::new(authorization_handler)),
-        );
-        self
+            enter_packet_handler(authorization_handler, characters),
+        )
     }
 
     pub fn with_move_handler(
-        mut self,
+        self,
         authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
     ) -> Self {
-        self.handler.handlers.insert(
-            OpCode::Move,
-            Box::new(MovePacketHandler::new(authorization_handler)),
-        );
-        self
+        self.with_handler(OpCode::Move, move_packet_handler(authorization_handler))
+    }
+
+    pub fn with_snapshot_ack_handler(
+        self,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
+    ) -> Self {
+        self.with_handler(
+            OpCode::SnapshotAck,
+            snapshot_ack_packet_handler(connection_manager),
+        )
+    }
+
+    pub fn with_account_handlers(self, accounts: Arc<AccountService>) -> Self {
+        self.with_handler(OpCode::Register, register_packet_handler(accounts.clone()))
+            .with_handler(OpCode::Login, login_packet_handler(accounts))
+    }
+
+    pub fn with_character_handlers(
+        self,
+        authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+        characters: Arc<CharacterService>,
+    ) -> Self {
+        self.with_handler(
+            OpCode::ListCharacters,
+            list_characters_packet_handler(authorization_handler.clone(), characters.clone()),
+        )
+        .with_handler(
+            OpCode::CreateCharacter,
+            create_character_packet_handler(authorization_handler.clone(), characters.clone()),
+        )
+        .with_handler(
+            OpCode::DeleteCharacter,
+            delete_character_packet_handler(authorization_handler, characters),
+        )
     }
 
     pub fn build(self) -> PacketHandler {
@@ -0,0 +1,142 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{trace, warn};
+
+use crate::server::{
+    characters::character_service::CharacterService,
+    commands::error_command::ErrorCommand,
+    packet_sender::TargetAddress,
+    protocols::{
+        recv::{
+            create_character_packet::CreateCharacterPacket,
+            delete_character_packet::DeleteCharacterPacket,
+            list_characters_packet::ListCharactersPacket,
+        },
+        send::error_packet::ErrorCode,
+    },
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+use super::typed_packet_handler::TypedPacketHandler;
+
+// characters are stored, every request is answered by `character_system` once the store is done
+pub(super) fn list_characters_packet_handler(
+    authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+    characters: Arc<CharacterService>,
+) -> TypedPacketHandler<ListCharactersPacket> {
+    TypedPacketHandler::<ListCharactersPacket>::new(move |world, packets| {
+        let authorization_handler = authorization_handler
+            .read()
+            .expect("Failed to get read lock on authorization handler");
+
+        for (addr, packet) in packets {
+            trace!(
+                "Processing list characters packet: {:?} from {:?}",
+                packet,
+                addr
+            );
+
+            if let Some(account) = require_account(world, &*authorization_handler, addr) {
+                characters.spawn_list(addr, account);
+            }
+        }
+    })
+}
+
+pub(super) fn create_character_packet_handler(
+    authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+    characters: Arc<CharacterService>,
+) -> TypedPacketHandler<CreateCharacterPacket> {
+    TypedPacketHandler::<CreateCharacterPacket>::new(move |world, packets| {
+        let authorization_handler = authorization_handler
+            .read()
+            .expect("Failed to get read lock on authorization handler");
+
+        for (addr, packet) in packets {
+            trace!(
+                "Processing create character packet: {:?} from {:?}",
+                packet,
+                addr
+            );
+
+            if let Some(account) = require_account(world, &*authorization_handler, addr) {
+                characters.spawn_create(
+                    addr,
+                    account,
+                    packet.name,
+                    packet.class,
+                    packet.appearance,
+                );
+            }
+        }
+    })
+}
+
+pub(super) fn delete_character_packet_handler(
+    authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+    characters: Arc<CharacterService>,
+) -> TypedPacketHandler<DeleteCharacterPacket> {
+    TypedPacketHandler::<DeleteCharacterPacket>::new(move |world, packets| {
+        let authorization_handler = authorization_handler
+            .read()
+            .expect("Failed to get read lock on authorization handler");
+
+        for (addr, packet) in packets {
+            trace!(
+                "Processing delete character packet: {:?} from {:?}",
+                packet,
+                addr
+            );
+
+            let Some(account) = require_account(world, &*authorization_handler, addr) else {
+                continue;
+            };
+
+            // the character being played cannot be deleted from under it
+            if authorization_handler.get_character_id(addr).is_some() {
+                reject(
+                    world,
+                    addr,
+                    ErrorCode::Unauthorized,
+                    "Leave the world before deleting characters",
+                );
+                continue;
+            }
+
+            characters.spawn_delete(addr, account, packet.id);
+        }
+    })
+}
+
+// the account `addr` is logged in to, rejects the packet when there is none
+pub(super) fn require_account(
+    world: &mut World,
+    authorization_handler: &dyn AuthorizationHandlerTrait,
+    addr: SocketAddr,
+) -> Option<String> {
+    let account = authorization_handler.account(addr);
+
+    if account.is_none() {
+        reject(world, addr, ErrorCode::NotAuthenticated, "Log in first");
+    }
+
+    account
+}
+
+fn reject(world: &mut World, addr: SocketAddr, code: ErrorCode, message: &str) {
+    warn!("Rejecting packet from {:?}: {}", addr, message);
+
+    world
+        .resource_mut::<UntargetedCommandContainer<ErrorCommand>>()
+        .entries
+        .push_back(ErrorCommand::new(
+            code,
+            message.to_string(),
+            TargetAddress::Targeted(vec![addr]),
+        ));
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use log::trace;
+
+use crate::server::{
+    accounts::account_service::AccountService,
+    protocols::recv::credentials_packet::CredentialsPacket,
+};
+
+use super::typed_packet_handler::TypedPacketHandler;
+
+// hashing takes far longer than a tick, the outcome is applied by `authentication_system`
+pub(super) fn register_packet_handler(
+    accounts: Arc<AccountService>,
+) -> TypedPacketHandler<CredentialsPacket> {
+    TypedPacketHandler::<CredentialsPacket>::new(move |_world, packets| {
+        for (addr, packet) in packets {
+            trace!("Processing register packet: {:?} from {:?}", packet, addr);
+
+            accounts.spawn_register(addr, packet.name, packet.password);
+        }
+    })
+}
+
+pub(super) fn login_packet_handler(
+    accounts: Arc<AccountService>,
+) -> TypedPacketHandler<CredentialsPacket> {
+    TypedPacketHandler::<CredentialsPacket>::new(move |_world, packets| {
+        for (addr, packet) in packets {
+            trace!("Processing login packet: {:?} from {:?}", packet, addr);
+
+            accounts.spawn_login(addr, packet.name, packet.password);
+        }
+    })
+}
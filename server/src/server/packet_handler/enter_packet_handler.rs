@@ -1,87 +1,179 @@
 use std::{
     net::SocketAddr,
     sync::{Arc, RwLock},
-    vec,
 };
 
 use bevy_ecs::world::World;
-use log::{debug, trace};
-use uuid::Uuid;
+use log::{trace, warn};
 
 use crate::server::{
-    commands::spawn_command::{EntityComponent, SpawnCommand},
-    components::movement_state::MovementStateType,
+    characters::character_service::CharacterService,
+    commands::{enown_command::EnownCommand, error_command::ErrorCommand},
     packet_sender::TargetAddress,
-    packets::{packet::Packet, received_packet::ReceivedPacket},
-    protocols::recv::enter_packet::EnterPacket,
+    protocols::{
+        recv::enter_packet::EnterPacket, send::error_packet::ErrorCode, version::PROTOCOL_VERSION,
+    },
     state::authorization_handler::AuthorizationHandlerTrait,
     systems::untargeted_command_container::UntargetedCommandContainer,
 };
 
-use super::packet_handler::PacketHandlerTrait;
+use super::{character_packet_handler::require_account, typed_packet_handler::TypedPacketHandler};
 
-pub(super) struct EnterPacketHandler {
-    packets: Vec<ReceivedPacket>,
+// the selected character is loaded from the store and spawned by `character_system`
+pub(super) fn enter_packet_handler(
     authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
-}
-
-impl EnterPacketHandler {
-    pub fn new(authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>) -> Self {
-        EnterPacketHandler {
-            authorization_handler,
-            packets: vec![],
+    characters: Arc<CharacterService>,
+) -> TypedPacketHandler<EnterPacket> {
+    TypedPacketHandler::<EnterPacket>::new(move |world, packets| {
+        let mut authorization_handler = authorization_handler
+            .write()
+            .expect("Failed to get write lock on authorization handler");
+
+        for (addr, packet) in packets {
+            trace!("Processing enter packet: {:?} from {:?}", packet, addr);
+
+            if !check_protocol_version(world, addr, &packet) {
+                continue;
+            }
+
+            let Some(account) = require_account(world, &*authorization_handler, addr) else {
+                continue;
+            };
+
+            // the session was reattached (or the enter repeated), the client gets its character back
+            if let Some(character) = authorization_handler.get_character_id(addr) {
+                let session = authorization_handler.issue_session(addr);
+
+                world
+                    .resource_mut::<UntargetedCommandContainer<EnownCommand>>()
+                    .entries
+                    .push_back(EnownCommand::new(character, session, addr));
+                continue;
+            }
+
+            let Some(character) = packet.character else {
+                warn!("Rejecting enter from {:?}: no character selected", addr);
+
+                world
+                    .resource_mut::<UntargetedCommandContainer<ErrorCommand>>()
+                    .entries
+                    .push_back(ErrorCommand::new(
+                        ErrorCode::NoCharacterSelected,
+                        "Pick a character from the character list to enter with".to_string(),
+                        TargetAddress::Targeted(vec![addr]),
+                    ));
+                continue;
+            };
+
+            characters.spawn_select(addr, account, character);
         }
-    }
+    })
 }
 
-impl PacketHandlerTrait for EnterPacketHandler {
-    fn handle_packet(&mut self, addr: SocketAddr, packet: Packet) {
-        trace!("Handling enter packet: {:?}", packet);
-
-        self.packets.push(ReceivedPacket::new(packet, addr));
-    }
-
-    fn transform_state(&mut self, world: Arc<RwLock<World>>) {
-        debug!(
-            "Transforming state with {} enter packets",
-            self.packets.len()
-        );
-
-        let mut authorization_handler = self
-            .authorization_handler
-            .write()
-            .expect("Failed to get read lock on authorization handler");
-
-        for packet in &self.packets {
-            trace!("Processing enter packet: {:?}", packet);
-
-            let mut world = world.write().expect("Failed to get write lock world");
+fn check_protocol_version(world: &mut World, addr: SocketAddr, packet: &EnterPacket) -> bool {
+    let (code, message) = match packet.protocol_version {
+        Some(version) if version.is_supported() => return true,
+        Some(version) => (
+            ErrorCode::IncompatibleProtocolVersion,
+            format!(
+                "Protocol version {} is not supported by server version {}",
+                version, PROTOCOL_VERSION
+            ),
+        ),
+        None => (
+            ErrorCode::MissingProtocolVersion,
+            format!(
+                "Enter packet carries no protocol version, server version is {}",
+                PROTOCOL_VERSION
+            ),
+        ),
+    };
+
+    warn!("Rejecting enter from {:?}: {}", addr, message);
+
+    world
+        .resource_mut::<UntargetedCommandContainer<ErrorCommand>>()
+        .entries
+        .push_back(ErrorCommand::new(
+            code,
+            message,
+            TargetAddress::Targeted(vec![addr]),
+        ));
+
+    false
+}
 
-            let _ = serde_json::from_str::<EnterPacket>(&packet.packet.data)
-                .expect("Failed to deserialize Enter Packet");
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+    use common::character_store::InMemoryCharacterStore;
 
-            let mut res = world.resource_mut::<UntargetedCommandContainer<SpawnCommand>>();
+    use crate::server::{
+        characters::character_service::CharacterOutcome,
+        connections::connection_manager::ConnectionManager, opcode::OpCode,
+        packet_handler::packet_handler::PacketHandlerTrait, packets::packet::Packet,
+    };
 
-            let character_id = Uuid::new_v4();
+    use super::*;
 
-            authorization_handler.add_entity(packet.addr.clone(), character_id.clone());
+    fn enter_packet(character: Option<&str>) -> Packet {
+        let packet = EnterPacket {
+            protocol_version: Some(PROTOCOL_VERSION),
+            character: character.map(str::to_string),
+        };
 
-            let cmd = SpawnCommand::new(
-                vec![
-                    EntityComponent::Position(0.0, 0.0, 0.0),
-                    EntityComponent::Networked(character_id.to_string()),
-                    EntityComponent::MovementState(MovementStateType::Stopped, 1.00),
-                ],
-                TargetAddress::Targeted(vec![packet.addr.clone()]),
-            );
+        Packet::new(0, OpCode::Enter, serde_json::to_string(&packet).unwrap())
+    }
 
-            trace!("Adding spawn command: {:?}", cmd);
+    #[tokio::test]
+    async fn test_only_logged_in_connections_enter_with_a_character() {
+        let guest: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let undecided: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let player: SocketAddr = "127.0.0.1:9999".parse().unwrap();
+
+        let mut connections = ConnectionManager::default();
+        connections.authenticate(undecided, "undecided");
+        connections.authenticate(player, "player");
+        let connections = Arc::new(RwLock::new(connections));
+
+        let characters = Arc::new(CharacterService::new(
+            Arc::new(InMemoryCharacterStore::new()),
+            1,
+        ));
+
+        let mut world = World::default();
+        world.insert_resource(UntargetedCommandContainer::<ErrorCommand> {
+            entries: Default::default(),
+        });
+        let world = Arc::new(RwLock::new(world));
+
+        let mut handler = enter_packet_handler(connections.clone(), characters.clone());
+        handler.handle_packet(guest, enter_packet(Some("character")));
+        handler.handle_packet(undecided, enter_packet(None));
+        handler.handle_packet(player, enter_packet(Some("character")));
+        handler.transform_state(world.clone());
+
+        let codes: Vec<_> = world
+            .read()
+            .unwrap()
+            .resource::<UntargetedCommandContainer<ErrorCommand>>()
+            .entries
+            .iter()
+            .map(|error| error.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![ErrorCode::NotAuthenticated, ErrorCode::NoCharacterSelected]
+        );
 
-            res.entries.push_back(cmd);
-        }
-    }
+        // only the player's selection reaches the store
+        tokio::task::yield_now().await;
 
-    fn clear_packets(&mut self) {
-        self.packets.clear();
+        let outcomes = characters.take_outcomes();
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(
+            outcomes[0],
+            CharacterOutcome::Selected { addr, .. } if addr == player
+        ));
     }
 }
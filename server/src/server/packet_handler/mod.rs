@@ -1,4 +1,8 @@
 pub mod builder;
+pub mod character_packet_handler;
+pub mod credentials_packet_handler;
 pub mod enter_packet_handler;
 pub mod move_packet_handler;
 pub mod packet_handler;
+pub mod snapshot_ack_packet_handler;
+pub mod typed_packet_handler;
@@ -1,99 +1,149 @@
-use std::{
-    collections::VecDeque,
-    net::SocketAddr,
-    sync::{Arc, RwLock},
-};
+use std::sync::{Arc, RwLock};
 
-use bevy_ecs::world::World;
-use log::{debug, trace};
+use log::{trace, warn};
 
 use crate::server::{
-    commands::move_command::MoveCommand,
-    packets::{packet::Packet, received_packet::ReceivedPacket},
-    protocols::recv::move_packet::MovePacket,
+    commands::{error_command::ErrorCommand, move_command::MoveCommand},
+    packet_sender::TargetAddress,
+    protocols::{recv::move_packet::MovePacket, send::error_packet::ErrorCode},
     state::authorization_handler::AuthorizationHandlerTrait,
-    systems::command_container::CommandContainer,
+    systems::{
+        command_container::CommandContainer,
+        untargeted_command_container::UntargetedCommandContainer,
+    },
 };
 
-use super::packet_handler::PacketHandlerTrait;
+use super::typed_packet_handler::TypedPacketHandler;
 
-pub(super) struct MovePacketHandler {
-    packets: Vec<ReceivedPacket>,
+pub(super) fn move_packet_handler(
     authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
-}
+) -> TypedPacketHandler<MovePacket> {
+    TypedPacketHandler::<MovePacket>::new(move |world, packets| {
+        let mut authorization_handler = authorization_handler
+            .write()
+            .expect("Failed to get write lock on authorization handler");
+
+        for (addr, packet) in packets {
+            trace!("Processing move packet: {:?} from {:?}", packet, addr);
+
+            let Some(target) = packet
+                .target
+                .or_else(|| authorization_handler.get_character_id(addr))
+            else {
+                warn!("No character ID for ({:?}), dropping move packet", addr);
+                continue;
+            };
+
+            if !authorization_handler.is_authorized(addr, target) {
+                let rejected = authorization_handler.record_rejection(addr);
+
+                warn!(
+                    "{:?} does not control {} ({} rejected so far), dropping move packet",
+                    addr, target, rejected
+                );
+
+                world
+                    .resource_mut::<UntargetedCommandContainer<ErrorCommand>>()
+                    .entries
+                    .push_back(ErrorCommand::new(
+                        ErrorCode::Unauthorized,
+                        format!("Not allowed to move {}", target),
+                        TargetAddress::Targeted(vec![addr]),
+                    ));
+                continue;
+            }
 
-impl MovePacketHandler {
-    pub fn new(authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>) -> Self {
-        MovePacketHandler {
-            authorization_handler,
-            packets: vec![],
+            world
+                .resource_mut::<CommandContainer<MoveCommand>>()
+                .entries
+                .entry(target)
+                .or_default()
+                .push_back(MoveCommand::new(
+                    target,
+                    packet.vector.x,
+                    packet.vector.y,
+                    packet.vector.z,
+                    packet.state,
+                ));
         }
-    }
+    })
 }
 
-impl PacketHandlerTrait for MovePacketHandler {
-    fn handle_packet(&mut self, addr: SocketAddr, packet: Packet) {
-        trace!("Handling move packet: {:?}", packet);
-
-        self.packets.push(ReceivedPacket::new(packet, addr));
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use bevy_ecs::world::World;
+
+    use crate::server::{
+        components::{
+            movement_state::MovementStateType,
+            shared::{net_id::NetId, vec3d::Vec3d},
+        },
+        connections::connection_manager::ConnectionManager,
+        opcode::OpCode,
+        packet_handler::packet_handler::PacketHandlerTrait,
+        packets::packet::Packet,
+    };
+
+    use super::*;
+
+    fn move_packet(target: Option<NetId>) -> Packet {
+        let packet = MovePacket::new(Vec3d::new(1.0, 0.0, 0.0), MovementStateType::Moving);
+        let packet = match target {
+            Some(target) => packet.targeting(target),
+            None => packet,
+        };
+
+        Packet::new(0, OpCode::Move, serde_json::to_string(&packet).unwrap())
     }
 
-    fn transform_state(&mut self, world: Arc<RwLock<World>>) {
-        debug!(
-            "Transforming state with {} move packets",
-            self.packets.len()
+    #[test]
+    fn test_moves_are_limited_to_controlled_entities() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let character = NetId::new(0, 0);
+        let pet = NetId::new(1, 0);
+        let stranger = NetId::new(2, 0);
+
+        let mut connections = ConnectionManager::default();
+        connections.add_entity(addr, character);
+        connections.grant_control(addr, pet);
+        let connections = Arc::new(RwLock::new(connections));
+
+        let mut world = World::default();
+        world.insert_resource(CommandContainer::<MoveCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<ErrorCommand> {
+            entries: Default::default(),
+        });
+        let world = Arc::new(RwLock::new(world));
+
+        let mut handler = move_packet_handler(connections.clone());
+        handler.handle_packet(addr, move_packet(None));
+        handler.handle_packet(addr, move_packet(Some(pet)));
+        handler.handle_packet(addr, move_packet(Some(stranger)));
+        handler.transform_state(world.clone());
+
+        let world = world.read().unwrap();
+        let moves = &world.resource::<CommandContainer<MoveCommand>>().entries;
+        assert!(moves.contains_key(&character) && moves.contains_key(&pet));
+        assert!(!moves.contains_key(&stranger));
+
+        let errors = &world
+            .resource::<UntargetedCommandContainer<ErrorCommand>>()
+            .entries;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, ErrorCode::Unauthorized);
+        assert_eq!(
+            connections
+                .read()
+                .unwrap()
+                .get(addr)
+                .unwrap()
+                .stats
+                .actions_rejected,
+            1
         );
-
-        let authorization_handler = self
-            .authorization_handler
-            .read()
-            .expect("Failed to get read lock on authorization handler");
-
-        for packet in &self.packets {
-            trace!("Processing move packet: {:?}", packet);
-
-            let mut world = world.write().expect("Failed to get write lock world");
-
-            let character_id = authorization_handler.get_character_id(packet.addr).expect(
-                format!(
-                    "Failed to get character ID for ({:?}) from authorization handler",
-                    packet.addr
-                )
-                .as_str(),
-            );
-
-            let mut res = world.resource_mut::<CommandContainer<MoveCommand>>();
-
-            let packet_data = serde_json::from_str::<MovePacket>(&packet.packet.data)
-                .expect("Failed to deserialize MovePacket");
-
-            match res.entries.get_mut(&character_id.to_string()) {
-                Some(queue) => {
-                    queue.push_back(MoveCommand::new(
-                        character_id.to_string(),
-                        packet_data.vector.x,
-                        packet_data.vector.y,
-                        packet_data.vector.z,
-                        packet_data.state.clone(),
-                    ));
-                }
-                None => {
-                    let mut queue = VecDeque::new();
-
-                    queue.push_back(MoveCommand::new(
-                        character_id.to_string().clone(),
-                        packet_data.vector.x,
-                        packet_data.vector.y,
-                        packet_data.vector.z,
-                        packet_data.state.clone(),
-                    ));
-                    res.entries.insert(character_id.to_string(), queue);
-                }
-            }
-        }
-    }
-
-    fn clear_packets(&mut self) {
-        self.packets.clear();
     }
 }
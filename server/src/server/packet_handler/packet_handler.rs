@@ -13,6 +13,9 @@ pub trait PacketHandlerTrait: Send + Sync {
     fn handle_packet(&mut self, addr: SocketAddr, packet: Packet);
     fn transform_state(&mut self, world: Arc<RwLock<World>>);
     fn clear_packets(&mut self);
+    fn rejected_packets(&self) -> usize {
+        0
+    }
 }
 
 pub struct PacketHandler {
@@ -66,4 +69,11 @@ impl PacketHandlerTrait for PacketHandler {
             handler.clear_packets();
         }
     }
+
+    fn rejected_packets(&self) -> usize {
+        self.handlers
+            .values()
+            .map(|handler| handler.rejected_packets())
+            .sum()
+    }
 }
@@ -0,0 +1,30 @@
+use std::sync::{Arc, RwLock};
+
+use log::trace;
+
+use crate::server::{
+    connections::connection_manager::ConnectionManager,
+    protocols::recv::snapshot_ack_packet::SnapshotAckPacket,
+    replication::client_snapshots::ClientSnapshots,
+};
+
+use super::typed_packet_handler::TypedPacketHandler;
+
+pub(super) fn snapshot_ack_packet_handler(
+    connection_manager: Arc<RwLock<ConnectionManager>>,
+) -> TypedPacketHandler<SnapshotAckPacket> {
+    TypedPacketHandler::<SnapshotAckPacket>::new(move |world, packets| {
+        let mut snapshots = world.resource_mut::<ClientSnapshots>();
+        let mut connection_manager = connection_manager
+            .write()
+            .expect("Failed to get write lock on connection manager");
+
+        for (addr, packet) in packets {
+            trace!("Processing snapshot ack: {:?} from {:?}", packet, addr);
+
+            if let Some(rtt) = snapshots.acknowledge(addr, packet.sequence) {
+                connection_manager.record_rtt(addr, rtt);
+            }
+        }
+    })
+}
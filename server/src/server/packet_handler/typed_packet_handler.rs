@@ -0,0 +1,170 @@
+use std::{
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+use bevy_ecs::world::World;
+use log::{debug, trace, warn};
+use serde::de::DeserializeOwned;
+
+use crate::server::{error::Error, packets::packet::Packet};
+
+use super::packet_handler::PacketHandlerTrait;
+
+pub type TypedPackets<P> = Vec<(SocketAddr, P)>;
+
+type BatchHandler<P> = Box<dyn FnMut(&mut World, TypedPackets<P>) + Send + Sync>;
+
+// Decodes packets as they arrive and hands the typed batch over to `on_batch` once per tick
+pub struct TypedPacketHandler<P> {
+    packets: TypedPackets<P>,
+    rejected: usize,
+    on_batch: BatchHandler<P>,
+}
+
+impl<P> TypedPacketHandler<P>
+where
+    P: DeserializeOwned + Debug + Send + Sync,
+{
+    pub fn new(on_batch: impl FnMut(&mut World, TypedPackets<P>) + Send + Sync + 'static) -> Self {
+        TypedPacketHandler {
+            packets: vec![],
+            rejected: 0,
+            on_batch: Box::new(on_batch),
+        }
+    }
+
+    fn decode(packet: &Packet) -> Result<P, Error> {
+        serde_json::from_str::<P>(&packet.data)
+            .map_err(|e| Error::PacketDeserialization(packet.opcode, e.to_string()))
+    }
+}
+
+impl<P> PacketHandlerTrait for TypedPacketHandler<P>
+where
+    P: DeserializeOwned + Debug + Send + Sync,
+{
+    fn handle_packet(&mut self, addr: SocketAddr, packet: Packet) {
+        match Self::decode(&packet) {
+            Ok(decoded) => {
+                trace!("Handling {:?} packet: {:?}", packet.opcode, decoded);
+
+                self.packets.push((addr, decoded));
+            }
+            Err(e) => {
+                self.rejected += 1;
+
+                warn!(
+                    "Rejected packet {} from {:?} ({} rejected so far): {}",
+                    packet.id, addr, self.rejected, e
+                );
+            }
+        }
+    }
+
+    fn transform_state(&mut self, world: Arc<RwLock<World>>) {
+        debug!(
+            "Transforming state with {} {} packets",
+            self.packets.len(),
+            std::any::type_name::<P>()
+        );
+
+        if self.packets.is_empty() {
+            return;
+        }
+
+        let packets = std::mem::take(&mut self.packets);
+
+        let mut world = world.write().expect("Failed to get write lock world");
+
+        (self.on_batch)(&mut world, packets);
+    }
+
+    fn clear_packets(&mut self) {
+        self.packets.clear();
+    }
+
+    fn rejected_packets(&self) -> usize {
+        self.rejected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        sync::{Arc, Mutex, RwLock},
+    };
+
+    use bevy_ecs::world::World;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::server::opcode::OpCode;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct TestPacket {
+        value: u32,
+    }
+
+    fn test_addr() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 12345))
+    }
+
+    fn packet(data: &str) -> Packet {
+        Packet::new(0, OpCode::Move, data.to_string())
+    }
+
+    fn recording_handler() -> (
+        TypedPacketHandler<TestPacket>,
+        Arc<Mutex<TypedPackets<TestPacket>>>,
+    ) {
+        let received = Arc::new(Mutex::new(vec![]));
+        let sink = received.clone();
+
+        let handler = TypedPacketHandler::new(move |_world, packets| {
+            sink.lock().unwrap().extend(packets);
+        });
+
+        (handler, received)
+    }
+
+    #[test]
+    fn test_decoded_packets_are_passed_to_batch_handler() {
+        let (mut handler, received) = recording_handler();
+
+        handler.handle_packet(test_addr(), packet(r#"{"value":7}"#));
+        handler.transform_state(Arc::new(RwLock::new(World::default())));
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![(test_addr(), TestPacket { value: 7 })]
+        );
+        assert_eq!(handler.rejected_packets(), 0);
+    }
+
+    #[test]
+    fn test_invalid_payload_is_rejected_and_counted() {
+        let (mut handler, received) = recording_handler();
+
+        handler.handle_packet(test_addr(), packet("not json"));
+        handler.handle_packet(test_addr(), packet(r#"{"other":1}"#));
+        handler.transform_state(Arc::new(RwLock::new(World::default())));
+
+        assert!(received.lock().unwrap().is_empty());
+        assert_eq!(handler.rejected_packets(), 2);
+    }
+
+    #[test]
+    fn test_batch_is_drained_after_transform_state() {
+        let (mut handler, received) = recording_handler();
+        let world = Arc::new(RwLock::new(World::default()));
+
+        handler.handle_packet(test_addr(), packet(r#"{"value":1}"#));
+        handler.transform_state(world.clone());
+        handler.transform_state(world);
+
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,14 @@
 use log::{debug, trace, warn};
 
+use crate::server::accounts::account_service::{AccountService, Accounts};
+use crate::server::characters::character_service::{CharacterService, Characters};
+use crate::server::connections::connection_manager::{ConnectionManager, Connections};
 use crate::server::packet_handler::builder::PacketHandlerBuilder;
 use crate::server::packet_handler::packet_handler::PacketHandlerTrait;
 use crate::server::packets::packet::Packet;
 use crate::server::state::authorization_handler::AuthorizationHandlerTrait;
 use crate::server::state::state_handler::StateHandler;
 use crate::server::state::ticker::TickerTrait;
-use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex, RwLock};
 
@@ -19,35 +21,51 @@ pub struct ServerPacketReceiver {
     ticker: Arc<Mutex<dyn TickerTrait>>,
     state: Arc<Mutex<ServerPacketReceiverState>>,
     packet_handler: Arc<Mutex<dyn PacketHandlerTrait>>,
+    connection_manager: Arc<RwLock<ConnectionManager>>,
 }
 
 pub struct ServerPacketReceiverState {
     pub(super) state_handler: Box<dyn StateHandler>,
-    connections: HashMap<SocketAddr, u128>,
 }
 
 impl ServerPacketReceiver {
     pub fn new(
         state_handler: Box<dyn StateHandler>,
         ticker: Arc<Mutex<dyn TickerTrait>>,
-        authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
+        accounts: Arc<AccountService>,
+        characters: Arc<CharacterService>,
     ) -> Self {
-        let state = ServerPacketReceiverState {
-            state_handler,
-            connections: HashMap::new(),
-        };
+        // systems see the same connections, accounts and characters as the packet handlers
+        {
+            let world = state_handler.get_world();
+            let mut world = world.write().expect("Failed to get write lock to world");
+
+            world.insert_resource(Connections(connection_manager.clone()));
+            world.insert_resource(Accounts(accounts.clone()));
+            world.insert_resource(Characters(characters.clone()));
+        }
+
+        let state = ServerPacketReceiverState { state_handler };
 
         let state = Arc::new(Mutex::new(state));
 
+        let authorization_handler: Arc<RwLock<dyn AuthorizationHandlerTrait>> =
+            connection_manager.clone();
+
         let packet_handler = PacketHandlerBuilder::new()
-            .with_enter_handler(authorization_handler.clone())
+            .with_enter_handler(authorization_handler.clone(), characters.clone())
             .with_move_handler(authorization_handler.clone())
+            .with_snapshot_ack_handler(connection_manager.clone())
+            .with_account_handlers(accounts)
+            .with_character_handlers(authorization_handler.clone(), characters)
             .build();
 
         ServerPacketReceiver {
             ticker,
             state,
             packet_handler: Arc::new(Mutex::new(packet_handler)),
+            connection_manager,
         }
     }
 
@@ -70,25 +88,23 @@ impl ServerPacketReceiver {
 
 impl PacketReceiver for ServerPacketReceiver {
     fn consume(&self, packet: Packet, addr: SocketAddr) {
-        let packet_id = packet.id;
+        // the payload is left out, it can carry credentials
+        trace!(
+            "Received packet {} ({:?}) from {:?}",
+            packet.id,
+            packet.opcode,
+            addr
+        );
 
-        trace!("Received packet: {:?} from {:?}", packet, addr);
+        let received = self
+            .connection_manager
+            .write()
+            .expect("Failed to get write lock on connection manager")
+            .receive(addr, packet.id, packet.data.len(), packet.session);
 
-        if self.state.lock().unwrap().connections.get(&addr).is_none() {
-            self.state
-                .lock()
-                .unwrap()
-                .connections
-                .insert(addr, packet_id);
-        } else if self.state.lock().unwrap().connections.get(&addr).unwrap() > &packet_id {
-            warn!("Packet loss detected, dropping packet...");
+        if let Err(e) = received {
+            warn!("Dropping packet from {:?}: {}", addr, e);
             return;
-        } else {
-            self.state
-                .lock()
-                .unwrap()
-                .connections
-                .insert(addr, packet_id);
         }
 
         self.packet_handler
@@ -150,7 +166,6 @@ mod tests {
         let packet_handler: Arc<Mutex<dyn PacketHandlerTrait>> = Arc::new(Mutex::new(handler));
         let state = ServerPacketReceiverState {
             state_handler: Box::new(state_handler),
-            connections: HashMap::new(),
         };
         let state = Arc::new(Mutex::new(state));
 
@@ -166,7 +181,6 @@ mod tests {
     mod consume_tests {
         use super::*;
         use bevy_ecs::world::World;
-        use std::collections::HashMap;
         use std::net::{Ipv4Addr, SocketAddr};
         use std::sync::RwLock;
         use std::sync::{Arc, Mutex};
@@ -192,20 +206,35 @@ mod tests {
 
         fn make_receiver_with_handler(
             handler: Arc<Mutex<dyn PacketHandlerTrait>>,
-            connections: HashMap<SocketAddr, u128>,
+            last_received_id: Option<u128>,
         ) -> ServerPacketReceiver {
             let state_handler = Box::new(MockStateHandler);
-            let state = ServerPacketReceiverState {
-                state_handler,
-                connections,
-            };
+            let state = ServerPacketReceiverState { state_handler };
+
+            let mut connection_manager = ConnectionManager::default();
+            if let Some(id) = last_received_id {
+                connection_manager
+                    .receive(test_addr(), id, 0, None)
+                    .unwrap();
+            }
+
             ServerPacketReceiver {
                 ticker: Arc::new(Mutex::new(MockTicker)),
                 state: Arc::new(Mutex::new(state)),
                 packet_handler: handler,
+                connection_manager: Arc::new(RwLock::new(connection_manager)),
             }
         }
 
+        fn last_received_id(receiver: &ServerPacketReceiver) -> Option<u128> {
+            receiver
+                .connection_manager
+                .read()
+                .unwrap()
+                .get(test_addr())
+                .and_then(|connection| connection.last_received_id)
+        }
+
         struct MockTicker;
         impl TickerTrait for MockTicker {
             fn register(&mut self, _f: Box<dyn Fn() + Send>) {}
@@ -223,20 +252,20 @@ mod tests {
             let handler = Arc::new(Mutex::new(MockPacketHandler {
                 called: called.clone(),
             }));
-            let receiver = make_receiver_with_handler(handler.clone(), HashMap::new());
+            let receiver = make_receiver_with_handler(handler.clone(), None);
 
             let packet = Packet {
                 id: 42,
                 opcode: crate::server::opcode::OpCode::Spawn,
                 data: "".to_string(),
+                session: None,
             };
             let addr = test_addr();
 
             receiver.consume(packet.clone(), addr);
 
             // Should insert connection
-            let state = receiver.state.lock().unwrap();
-            assert_eq!(state.connections.get(&addr), Some(&42));
+            assert_eq!(last_received_id(&receiver), Some(42));
             // Should call handle_packet
             assert_eq!(*called.lock().unwrap(), Some(packet));
         }
@@ -248,22 +277,20 @@ mod tests {
                 called: called.clone(),
             }));
 
-            let mut connections = HashMap::new();
-            connections.insert(test_addr(), 100);
-            let receiver = make_receiver_with_handler(handler.clone(), connections);
+            let receiver = make_receiver_with_handler(handler.clone(), Some(100));
 
             let packet = Packet {
                 id: 50,
                 opcode: crate::server::opcode::OpCode::Spawn,
                 data: "".to_string(),
+                session: None,
             };
             let addr = test_addr();
 
             receiver.consume(packet.clone(), addr);
 
             // Should NOT update connection
-            let state = receiver.state.lock().unwrap();
-            assert_eq!(state.connections.get(&addr), Some(&100));
+            assert_eq!(last_received_id(&receiver), Some(100));
             // Should NOT call handle_packet
             assert_eq!(*called.lock().unwrap(), None);
         }
@@ -275,22 +302,20 @@ mod tests {
                 called: called.clone(),
             }));
 
-            let mut connections = HashMap::new();
-            connections.insert(test_addr(), 10);
-            let receiver = make_receiver_with_handler(handler.clone(), connections);
+            let receiver = make_receiver_with_handler(handler.clone(), Some(10));
 
             let packet = Packet {
                 id: 20,
                 opcode: crate::server::opcode::OpCode::Spawn,
                 data: "".to_string(),
+                session: None,
             };
             let addr = test_addr();
 
             receiver.consume(packet.clone(), addr);
 
             // Should update connection
-            let state = receiver.state.lock().unwrap();
-            assert_eq!(state.connections.get(&addr), Some(&20));
+            assert_eq!(last_received_id(&receiver), Some(20));
             // Should call handle_packet
             assert_eq!(*called.lock().unwrap(), Some(packet));
         }
@@ -300,7 +325,6 @@ mod tests {
     mod initialise_tests {
         use super::*;
         use bevy_ecs::world::World;
-        use std::collections::HashMap;
         use std::sync::RwLock;
         use std::sync::{Arc, Mutex};
 
@@ -345,15 +369,13 @@ mod tests {
                 start_called: start_called.clone(),
             });
 
-            let state = ServerPacketReceiverState {
-                state_handler,
-                connections: HashMap::new(),
-            };
+            let state = ServerPacketReceiverState { state_handler };
 
             let mut receiver = ServerPacketReceiver {
                 ticker: Arc::new(Mutex::new(ticker)),
                 state: Arc::new(Mutex::new(state)),
                 packet_handler: Arc::new(Mutex::new(MockPacketHandler)),
+                connection_manager: Arc::new(RwLock::new(ConnectionManager::default())),
             };
 
             receiver.initialise();
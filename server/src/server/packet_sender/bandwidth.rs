@@ -0,0 +1,389 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+};
+
+use log::{trace, warn};
+
+use crate::server::{
+    opcode::{OpCode, Reliability},
+    packet_sender::{send_packet::SendPacket, TargetAddress},
+    protocols::send::replicate_packet::ReplicatePacket,
+};
+
+// rough size of the `Packet` envelope around the payload, id and opcode included
+const PACKET_OVERHEAD: usize = 48;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BandwidthConfig {
+    // bytes each connection may be sent per tick, the rest waits for later ticks
+    pub bytes_per_tick: usize,
+    // packets a connection may have queued, the stalest unreliable ones are dropped past it
+    pub max_pending: usize,
+    // ticks a connection may stay over `max_pending` on reliable packets alone before it is dropped
+    pub backlog_ticks: u32,
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        BandwidthConfig {
+            bytes_per_tick: 8192,
+            max_pending: 512,
+            backlog_ticks: 40,
+        }
+    }
+}
+
+struct Pending {
+    packet: SendPacket,
+    // grows by the packet's priority every tick it waits, so nothing starves
+    accumulated: f32,
+    waited: u32,
+}
+
+impl Pending {
+    fn size(&self) -> usize {
+        self.packet.packet_data.len() + PACKET_OVERHEAD
+    }
+
+    // an unreliable packet is worthless once a newer one about the same thing is queued
+    fn is_superseded_by(&self, packet: &SendPacket) -> bool {
+        packet.opcode.reliability() == Reliability::Unreliable
+            && self.packet.opcode == packet.opcode
+            && self.packet.entity == packet.entity
+    }
+
+    // takes the newer packet on, keeping the replicated components only this one carried
+    fn supersede(&mut self, mut packet: SendPacket) {
+        if packet.opcode == OpCode::ReplicateUnreliable {
+            let older = serde_json::from_str::<ReplicatePacket>(&self.packet.packet_data);
+            let newer = serde_json::from_str::<ReplicatePacket>(&packet.packet_data);
+
+            if let (Ok(mut merged), Ok(newer)) = (older, newer) {
+                merged.merge(newer);
+                packet.packet_data =
+                    serde_json::to_string(&merged).expect("Failed to serialize ReplicatePacket");
+            }
+        }
+
+        self.packet = packet;
+    }
+}
+
+pub struct BandwidthBudget {
+    config: BandwidthConfig,
+    pending: HashMap<SocketAddr, Vec<Pending>>,
+    // consecutive ticks each connection has been over `max_pending`
+    over_cap: HashMap<SocketAddr, u32>,
+    backlogged: Vec<SocketAddr>,
+}
+
+impl BandwidthBudget {
+    pub fn new(config: BandwidthConfig) -> Self {
+        BandwidthBudget {
+            config,
+            pending: HashMap::new(),
+            over_cap: HashMap::new(),
+            backlogged: vec![],
+        }
+    }
+
+    pub fn deferred(&self, addr: SocketAddr) -> usize {
+        self.pending.get(&addr).map_or(0, Vec::len)
+    }
+
+    // connections that stayed over `max_pending` for `backlog_ticks`, to be disconnected
+    pub fn take_backlogged(&mut self) -> Vec<SocketAddr> {
+        std::mem::take(&mut self.backlogged)
+    }
+
+    // queues `packets` per connection and returns what fits this tick, each targeted at one address
+    pub fn schedule(
+        &mut self,
+        packets: Vec<SendPacket>,
+        connections: &HashSet<SocketAddr>,
+    ) -> Vec<SendPacket> {
+        self.pending.retain(|addr, _| connections.contains(addr));
+        self.over_cap.retain(|addr, _| connections.contains(addr));
+
+        for packet in packets {
+            let addrs: Vec<SocketAddr> = match &packet.addr {
+                TargetAddress::Broadcast => connections.iter().copied().collect(),
+                TargetAddress::Targeted(addrs) => addrs.clone(),
+            };
+
+            for addr in addrs {
+                self.push(addr, packet.clone());
+            }
+        }
+
+        let mut ready = vec![];
+
+        for (addr, queue) in self.pending.iter_mut() {
+            for pending in queue.iter_mut() {
+                pending.accumulated += pending.packet.priority;
+                pending.waited += 1;
+            }
+
+            // stable, so equally urgent packets keep the order they were enqueued in
+            queue.sort_by(|a, b| b.accumulated.total_cmp(&a.accumulated));
+
+            let mut budget = self.config.bytes_per_tick;
+            let mut sent = 0;
+
+            // stops at the first packet that does not fit, a smaller one must not overtake it
+            for pending in queue.iter() {
+                let size = pending.size();
+
+                // an oversized packet still goes out alone rather than never
+                if size > budget && sent > 0 {
+                    break;
+                }
+
+                budget = budget.saturating_sub(size);
+                sent += 1;
+            }
+
+            trace!(
+                "Sending {} packets to {:?}, deferring {}",
+                sent,
+                addr,
+                queue.len() - sent
+            );
+
+            ready.extend(queue.drain(..sent).map(|pending| pending.packet));
+
+            trim(queue, self.config.max_pending);
+
+            if queue.len() <= self.config.max_pending {
+                self.over_cap.remove(addr);
+                continue;
+            }
+
+            let ticks = self.over_cap.entry(*addr).or_default();
+            *ticks += 1;
+
+            if *ticks >= self.config.backlog_ticks {
+                warn!(
+                    "{:?} has had over {} packets queued for {} ticks",
+                    addr, self.config.max_pending, ticks
+                );
+                self.backlogged.push(*addr);
+            }
+        }
+
+        self.pending.retain(|_, queue| !queue.is_empty());
+
+        ready
+    }
+
+    fn push(&mut self, addr: SocketAddr, mut packet: SendPacket) {
+        packet.addr = TargetAddress::Targeted(vec![addr]);
+
+        let queue = self.pending.entry(addr).or_default();
+
+        match queue
+            .iter_mut()
+            .find(|pending| pending.is_superseded_by(&packet))
+        {
+            Some(pending) => pending.supersede(packet),
+            None => queue.push(Pending {
+                packet,
+                accumulated: 0.0,
+                waited: 0,
+            }),
+        }
+    }
+}
+
+// drops the unreliable packets that waited longest until `max_pending` are left, reliable ones
+// can only be sent
+fn trim(queue: &mut Vec<Pending>, max_pending: usize) {
+    let excess = queue.len().saturating_sub(max_pending);
+
+    if excess == 0 {
+        return;
+    }
+
+    let mut stale: Vec<usize> = (0..queue.len())
+        .filter(|index| queue[*index].packet.opcode.reliability() == Reliability::Unreliable)
+        .collect();
+    stale.sort_by_key(|index| Reverse(queue[*index].waited));
+    stale.truncate(excess);
+
+    trace!("Dropping {} stale packets", stale.len());
+
+    let stale: HashSet<usize> = stale.into_iter().collect();
+    let mut index = 0;
+
+    queue.retain(|_| {
+        index += 1;
+        !stale.contains(&(index - 1))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{
+        components::shared::net_id::NetId, protocols::send::replicate_packet::ComponentUpdate,
+    };
+
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:7777".parse().unwrap()
+    }
+
+    fn packet(opcode: OpCode, bytes: usize) -> SendPacket {
+        SendPacket::new("x".repeat(bytes), opcode, TargetAddress::Broadcast)
+    }
+
+    fn budget(bytes_per_tick: usize) -> BandwidthBudget {
+        BandwidthBudget::new(BandwidthConfig {
+            bytes_per_tick,
+            ..BandwidthConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_packets_over_budget_are_deferred_not_dropped() {
+        let mut budget = budget(2 * (100 + PACKET_OVERHEAD));
+        let connections = HashSet::from([addr()]);
+
+        let packets = (0..5).map(|_| packet(OpCode::Spawn, 100)).collect();
+
+        assert_eq!(budget.schedule(packets, &connections).len(), 2);
+        assert_eq!(budget.deferred(addr()), 3);
+        assert_eq!(budget.schedule(vec![], &connections).len(), 2);
+        assert_eq!(budget.schedule(vec![], &connections).len(), 1);
+        assert_eq!(budget.deferred(addr()), 0);
+    }
+
+    #[test]
+    fn test_higher_priority_goes_first_and_waiting_raises_priority() {
+        let mut budget = budget(1);
+        let connections = HashSet::from([addr()]);
+
+        let sent = budget.schedule(
+            vec![packet(OpCode::Snapshot, 10), packet(OpCode::Enown, 10)],
+            &connections,
+        );
+        assert_eq!(sent[0].opcode, OpCode::Enown);
+
+        // the snapshot has been waiting, a fresh replicate does not overtake it forever
+        let mut opcodes = vec![];
+        for _ in 0..4 {
+            let sent = budget.schedule(vec![packet(OpCode::ReplicateUnreliable, 10)], &connections);
+            opcodes.push(sent[0].opcode);
+        }
+        assert!(opcodes.contains(&OpCode::Snapshot));
+    }
+
+    #[test]
+    fn test_newer_unreliable_packet_replaces_deferred_one() {
+        let mut budget = budget(1);
+        let connections = HashSet::from([addr()]);
+        let id = NetId::new(1, 0);
+
+        budget.schedule(
+            vec![
+                packet(OpCode::Enown, 10),
+                packet(OpCode::ReplicateUnreliable, 10).about(id),
+                packet(OpCode::ReplicateUnreliable, 20).about(id),
+                packet(OpCode::Replicate, 10).about(id),
+                packet(OpCode::Replicate, 20).about(id),
+            ],
+            &connections,
+        );
+
+        assert_eq!(budget.deferred(addr()), 3);
+    }
+
+    #[test]
+    fn test_superseded_replicates_keep_the_other_components() {
+        let mut budget = budget(1);
+        let connections = HashSet::from([addr()]);
+        let id = NetId::new(1, 0);
+
+        let replicate = |name: &str, value: u32| {
+            let packet = ReplicatePacket {
+                id,
+                components: vec![ComponentUpdate {
+                    name: name.to_string(),
+                    data: serde_json::json!(value),
+                }],
+            };
+
+            SendPacket::new(
+                serde_json::to_string(&packet).unwrap(),
+                OpCode::ReplicateUnreliable,
+                TargetAddress::Broadcast,
+            )
+            .about(id)
+        };
+
+        budget.schedule(
+            vec![
+                packet(OpCode::Enown, 10),
+                replicate("Health", 1),
+                replicate("Mana", 2),
+                replicate("Health", 3),
+            ],
+            &connections,
+        );
+
+        let sent = budget.schedule(vec![], &connections);
+        let packet: ReplicatePacket = serde_json::from_str(&sent[0].packet_data).unwrap();
+
+        assert_eq!(
+            packet.components,
+            vec![
+                ComponentUpdate {
+                    name: "Health".to_string(),
+                    data: serde_json::json!(3),
+                },
+                ComponentUpdate {
+                    name: "Mana".to_string(),
+                    data: serde_json::json!(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_full_queues_drop_stale_unreliable_packets_then_the_peer() {
+        let mut budget = BandwidthBudget::new(BandwidthConfig {
+            bytes_per_tick: 1,
+            max_pending: 3,
+            backlog_ticks: 2,
+        });
+        let connections = HashSet::from([addr()]);
+
+        let unreliable =
+            (0..4).map(|i| packet(OpCode::ReplicateUnreliable, 10).about(NetId::new(i, 0)));
+        budget.schedule(unreliable.collect(), &connections);
+
+        // one went out, the rest fit
+        assert_eq!(budget.deferred(addr()), 3);
+
+        budget.schedule(
+            (0..3).map(|_| packet(OpCode::Spawn, 10)).collect(),
+            &connections,
+        );
+
+        // the reliable ones stay, only the unreliable make room
+        assert_eq!(budget.deferred(addr()), 3);
+        assert!(budget.take_backlogged().is_empty());
+
+        budget.schedule(
+            (0..3).map(|_| packet(OpCode::Spawn, 10)).collect(),
+            &connections,
+        );
+        assert_eq!(budget.deferred(addr()), 4);
+        assert!(budget.take_backlogged().is_empty());
+
+        budget.schedule(vec![packet(OpCode::Spawn, 10)], &connections);
+        assert_eq!(budget.take_backlogged(), vec![addr()]);
+    }
+}
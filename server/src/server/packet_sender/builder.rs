@@ -1,6 +1,8 @@
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::server::state::{packet_id_generator::PacketIdGenerator, ticker::TickerTrait};
+use crate::server::{
+    connections::connection_manager::ConnectionManager, state::ticker::TickerTrait,
+};
 
 use super::packet_sender::ServerPacketSender;
 
@@ -9,8 +11,8 @@ pub struct ServerPacketSenderBuilder;
 impl ServerPacketSenderBuilder {
     pub fn build(
         ticker: Arc<Mutex<dyn TickerTrait>>,
-        packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
     ) -> ServerPacketSender {
-        ServerPacketSender::new(ticker, packet_id_generator)
+        ServerPacketSender::new(ticker, connection_manager)
     }
 }
@@ -2,11 +2,12 @@ use std::net::SocketAddr;
 
 use serde::{Deserialize, Serialize};
 
+pub mod bandwidth;
 pub mod builder;
 pub mod packet_sender;
 pub mod send_packet;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum TargetAddress {
     Broadcast,
     Targeted(Vec<SocketAddr>),
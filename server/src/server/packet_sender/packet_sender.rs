@@ -1,52 +1,58 @@
 use std::{
     collections::{HashMap, HashSet},
     net::SocketAddr,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
 };
 
 use log::{debug, error, info, trace};
 use tokio::{io::Interest, net::UdpSocket};
 
 use crate::server::{
-    packet_sender::{send_packet::SendPacket, TargetAddress},
+    connections::connection_manager::ConnectionManager,
+    packet_sender::{
+        bandwidth::{BandwidthBudget, BandwidthConfig},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
     packets::packet::Packet,
-    state::{packet_id_generator::PacketIdGenerator, ticker::TickerTrait},
+    state::ticker::TickerTrait,
 };
 
 pub struct ServerPacketSenderState {
     pub packet_datas: Vec<SendPacket>,
-    pub connections: HashSet<SocketAddr>,
     pub socket: Option<Arc<UdpSocket>>,
+    pub bandwidth: BandwidthBudget,
 }
 
 // this is the trivial implementation where everything gets broadcasted to everyone
 pub trait PacketSender: Send + Sync {
     fn try_register(&mut self, addr: SocketAddr);
     fn enqueue(&self, send_packet: SendPacket);
+    fn connections(&self) -> HashSet<SocketAddr>;
     fn initialise(&mut self, socket: Arc<UdpSocket>);
     fn emit_packets(
         packet_datas: Vec<SendPacket>,
         connections: HashSet<SocketAddr>,
         socket: Arc<UdpSocket>,
-        packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
     );
 }
 
 pub struct ServerPacketSender {
     state: Arc<Mutex<ServerPacketSenderState>>,
     ticker: Arc<Mutex<dyn TickerTrait>>,
-    packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+    connection_manager: Arc<RwLock<ConnectionManager>>,
 }
 
 impl ServerPacketSender {
     pub fn new(
         ticker: Arc<Mutex<dyn TickerTrait>>,
-        packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
     ) -> Self {
         let state = ServerPacketSenderState {
             packet_datas: vec![],
-            connections: HashSet::new(),
             socket: None,
+            bandwidth: BandwidthBudget::new(BandwidthConfig::default()),
         };
 
         let state = Arc::new(Mutex::new(state));
@@ -54,19 +60,22 @@ impl ServerPacketSender {
         ServerPacketSender {
             state,
             ticker,
-            packet_id_generator,
+            connection_manager,
         }
     }
+
+    pub fn with_bandwidth_config(self, config: BandwidthConfig) -> Self {
+        self.state.lock().unwrap().bandwidth = BandwidthBudget::new(config);
+        self
+    }
 }
 
 impl PacketSender for ServerPacketSender {
     fn try_register(&mut self, addr: SocketAddr) {
-        let mut state = self.state.lock().unwrap();
-
-        if !state.connections.contains(&Arc::new(addr)) {
-            info!("Registering address {:?}", addr);
-            state.connections.insert(addr);
-        }
+        self.connection_manager
+            .write()
+            .expect("Failed to get write lock on connection manager")
+            .register(addr);
     }
 
     fn enqueue(&self, send_packet: SendPacket) {
@@ -80,6 +89,13 @@ impl PacketSender for ServerPacketSender {
         state.packet_datas.push(send_packet);
     }
 
+    fn connections(&self) -> HashSet<SocketAddr> {
+        self.connection_manager
+            .read()
+            .expect("Failed to get read lock on connection manager")
+            .addrs()
+    }
+
     fn initialise(&mut self, socket: Arc<UdpSocket>) {
         info!("Initialising packet sender");
 
@@ -89,22 +105,42 @@ impl PacketSender for ServerPacketSender {
 
         let state = self.state.clone();
 
-        let packet_id_generator = self.packet_id_generator.clone();
+        let connection_manager = self.connection_manager.clone();
 
         self.ticker.lock().unwrap().register(Box::new(move || {
             // Emit packets every tick
             let mut state = state.lock().expect("Failed to lock packet sender state");
 
             let packets = std::mem::take(&mut state.packet_datas);
+            let connections = connection_manager
+                .read()
+                .expect("Failed to get read lock on connection manager")
+                .addrs();
+
+            // whatever does not fit a connection's budget stays queued for the next tick
+            let packets = state.bandwidth.schedule(packets, &connections);
+
+            // dropped by `connection_timeout_system`, which takes their entities out of the world
+            let backlogged = state.bandwidth.take_backlogged();
+
+            if !backlogged.is_empty() {
+                let mut connection_manager = connection_manager
+                    .write()
+                    .expect("Failed to get write lock on connection manager");
+
+                for addr in backlogged {
+                    connection_manager.mark_backlogged(addr);
+                }
+            }
 
             ServerPacketSender::emit_packets(
                 packets,
-                state.connections.clone(),
+                connections,
                 state
                     .socket
                     .clone()
                     .expect("Socket should be initialized before emitting packets"),
-                packet_id_generator.clone(),
+                connection_manager.clone(),
             );
         }));
     }
@@ -113,7 +149,7 @@ impl PacketSender for ServerPacketSender {
         packets: Vec<SendPacket>,
         connections: HashSet<SocketAddr>,
         socket: Arc<UdpSocket>,
-        packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+        connection_manager: Arc<RwLock<ConnectionManager>>,
     ) {
         trace!(
             "Emitting {} packets to {} connections",
@@ -153,17 +189,17 @@ impl PacketSender for ServerPacketSender {
             }
 
             for addr in &connections {
-                let packet_id_generator = packet_id_generator
-                    .lock()
-                    .expect("Failed to get lock to shared_id_generator");
+                let mut connection_manager = connection_manager
+                    .write()
+                    .expect("Failed to get write lock on connection manager");
 
                 if let Some(send_packets) = packets_by_addr.get(addr) {
                     for send_packet in send_packets {
-                        let packet = Packet {
-                            id: packet_id_generator.generate_id(*addr),
-                            opcode: send_packet.opcode,
-                            data: send_packet.packet_data.clone(),
-                        };
+                        let packet = Packet::new(
+                            connection_manager.next_packet_id(*addr),
+                            send_packet.opcode,
+                            send_packet.packet_data.clone(),
+                        );
 
                         let bytes = match serde_json::to_vec(&packet) {
                             Ok(b) => b,
@@ -173,6 +209,8 @@ impl PacketSender for ServerPacketSender {
                             }
                         };
 
+                        connection_manager.record_sent(*addr, bytes.len());
+
                         let socket = socket.clone();
                         let addr = *addr;
                         let bytes = bytes.clone();
@@ -207,7 +245,7 @@ mod tests {
     use super::*;
     use crate::server::opcode::OpCode;
     use std::net::{Ipv4Addr, SocketAddr};
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Mutex, RwLock};
 
     fn test_addr(port: u16) -> SocketAddr {
         SocketAddr::from((Ipv4Addr::LOCALHOST, port))
@@ -216,40 +254,32 @@ mod tests {
     #[test]
     fn test_try_register_adds_new_address() {
         let ticker = Arc::new(Mutex::new(MockTicker));
-        let packet_id_generator = Arc::new(Mutex::new(PacketIdGenerator::new()));
-        let mut sender = ServerPacketSender::new(ticker, packet_id_generator);
+        let connection_manager = Arc::new(RwLock::new(ConnectionManager::default()));
+        let mut sender = ServerPacketSender::new(ticker, connection_manager);
 
         let addr = test_addr(12345);
 
-        {
-            let state = sender.state.lock().unwrap();
-            assert!(!state.connections.contains(&addr));
-        }
+        assert!(!sender.connections().contains(&addr));
 
         sender.try_register(addr);
 
-        {
-            let state = sender.state.lock().unwrap();
-            assert!(state.connections.contains(&addr));
-        }
+        assert!(sender.connections().contains(&addr));
     }
 
     #[test]
     fn test_try_register_does_not_duplicate_address() {
         let ticker = Arc::new(Mutex::new(MockTicker));
-        let packet_id_generator = Arc::new(Mutex::new(PacketIdGenerator::new()));
-        let mut sender = ServerPacketSender::new(ticker, packet_id_generator);
+        let connection_manager = Arc::new(RwLock::new(ConnectionManager::default()));
+        let mut sender = ServerPacketSender::new(ticker, connection_manager);
 
         let addr = test_addr(12345);
 
         sender.try_register(addr);
         sender.try_register(addr);
 
-        {
-            let state = sender.state.lock().unwrap();
-            assert!(state.connections.contains(&addr));
-            assert_eq!(state.connections.iter().filter(|&&a| a == addr).count(), 1);
-        }
+        let connections = sender.connections();
+        assert!(connections.contains(&addr));
+        assert_eq!(connections.len(), 1);
     }
 
     struct MockTicker;
@@ -261,16 +291,12 @@ mod tests {
     #[test]
     fn test_enqueue_adds_packet_to_state() {
         let ticker = Arc::new(Mutex::new(MockTicker));
-        let packet_id_generator = Arc::new(Mutex::new(PacketIdGenerator::new()));
-        let sender = ServerPacketSender::new(ticker, packet_id_generator);
+        let connection_manager = Arc::new(RwLock::new(ConnectionManager::default()));
+        let sender = ServerPacketSender::new(ticker, connection_manager);
 
         let data = "test".to_string();
         let opcode = OpCode::Spawn;
-        let send_packet = SendPacket {
-            addr: TargetAddress::Broadcast,
-            opcode,
-            packet_data: data.clone(),
-        };
+        let send_packet = SendPacket::new(data.clone(), opcode, TargetAddress::Broadcast);
 
         sender.enqueue(send_packet);
 
@@ -283,22 +309,14 @@ mod tests {
     #[test]
     fn test_enqueue_multiple_packets() {
         let ticker = Arc::new(Mutex::new(MockTicker));
-        let packet_id_generator = Arc::new(Mutex::new(PacketIdGenerator::new()));
-        let sender = ServerPacketSender::new(ticker, packet_id_generator);
+        let connection_manager = Arc::new(RwLock::new(ConnectionManager::default()));
+        let sender = ServerPacketSender::new(ticker, connection_manager);
 
         let data1 = "first".to_string();
         let data2 = "second".to_string();
         let opcode = OpCode::Spawn;
-        let send_packet1 = SendPacket {
-            addr: TargetAddress::Broadcast,
-            opcode,
-            packet_data: data1.clone(),
-        };
-        let send_packet2 = SendPacket {
-            addr: TargetAddress::Broadcast,
-            opcode,
-            packet_data: data2.clone(),
-        };
+        let send_packet1 = SendPacket::new(data1.clone(), opcode, TargetAddress::Broadcast);
+        let send_packet2 = SendPacket::new(data2.clone(), opcode, TargetAddress::Broadcast);
 
         sender.enqueue(send_packet1);
         sender.enqueue(send_packet2);
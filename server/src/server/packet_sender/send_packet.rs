@@ -1,12 +1,18 @@
 use serde::{Deserialize, Serialize};
 
-use crate::server::{opcode::OpCode, packet_sender::TargetAddress};
+use crate::server::{
+    components::shared::net_id::NetId, opcode::OpCode, packet_sender::TargetAddress,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SendPacket {
     pub packet_data: String,
     pub opcode: OpCode,
     pub addr: TargetAddress,
+    // decides what goes first once a connection runs out of bandwidth for the tick
+    pub priority: f32,
+    // the entity the packet is about, if any
+    pub entity: Option<NetId>,
 }
 
 impl SendPacket {
@@ -15,6 +21,19 @@ impl SendPacket {
             packet_data,
             opcode,
             addr,
+            priority: opcode.importance(),
+            entity: None,
         }
     }
+
+    pub fn about(mut self, entity: NetId) -> Self {
+        self.entity = Some(entity);
+        self
+    }
+
+    // scales the priority, e.g. by how close the entity is to the receiving player
+    pub fn with_relevance(mut self, relevance: f32) -> Self {
+        self.priority *= relevance;
+        self
+    }
 }
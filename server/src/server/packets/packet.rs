@@ -1,12 +1,17 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::server::opcode::OpCode;
+use crate::server::{connections::session_token::SessionToken, opcode::OpCode};
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq, JsonSchema)]
 pub struct Packet {
     pub id: u128,
+    #[schemars(with = "u16")]
     pub opcode: OpCode,
     pub data: String,
+    // the client's session once it has entered, lets the server follow it to a new address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionToken>,
 }
 
 impl Packet {
@@ -15,6 +20,12 @@ impl Packet {
             id,
             opcode,
             data,
+            session: None,
         }
     }
+
+    pub fn with_session(mut self, session: Option<SessionToken>) -> Self {
+        self.session = session;
+        self
+    }
 }
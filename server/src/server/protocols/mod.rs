@@ -1,2 +1,4 @@
 pub mod recv;
+pub mod schema;
 pub mod send;
+pub mod version;
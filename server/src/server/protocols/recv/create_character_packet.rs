@@ -0,0 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// re-exported so clients can build the packet without depending on `common`
+pub use common::model::{Appearance, CharacterClass};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateCharacterPacket {
+    pub name: String,
+    pub class: CharacterClass,
+    pub appearance: Appearance,
+}
+
+impl CreateCharacterPacket {
+    pub fn new(name: String, class: CharacterClass, appearance: Appearance) -> Self {
+        CreateCharacterPacket {
+            name,
+            class,
+            appearance,
+        }
+    }
+}
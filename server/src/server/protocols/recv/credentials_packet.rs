@@ -0,0 +1,27 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// the payload of both Register and Login
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CredentialsPacket {
+    pub name: String,
+    pub password: String,
+}
+
+impl CredentialsPacket {
+    pub fn new(name: String, password: String) -> Self {
+        CredentialsPacket { name, password }
+    }
+}
+
+// packets are traced as they are handled, the password must not end up in the logs
+impl fmt::Debug for CredentialsPacket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CredentialsPacket")
+            .field("name", &self.name)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
@@ -0,0 +1,14 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteCharacterPacket {
+    // as listed in the character list
+    pub id: String,
+}
+
+impl DeleteCharacterPacket {
+    pub fn new(id: String) -> Self {
+        DeleteCharacterPacket { id }
+    }
+}
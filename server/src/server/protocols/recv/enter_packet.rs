@@ -1,4 +1,23 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EnterPacket {}
+use crate::server::protocols::version::ProtocolVersion;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EnterPacket {
+    // optional so that clients predating versioning still get a proper rejection
+    #[serde(default)]
+    pub protocol_version: Option<ProtocolVersion>,
+    // id of a character from the character list, rejected when missing
+    #[serde(default)]
+    pub character: Option<String>,
+}
+
+impl EnterPacket {
+    pub fn new(protocol_version: ProtocolVersion, character: String) -> Self {
+        EnterPacket {
+            protocol_version: Some(protocol_version),
+            character: Some(character),
+        }
+    }
+}
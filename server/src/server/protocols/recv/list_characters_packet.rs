@@ -0,0 +1,6 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// asks for the logged in account's characters, answered with a character list
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ListCharactersPacket {}
@@ -1,2 +1,7 @@
+pub mod create_character_packet;
+pub mod credentials_packet;
+pub mod delete_character_packet;
 pub mod enter_packet;
+pub mod list_characters_packet;
 pub mod move_packet;
+pub mod snapshot_ack_packet;
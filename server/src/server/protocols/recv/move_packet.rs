@@ -1,14 +1,30 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::server::components::{movement_state::MovementStateType, shared::vec3d::Vec3d};
-#[derive(Debug, Serialize, Deserialize)]
+use crate::server::components::{
+    movement_state::MovementStateType,
+    shared::{net_id::NetId, vec3d::Vec3d},
+};
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct MovePacket {
     pub vector: Vec3d,
     pub state: MovementStateType,
+    // an entity the client was granted control of, the player character when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<NetId>,
 }
 
 impl MovePacket {
     pub fn new(vector: Vec3d, state: MovementStateType) -> Self {
-        MovePacket { vector, state }
+        MovePacket {
+            vector,
+            state,
+            target: None,
+        }
+    }
+
+    pub fn targeting(mut self, target: NetId) -> Self {
+        self.target = Some(target);
+        self
     }
 }
@@ -0,0 +1,13 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotAckPacket {
+    pub sequence: u32,
+}
+
+impl SnapshotAckPacket {
+    pub fn new(sequence: u32) -> Self {
+        SnapshotAckPacket { sequence }
+    }
+}
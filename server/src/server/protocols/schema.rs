@@ -0,0 +1,123 @@
+use schemars::{schema::RootSchema, schema_for};
+use serde::Serialize;
+
+use crate::server::{
+    opcode::{Direction, OpCode, Reliability},
+    packets::packet::Packet,
+    protocols::{
+        recv::{
+            create_character_packet::CreateCharacterPacket, credentials_packet::CredentialsPacket,
+            delete_character_packet::DeleteCharacterPacket, enter_packet::EnterPacket,
+            list_characters_packet::ListCharactersPacket, move_packet::MovePacket,
+            snapshot_ack_packet::SnapshotAckPacket,
+        },
+        send::{
+            authenticated_packet::AuthenticatedPacket, character_list_packet::CharacterListPacket,
+            control_packet::ControlPacket, despawn_packet::DespawnPacket,
+            enown_packet::EnownPacket, error_packet::ErrorPacket,
+            replicate_packet::ReplicatePacket, snapshot_packet::SnapshotPacket,
+            spawn_packet::SpawnPacket,
+        },
+        version::{ProtocolVersion, PROTOCOL_VERSION},
+    },
+};
+
+#[derive(Serialize, Debug)]
+pub struct ProtocolSchema {
+    pub protocol_version: ProtocolVersion,
+    // every datagram is a json encoded envelope, its `data` field holds the json encoded payload
+    pub envelope: RootSchema,
+    pub opcodes: Vec<OpCodeSchema>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OpCodeSchema {
+    pub name: String,
+    pub code: u16,
+    pub direction: Direction,
+    pub reliability: Reliability,
+    pub payload: RootSchema,
+}
+
+impl ProtocolSchema {
+    pub fn generate() -> Self {
+        let opcodes = OpCode::ALL
+            .into_iter()
+            .filter_map(|opcode| {
+                payload_schema(opcode).map(|payload| OpCodeSchema {
+                    name: format!("{:?}", opcode),
+                    code: opcode.code(),
+                    direction: opcode.direction(),
+                    reliability: opcode.reliability(),
+                    payload,
+                })
+            })
+            .collect();
+
+        ProtocolSchema {
+            protocol_version: PROTOCOL_VERSION,
+            envelope: schema_for!(Packet),
+            opcodes,
+        }
+    }
+}
+
+// kept exhaustive on purpose, adding an opcode must not compile until its payload is listed here
+fn payload_schema(opcode: OpCode) -> Option<RootSchema> {
+    match opcode {
+        OpCode::Unset => None,
+        OpCode::Move => Some(schema_for!(MovePacket)),
+        OpCode::Spawn => Some(schema_for!(SpawnPacket)),
+        OpCode::Enter => Some(schema_for!(EnterPacket)),
+        OpCode::Enown => Some(schema_for!(EnownPacket)),
+        OpCode::Error => Some(schema_for!(ErrorPacket)),
+        OpCode::Replicate | OpCode::ReplicateUnreliable => Some(schema_for!(ReplicatePacket)),
+        OpCode::Snapshot => Some(schema_for!(SnapshotPacket)),
+        OpCode::SnapshotAck => Some(schema_for!(SnapshotAckPacket)),
+        OpCode::Despawn => Some(schema_for!(DespawnPacket)),
+        OpCode::Control => Some(schema_for!(ControlPacket)),
+        OpCode::Register | OpCode::Login => Some(schema_for!(CredentialsPacket)),
+        OpCode::Authenticated => Some(schema_for!(AuthenticatedPacket)),
+        OpCode::ListCharacters => Some(schema_for!(ListCharactersPacket)),
+        OpCode::CreateCharacter => Some(schema_for!(CreateCharacterPacket)),
+        OpCode::DeleteCharacter => Some(schema_for!(DeleteCharacterPacket)),
+        OpCode::CharacterList => Some(schema_for!(CharacterListPacket)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lists_every_opcode_with_a_payload() {
+        let schema = ProtocolSchema::generate();
+
+        let codes: Vec<u16> = schema.opcodes.iter().map(|opcode| opcode.code).collect();
+
+        for opcode in OpCode::ALL
+            .into_iter()
+            .filter(|opcode| *opcode != OpCode::Unset)
+        {
+            assert!(codes.contains(&opcode.code()), "{:?} is missing", opcode);
+        }
+        assert!(!codes.contains(&OpCode::Unset.code()));
+    }
+
+    #[test]
+    fn test_payload_schema_describes_fields() {
+        let schema = serde_json::to_value(ProtocolSchema::generate()).unwrap();
+
+        let snapshot = schema["opcodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|opcode| opcode["name"] == "Snapshot")
+            .unwrap();
+
+        assert_eq!(snapshot["direction"], "ServerToClient");
+        assert_eq!(snapshot["reliability"], "Unreliable");
+        assert!(snapshot["payload"]["properties"]["baseline"].is_object());
+        assert!(snapshot["payload"]["properties"]["moved"].is_object());
+    }
+}
@@ -0,0 +1,8 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// the connection is logged in as `account` and may enter the world
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct AuthenticatedPacket {
+    pub account: String,
+}
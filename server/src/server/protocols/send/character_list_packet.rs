@@ -0,0 +1,33 @@
+use common::model::{Appearance, Character, CharacterClass};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct CharacterSummary {
+    // picked in the enter packet
+    pub id: String,
+    pub name: String,
+    pub class: CharacterClass,
+    pub appearance: Appearance,
+    pub level: u32,
+    pub zone: String,
+}
+
+impl From<&Character> for CharacterSummary {
+    fn from(character: &Character) -> Self {
+        CharacterSummary {
+            id: character.uuid.clone(),
+            name: character.name.clone(),
+            class: character.class,
+            appearance: character.appearance.clone(),
+            level: character.level,
+            zone: character.zone.clone(),
+        }
+    }
+}
+
+// the account's characters, sent after listing, creating or deleting one
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct CharacterListPacket {
+    pub characters: Vec<CharacterSummary>,
+}
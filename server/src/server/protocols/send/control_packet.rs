@@ -0,0 +1,11 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::server::components::shared::net_id::NetId;
+
+// the receiving client gained or lost control of `id`, see `ControlCommand`
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ControlPacket {
+    pub id: NetId,
+    pub controlled: bool,
+}
@@ -0,0 +1,19 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::server::components::shared::net_id::NetId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum DespawnReason {
+    // the owning connection left
+    Disconnect,
+    Death,
+    // still exists, but no longer within the receiver's area of interest
+    OutOfRange,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct DespawnPacket {
+    pub id: NetId,
+    pub reason: DespawnReason,
+}
@@ -1,6 +1,12 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::server::{components::shared::net_id::NetId, connections::session_token::SessionToken};
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct EnownPacket {
-    pub id: String,
+    pub id: NetId,
+    // to be sent along with every later packet, see `Packet::session`
+    #[serde(default)]
+    pub session: Option<SessionToken>,
 }
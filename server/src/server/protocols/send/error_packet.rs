@@ -0,0 +1,34 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub enum ErrorCode {
+    MissingProtocolVersion,
+    IncompatibleProtocolVersion,
+    // the packet targeted an entity the connection does not control
+    Unauthorized,
+    // only logged in connections can enter the world
+    NotAuthenticated,
+    AccountExists,
+    InvalidAccountDetails,
+    InvalidCredentials,
+    AccountBanned,
+    LoginThrottled,
+    RegistrationThrottled,
+    AccountInUse,
+    // the account could not be read or written, worth retrying later
+    AccountUnavailable,
+    // entering needs a character picked from the character list
+    NoCharacterSelected,
+    CharacterNameTaken,
+    InvalidCharacterDetails,
+    CharacterNotFound,
+    CharacterLimitReached,
+    CharacterUnavailable,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ErrorPacket {
+    pub code: ErrorCode,
+    pub message: String,
+}
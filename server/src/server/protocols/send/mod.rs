@@ -1,3 +1,9 @@
+pub mod authenticated_packet;
+pub mod character_list_packet;
+pub mod control_packet;
+pub mod despawn_packet;
 pub mod enown_packet;
-pub mod moved_packet;
+pub mod error_packet;
+pub mod replicate_packet;
+pub mod snapshot_packet;
 pub mod spawn_packet;
@@ -0,0 +1,34 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::server::components::shared::net_id::NetId;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ComponentUpdate {
+    // `Replicated::NAME` of the component
+    pub name: String,
+    pub data: serde_json::Value,
+}
+
+// every changed replicated component of one entity, batched into a single packet
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ReplicatePacket {
+    pub id: NetId,
+    pub components: Vec<ComponentUpdate>,
+}
+
+impl ReplicatePacket {
+    // folds in a newer packet about the same entity, its components win over the ones carried here
+    pub fn merge(&mut self, newer: ReplicatePacket) {
+        for component in newer.components {
+            match self
+                .components
+                .iter_mut()
+                .find(|existing| existing.name == component.name)
+            {
+                Some(existing) => *existing = component,
+                None => self.components.push(component),
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::server::components::shared::{net_id::NetId, quantized_vec3::QuantizedVec3};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct EntityPosition {
+    pub id: NetId,
+    pub position: QuantizedVec3,
+}
+
+// positions are quantized, `moved` holds the offset from the baseline snapshot
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct SnapshotPacket {
+    pub sequence: u32,
+    // the acknowledged snapshot this one is relative to, none means a full snapshot
+    pub baseline: Option<u32>,
+    pub precision: f64,
+    pub added: Vec<EntityPosition>,
+    pub moved: Vec<EntityPosition>,
+    pub removed: Vec<NetId>,
+}
+
+impl SnapshotPacket {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.moved.is_empty() && self.removed.is_empty()
+    }
+}
@@ -1,8 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::server::components::shared::vec3d::Vec3d;
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::server::components::shared::{net_id::NetId, vec3d::Vec3d};
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct SpawnPacket {
-    pub id: String,
+    pub id: NetId,
     pub location: Vec3d,
 }
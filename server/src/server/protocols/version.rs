@@ -0,0 +1,83 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// the major goes up with every change an older client cannot handle
+// 2: snapshots replace Moved, opcodes renumbered, network ids are u32
+// 3: the envelope carries a session token, enown hands it out
+// 4: moves name the entity they target
+// 5: entering requires a login
+// 6: entering requires a selected character
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::new(6, 0);
+
+// (major, oldest minor of that major the server still accepts)
+const COMPATIBILITY_MATRIX: &[(u16, u16)] = &[(6, 0)];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct ProtocolVersion {
+    pub major: u16,
+    pub minor: u16,
+}
+
+impl ProtocolVersion {
+    pub const fn new(major: u16, minor: u16) -> Self {
+        ProtocolVersion { major, minor }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.is_compatible_with(PROTOCOL_VERSION, COMPATIBILITY_MATRIX)
+    }
+
+    // a client may lag behind on minor versions listed in the matrix, but never run ahead of the server
+    fn is_compatible_with(&self, server: ProtocolVersion, matrix: &[(u16, u16)]) -> bool {
+        if self.major == server.major && self.minor > server.minor {
+            return false;
+        }
+
+        matrix
+            .iter()
+            .any(|(major, oldest_minor)| self.major == *major && self.minor >= *oldest_minor)
+    }
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATRIX: &[(u16, u16)] = &[(1, 2), (2, 0)];
+    const SERVER: ProtocolVersion = ProtocolVersion::new(2, 1);
+
+    #[test]
+    fn test_current_version_is_supported() {
+        assert!(PROTOCOL_VERSION.is_supported());
+    }
+
+    #[test]
+    fn test_previous_majors_are_unsupported() {
+        for major in 1..PROTOCOL_VERSION.major {
+            assert!(!ProtocolVersion::new(major, 0).is_supported());
+        }
+    }
+
+    #[test]
+    fn test_older_minor_is_compatible() {
+        assert!(ProtocolVersion::new(2, 0).is_compatible_with(SERVER, MATRIX));
+        assert!(ProtocolVersion::new(1, 2).is_compatible_with(SERVER, MATRIX));
+    }
+
+    #[test]
+    fn test_newer_minor_is_incompatible() {
+        assert!(!ProtocolVersion::new(2, 2).is_compatible_with(SERVER, MATRIX));
+    }
+
+    #[test]
+    fn test_versions_outside_matrix_are_incompatible() {
+        assert!(!ProtocolVersion::new(1, 1).is_compatible_with(SERVER, MATRIX));
+        assert!(!ProtocolVersion::new(3, 0).is_compatible_with(SERVER, MATRIX));
+    }
+}
@@ -0,0 +1,438 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::{
+    resource::Resource,
+    world::{Mut, World},
+};
+use log::{debug, trace};
+
+use crate::server::{
+    commands::StateMappedCommand,
+    components::{
+        networked::Networked,
+        position::Position,
+        shared::{net_id::NetId, quantized_vec3::QuantizedVec3},
+    },
+    opcode::OpCode,
+    packet_sender::{
+        packet_sender::{PacketSender, ServerPacketSender},
+        send_packet::SendPacket,
+        TargetAddress,
+    },
+    protocols::send::snapshot_packet::SnapshotPacket,
+    replication::{
+        dead_reckoning::DeadReckoning,
+        interest::Interest,
+        rate_tiers::{is_due, RateTier, DISTANCE_TIERS},
+        snapshot::{SnapshotHistory, WorldSnapshot},
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotConfig {
+    // world units per quantization step
+    pub precision: f64,
+    // unacknowledged snapshots kept per connection before falling back to a full one
+    pub history_len: usize,
+    // positions of distant entities are refreshed less often, see `RateTier`
+    pub tiers: &'static [RateTier],
+    // a client that is up to date still gets an empty snapshot this often, acking it is what
+    // keeps an idle connection from timing out
+    pub keepalive_ticks: u32,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        SnapshotConfig {
+            precision: 0.01,
+            history_len: 32,
+            tiers: DISTANCE_TIERS,
+            keepalive_ticks: 40,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ClientSnapshots {
+    config: SnapshotConfig,
+    next_sequence: u32,
+    clients: HashMap<SocketAddr, SnapshotHistory>,
+    // when each recent snapshot went out, an ack for it measures the round trip
+    sent_at: BTreeMap<u32, Instant>,
+}
+
+impl ClientSnapshots {
+    pub fn new(config: SnapshotConfig) -> Self {
+        ClientSnapshots {
+            config,
+            next_sequence: 0,
+            clients: HashMap::new(),
+            sent_at: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> SnapshotConfig {
+        self.config
+    }
+
+    // returns the round trip time when this is the first ack of a snapshot still in flight
+    pub fn acknowledge(&mut self, addr: SocketAddr, sequence: u32) -> Option<Duration> {
+        let history = self.clients.get_mut(&addr)?;
+
+        let fresh = history.get(sequence).is_some()
+            && history
+                .baseline()
+                .is_none_or(|baseline| baseline.sequence < sequence);
+
+        history.acknowledge(sequence);
+
+        if !fresh {
+            return None;
+        }
+
+        self.sent_at.get(&sequence).map(|sent_at| sent_at.elapsed())
+    }
+
+    // records what each connection can see of `positions` and returns what each of them is missing
+    pub fn capture(
+        &mut self,
+        positions: &BTreeMap<NetId, QuantizedVec3>,
+        interest: &Interest,
+        connections: &HashSet<SocketAddr>,
+    ) -> Vec<(SocketAddr, SnapshotPacket)> {
+        self.clients.retain(|addr, _| connections.contains(addr));
+
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        let mut packets = vec![];
+        // views built this tick, clients seeing the same thing share one
+        let mut views: HashSet<Arc<BTreeMap<NetId, QuantizedVec3>>> = HashSet::new();
+
+        for addr in connections {
+            let history = self
+                .clients
+                .entry(*addr)
+                .or_insert_with(|| SnapshotHistory::new(self.config.history_len));
+
+            let visible: BTreeMap<_, _> = interest
+                .entities(*addr)
+                .into_iter()
+                .flatten()
+                .filter_map(|id| {
+                    let position = positions.get(id)?;
+                    let distance = interest.distance(*addr, *id)?;
+
+                    // an entity whose tier is not up yet keeps the position it was last sent with
+                    let held = history
+                        .latest()
+                        .and_then(|latest| latest.positions.get(id))
+                        .filter(|_| {
+                            !is_due(
+                                self.config.tiers,
+                                sequence as u64,
+                                *id,
+                                distance,
+                                interest.radius(),
+                            )
+                        });
+
+                    Some((*id, *held.unwrap_or(position)))
+                })
+                .collect();
+
+            // an unchanged view keeps pointing at the map the last snapshot already holds
+            let visible = match history.latest() {
+                Some(latest) if *latest.positions == visible => latest.positions.clone(),
+                _ => match views.get(&visible) {
+                    Some(view) => view.clone(),
+                    None => {
+                        let view = Arc::new(visible);
+                        views.insert(view.clone());
+                        view
+                    }
+                },
+            };
+
+            let snapshot = WorldSnapshot::shared(sequence, self.config.precision, visible);
+
+            let packet = snapshot.diff(history.baseline());
+
+            let idle = history.latest().is_none_or(|latest| {
+                sequence.wrapping_sub(latest.sequence) >= self.config.keepalive_ticks
+            });
+
+            // either nothing is visible yet or the client is already up to date
+            if packet.is_empty() && !idle {
+                continue;
+            }
+
+            history.push(snapshot);
+            packets.push((*addr, packet));
+        }
+
+        // nothing older than a full history can still be acknowledged
+        self.sent_at = self
+            .sent_at
+            .split_off(&sequence.saturating_sub(self.config.history_len as u32));
+
+        if !packets.is_empty() {
+            self.sent_at.insert(sequence, Instant::now());
+        }
+
+        packets
+    }
+}
+
+impl StateMappedCommand for ClientSnapshots {
+    fn map(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
+        let mut world = world.write().expect("Failed to get write lock to world");
+        let sender = sender.lock().expect("Failed to lock sender");
+
+        let precision = world.resource::<ClientSnapshots>().config.precision;
+
+        // moving entities publish their dead reckoning anchor, clients extrapolate the rest
+        let positions = world
+            .query::<(&Networked, &Position, Option<&DeadReckoning>)>()
+            .iter(&world)
+            .map(|(networked, position, reckoning)| {
+                let position = reckoning.map_or(&position.position, |r| &r.anchor);
+
+                (networked.id, QuantizedVec3::quantize(position, precision))
+            })
+            .collect();
+
+        let connections = sender.connections();
+
+        let packets = world.resource_scope(|world, mut snapshots: Mut<ClientSnapshots>| {
+            snapshots.capture(&positions, world.resource::<Interest>(), &connections)
+        });
+
+        debug!("Enqueuing {:?} snapshot packets", packets.len());
+
+        for (addr, packet) in packets {
+            trace!("Enqueuing snapshot for {:?}: {:?}", addr, packet);
+
+            sender.enqueue(SendPacket::new(
+                serde_json::to_string(&packet).expect("Failed to serialize SnapshotPacket"),
+                OpCode::Snapshot,
+                TargetAddress::Targeted(vec![addr]),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{
+        connections::connection_manager::{ConnectionConfig, ConnectionManager},
+        replication::interest::InterestConfig,
+    };
+
+    use super::*;
+
+    fn positions(entries: &[(u32, i32)]) -> BTreeMap<NetId, QuantizedVec3> {
+        entries
+            .iter()
+            .map(|(index, x)| (NetId::new(*index, 0), QuantizedVec3::new(*x, 0, 0)))
+            .collect()
+    }
+
+    fn interest(addr: SocketAddr, indices: &[u32]) -> Interest {
+        let mut interest = Interest::new(InterestConfig::default());
+        interest.update(
+            addr,
+            indices
+                .iter()
+                .map(|index| (NetId::new(*index, 0), 0.0))
+                .collect(),
+        );
+        interest
+    }
+
+    #[test]
+    fn test_sends_deltas_against_acknowledged_snapshot() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let connections = HashSet::from([addr]);
+        let interest = interest(addr, &[0, 1]);
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let first = snapshots.capture(&positions(&[(0, 10), (1, 20)]), &interest, &connections);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].1.added.len(), 2);
+
+        // not acknowledged yet, so the next one is still full
+        let second = snapshots.capture(&positions(&[(0, 10), (1, 20)]), &interest, &connections);
+        assert_eq!(second[0].1.baseline, None);
+
+        snapshots.acknowledge(addr, second[0].1.sequence);
+
+        assert!(snapshots
+            .capture(&positions(&[(0, 10), (1, 20)]), &interest, &connections)
+            .is_empty());
+
+        let moved = snapshots.capture(&positions(&[(0, 10), (1, 25)]), &interest, &connections);
+        assert_eq!(moved[0].1.baseline, Some(second[0].1.sequence));
+        assert!(moved[0].1.added.is_empty());
+        assert_eq!(moved[0].1.moved[0].position, QuantizedVec3::new(5, 0, 0));
+    }
+
+    #[test]
+    fn test_first_ack_measures_round_trip() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let connections = HashSet::from([addr]);
+        let interest = interest(addr, &[0]);
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let sent = snapshots.capture(&positions(&[(0, 10)]), &interest, &connections);
+        let sequence = sent[0].1.sequence;
+
+        assert!(snapshots.acknowledge(addr, sequence).is_some());
+        assert!(snapshots.acknowledge(addr, sequence).is_none());
+        assert!(snapshots.acknowledge(addr, sequence + 1).is_none());
+    }
+
+    #[test]
+    fn test_only_interesting_entities_are_sent() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let sent = snapshots.capture(
+            &positions(&[(0, 10), (1, 20)]),
+            &interest(addr, &[1]),
+            &HashSet::from([addr]),
+        );
+
+        assert_eq!(sent[0].1.added.len(), 1);
+        assert_eq!(sent[0].1.added[0].id, NetId::new(1, 0));
+
+        assert!(snapshots
+            .capture(
+                &positions(&[(0, 10)]),
+                &interest(addr, &[]),
+                &HashSet::from([addr])
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_distant_entities_move_at_their_tier_rate() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let connections = HashSet::from([addr]);
+        let mut interest = Interest::new(InterestConfig::default());
+        interest.update(addr, [(NetId::new(0, 0), 90.0)].into());
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let first = snapshots.capture(&positions(&[(0, 10)]), &interest, &connections);
+        snapshots.acknowledge(addr, first[0].1.sequence);
+
+        // quarter rate, the move only goes out on the fourth snapshot after the first
+        for _ in 0..3 {
+            assert!(snapshots
+                .capture(&positions(&[(0, 30)]), &interest, &connections)
+                .is_empty());
+        }
+
+        let moved = snapshots.capture(&positions(&[(0, 30)]), &interest, &connections);
+        assert_eq!(moved[0].1.moved[0].position, QuantizedVec3::new(20, 0, 0));
+    }
+
+    #[test]
+    fn test_identical_views_are_shared() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let other: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let connections = HashSet::from([addr, other]);
+        let mut interest = interest(addr, &[0, 1]);
+        interest.update(
+            other,
+            [(NetId::new(0, 0), 0.0), (NetId::new(1, 0), 0.0)].into(),
+        );
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let view = |snapshots: &ClientSnapshots, addr: SocketAddr| {
+            snapshots.clients[&addr].latest().unwrap().positions.clone()
+        };
+
+        snapshots.capture(
+            &positions(&[(0, 10), (1, 20), (2, 30)]),
+            &interest,
+            &connections,
+        );
+        let first = view(&snapshots, addr);
+
+        assert!(Arc::ptr_eq(&first, &view(&snapshots, other)));
+        assert_eq!(first.len(), 2);
+
+        // unacknowledged and unchanged, the next full snapshot holds the same map
+        snapshots.capture(
+            &positions(&[(0, 10), (1, 20), (2, 35)]),
+            &interest,
+            &connections,
+        );
+
+        assert!(Arc::ptr_eq(&first, &view(&snapshots, addr)));
+        assert!(Arc::ptr_eq(&first, &view(&snapshots, other)));
+    }
+
+    #[test]
+    fn test_idle_clients_acking_keepalives_are_not_timed_out() {
+        let idle: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let silent: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let connections = HashSet::from([idle, silent]);
+        let interest = Interest::new(InterestConfig::default());
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig {
+            keepalive_ticks: 2,
+            ..SnapshotConfig::default()
+        });
+        let mut manager = ConnectionManager::new(ConnectionConfig {
+            timeout: Duration::from_millis(50),
+            ..ConnectionConfig::default()
+        });
+
+        for (id, addr) in connections.iter().enumerate() {
+            manager.receive(*addr, id as u128, 0, None).unwrap();
+        }
+
+        // nothing to see, yet every other tick carries an empty snapshot the idle client acks
+        for tick in 0..10 {
+            for (addr, packet) in snapshots.capture(&BTreeMap::new(), &interest, &connections) {
+                assert!(packet.is_empty());
+
+                if addr == idle {
+                    manager.receive(idle, 2 + tick, 0, None).unwrap();
+                    snapshots.acknowledge(idle, packet.sequence);
+                }
+            }
+
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let expired: Vec<_> = manager
+            .expire(Instant::now())
+            .into_iter()
+            .map(|connection| connection.addr)
+            .collect();
+
+        assert_eq!(expired, vec![silent]);
+    }
+
+    #[test]
+    fn test_forgets_disconnected_clients() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let interest = interest(addr, &[0]);
+        let mut snapshots = ClientSnapshots::new(SnapshotConfig::default());
+
+        let sent = snapshots.capture(&positions(&[(0, 10)]), &interest, &HashSet::from([addr]));
+        snapshots.acknowledge(addr, sent[0].1.sequence);
+
+        snapshots.capture(&positions(&[(0, 10)]), &interest, &HashSet::new());
+        let resent = snapshots.capture(&positions(&[(0, 10)]), &interest, &HashSet::from([addr]));
+
+        assert_eq!(resent[0].1.baseline, None);
+    }
+}
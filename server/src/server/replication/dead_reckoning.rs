@@ -0,0 +1,168 @@
+use std::time::Instant;
+
+use bevy_ecs::{
+    change_detection::DetectChanges,
+    component::Component,
+    entity::Entity,
+    resource::Resource,
+    system::{Commands, Query, Res},
+    world::Ref,
+};
+
+use crate::server::components::{
+    movement_state::MovementState, position::Position, shared::vec3d::Vec3d,
+};
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct DeadReckoningConfig {
+    // how far the server position may drift from the client's extrapolation before a correction
+    pub tolerance: f64,
+    // ticks between forced corrections, so rounding errors cannot accumulate forever
+    pub keyframe_ticks: u32,
+}
+
+impl Default for DeadReckoningConfig {
+    fn default() -> Self {
+        DeadReckoningConfig {
+            tolerance: 0.5,
+            keyframe_ticks: 40,
+        }
+    }
+}
+
+// the last position clients were told about, snapshots carry this instead of `Position`
+#[derive(Component, Debug, Clone)]
+pub struct DeadReckoning {
+    pub anchor: Vec3d,
+    anchored_at: Instant,
+    ticks_since_anchor: u32,
+}
+
+impl DeadReckoning {
+    pub fn new(anchor: Vec3d) -> Self {
+        DeadReckoning {
+            anchor,
+            anchored_at: Instant::now(),
+            ticks_since_anchor: 0,
+        }
+    }
+
+    // where a client extrapolating from the anchor believes the entity is
+    pub fn predicted(&self, movement_state: &MovementState) -> Vec3d {
+        movement_state.extrapolate(&self.anchor, self.anchored_at.elapsed().as_secs_f64())
+    }
+}
+
+pub fn dead_reckoning_system(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &Position,
+        Ref<MovementState>,
+        Option<&mut DeadReckoning>,
+    )>,
+    config: Res<DeadReckoningConfig>,
+) {
+    for (entity, position, movement_state, reckoning) in query.iter_mut() {
+        let Some(mut reckoning) = reckoning else {
+            commands
+                .entity(entity)
+                .insert(DeadReckoning::new(position.position.clone()));
+            continue;
+        };
+
+        reckoning.ticks_since_anchor += 1;
+
+        // a new movement state restarts the client's extrapolation, so it needs a fresh anchor
+        let diverged = movement_state.is_changed()
+            || reckoning.ticks_since_anchor >= config.keyframe_ticks
+            || reckoning
+                .predicted(&movement_state)
+                .distance(&position.position)
+                > config.tolerance;
+
+        if diverged {
+            *reckoning = DeadReckoning::new(position.position.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use crate::server::components::movement_state::MovementStateType;
+
+    use super::*;
+
+    fn setup(keyframe_ticks: u32) -> (World, Schedule, Entity) {
+        let mut world = World::default();
+        world.insert_resource(DeadReckoningConfig {
+            tolerance: 0.5,
+            keyframe_ticks,
+        });
+
+        let entity = world
+            .spawn((
+                Position {
+                    position: Vec3d::zero(),
+                },
+                MovementState {
+                    current_state: MovementStateType::Stopped,
+                    velocity: 1.0,
+                    direction: Vec3d::zero(),
+                },
+            ))
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(dead_reckoning_system);
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        (world, schedule, entity)
+    }
+
+    fn anchor(world: &World, entity: Entity) -> Vec3d {
+        world.get::<DeadReckoning>(entity).unwrap().anchor.clone()
+    }
+
+    #[test]
+    fn test_small_drift_keeps_the_anchor() {
+        let (mut world, mut schedule, entity) = setup(100);
+
+        world.get_mut::<Position>(entity).unwrap().position.x = 0.4;
+        schedule.run(&mut world);
+        assert_eq!(anchor(&world, entity).x, 0.0);
+
+        world.get_mut::<Position>(entity).unwrap().position.x = 0.6;
+        schedule.run(&mut world);
+        assert_eq!(anchor(&world, entity).x, 0.6);
+    }
+
+    #[test]
+    fn test_movement_change_reanchors() {
+        let (mut world, mut schedule, entity) = setup(100);
+
+        world.get_mut::<Position>(entity).unwrap().position.x = 0.1;
+        world
+            .get_mut::<MovementState>(entity)
+            .unwrap()
+            .current_state = MovementStateType::Moving;
+        schedule.run(&mut world);
+
+        assert_eq!(anchor(&world, entity).x, 0.1);
+    }
+
+    #[test]
+    fn test_keyframe_interval_reanchors() {
+        let (mut world, mut schedule, entity) = setup(3);
+
+        world.get_mut::<Position>(entity).unwrap().position.x = 0.1;
+        schedule.run(&mut world);
+        assert_eq!(anchor(&world, entity).x, 0.0);
+
+        schedule.run(&mut world);
+        assert_eq!(anchor(&world, entity).x, 0.1);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    net::SocketAddr,
+};
+
+use bevy_ecs::{
+    resource::Resource,
+    system::{Query, Res, ResMut},
+    world::World,
+};
+
+use crate::server::{
+    commands::{
+        enter_interest_command::EnterInterestCommand, leave_interest_command::LeaveInterestCommand,
+    },
+    components::{networked::Networked, position::Position, shared::net_id::NetId},
+    connections::connection_manager::Connections,
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::{
+        networked_index::NetworkedIndex, spatial_grid::Proximity,
+        untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct InterestConfig {
+    // entities within this distance of a connection's player character are sent to it
+    pub radius: f64,
+}
+
+impl Default for InterestConfig {
+    fn default() -> Self {
+        InterestConfig { radius: 100.0 }
+    }
+}
+
+// which entities each connection currently knows about, and how far they are from its character
+#[derive(Resource)]
+pub struct Interest {
+    config: InterestConfig,
+    subscriptions: HashMap<SocketAddr, BTreeMap<NetId, f64>>,
+}
+
+impl Interest {
+    pub fn new(config: InterestConfig) -> Self {
+        Interest {
+            config,
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.config.radius
+    }
+
+    pub fn entities(&self, addr: SocketAddr) -> Option<impl Iterator<Item = &NetId>> {
+        self.subscriptions
+            .get(&addr)
+            .map(|entities| entities.keys())
+    }
+
+    pub fn is_interested(&self, addr: SocketAddr, id: NetId) -> bool {
+        self.distance(addr, id).is_some()
+    }
+
+    // as of the last interest update, `None` when `addr` is not interested in `id`
+    pub fn distance(&self, addr: SocketAddr, id: NetId) -> Option<f64> {
+        self.subscriptions
+            .get(&addr)
+            .and_then(|entities| entities.get(&id))
+            .copied()
+    }
+
+    pub fn subscribers(&self, id: NetId) -> Vec<SocketAddr> {
+        self.subscriptions
+            .iter()
+            .filter(|(_, entities)| entities.contains_key(&id))
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    // replaces what `addr` is subscribed to, returns the entities that entered and left
+    pub(crate) fn update(
+        &mut self,
+        addr: SocketAddr,
+        entities: BTreeMap<NetId, f64>,
+    ) -> (Vec<NetId>, Vec<NetId>) {
+        let previous = self.subscriptions.entry(addr).or_default();
+
+        let entered = entities
+            .keys()
+            .filter(|id| !previous.contains_key(id))
+            .copied()
+            .collect();
+        let left = previous
+            .keys()
+            .filter(|id| !entities.contains_key(id))
+            .copied()
+            .collect();
+
+        *previous = entities;
+
+        (entered, left)
+    }
+
+    // drops `id` from every subscription, returns who was subscribed to it
+    pub(crate) fn forget(&mut self, id: NetId) -> Vec<SocketAddr> {
+        self.subscriptions
+            .iter_mut()
+            .filter_map(|(addr, entities)| entities.remove(&id).map(|_| *addr))
+            .collect()
+    }
+
+    pub(crate) fn retain(&mut self, addrs: &HashSet<SocketAddr>) {
+        self.subscriptions.retain(|addr, _| addrs.contains(addr));
+    }
+}
+
+// 1.0 right next to the player's character of `addr`, 0.5 at the edge of the interest radius
+pub fn relevance(world: &World, addr: SocketAddr, id: NetId) -> f32 {
+    let interest = world.resource::<Interest>();
+
+    interest.distance(addr, id).map_or(1.0, |distance| {
+        (interest.radius() / (interest.radius() + distance)) as f32
+    })
+}
+
+pub fn interest_system(
+    connections: Res<Connections>,
+    networked_index: Res<NetworkedIndex>,
+    proximity: Proximity,
+    query: Query<(&Networked, &Position)>,
+    mut interest: ResMut<Interest>,
+    mut entered_commands: ResMut<UntargetedCommandContainer<EnterInterestCommand>>,
+    mut left_commands: ResMut<UntargetedCommandContainer<LeaveInterestCommand>>,
+) {
+    let players = connections
+        .0
+        .read()
+        .expect("Failed to get read lock on connection manager")
+        .player_characters();
+
+    interest.retain(&players.iter().map(|(addr, _)| *addr).collect());
+
+    let radius = interest.radius();
+
+    for (addr, character) in players {
+        // the character is spawned a tick after it is authorized
+        let Some(center) = networked_index
+            .get(character)
+            .and_then(|entity| query.get(entity).ok())
+            .map(|(_, position)| position.position.clone())
+        else {
+            continue;
+        };
+
+        let visible = proximity
+            .within_radius(&center, radius)
+            .into_iter()
+            .filter_map(|entity| query.get(entity).ok())
+            .map(|(networked, position)| (networked.id, position.position.distance(&center)))
+            .collect();
+
+        let (entered, left) = interest.update(addr, visible);
+
+        for id in entered {
+            entered_commands
+                .entries
+                .push_back(EnterInterestCommand::new(id, addr));
+        }
+
+        for id in left {
+            left_commands
+                .entries
+                .push_back(LeaveInterestCommand::new(id, addr));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use bevy_ecs::{
+        schedule::{IntoScheduleConfigs, Schedule},
+        world::World,
+    };
+
+    use crate::server::{
+        components::shared::vec3d::Vec3d,
+        connections::connection_manager::ConnectionManager,
+        systems::{
+            net_id_allocator::NetIdAllocator,
+            spatial_grid::{update_spatial_grid, SpatialGrid},
+        },
+    };
+
+    use super::*;
+
+    fn spawn(world: &mut World, x: f64) -> (NetId, bevy_ecs::entity::Entity) {
+        let id = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        let entity = world
+            .spawn((
+                Networked { id },
+                Position {
+                    position: Vec3d::new(x, 0.0, 0.0),
+                },
+            ))
+            .id();
+
+        (id, entity)
+    }
+
+    fn setup(addr: SocketAddr) -> (World, Schedule, NetId, NetId, bevy_ecs::entity::Entity) {
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(Interest::new(InterestConfig { radius: 10.0 }));
+        world.insert_resource(SpatialGrid::new(10.0));
+        world.insert_resource(UntargetedCommandContainer::<EnterInterestCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<LeaveInterestCommand> {
+            entries: Default::default(),
+        });
+
+        let (player, _) = spawn(&mut world, 0.0);
+        let (other, other_entity) = spawn(&mut world, 5.0);
+
+        let mut connections = ConnectionManager::default();
+        connections.add_entity(addr, player);
+        world.insert_resource(Connections(Arc::new(RwLock::new(connections))));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            update_spatial_grid,
+            interest_system.after(update_spatial_grid),
+        ));
+
+        (world, schedule, player, other, other_entity)
+    }
+
+    #[test]
+    fn test_entities_in_radius_enter_interest() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let (mut world, mut schedule, player, other, _) = setup(addr);
+
+        schedule.run(&mut world);
+
+        let interest = world.resource::<Interest>();
+        assert!(interest.is_interested(addr, player));
+        assert!(interest.is_interested(addr, other));
+        assert_eq!(
+            world
+                .resource::<UntargetedCommandContainer<EnterInterestCommand>>()
+                .entries
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_entities_leaving_radius_leave_interest() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let (mut world, mut schedule, _, other, other_entity) = setup(addr);
+
+        schedule.run(&mut world);
+        world.get_mut::<Position>(other_entity).unwrap().position.x = 50.0;
+        schedule.run(&mut world);
+
+        assert!(!world.resource::<Interest>().is_interested(addr, other));
+        assert!(world.resource::<Interest>().subscribers(other).is_empty());
+
+        let left = &world
+            .resource::<UntargetedCommandContainer<LeaveInterestCommand>>()
+            .entries;
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].id, other);
+    }
+}
@@ -0,0 +1,8 @@
+pub mod client_snapshots;
+pub mod dead_reckoning;
+pub mod interest;
+pub mod rate_tiers;
+pub mod registry;
+pub mod replicate_system;
+pub mod replicated;
+pub mod snapshot;
@@ -0,0 +1,71 @@
+use crate::server::components::shared::net_id::NetId;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateTier {
+    // upper bound of the tier, as a fraction of the interest radius
+    pub within: f64,
+    // entities in this tier are sent every `interval` ticks
+    pub interval: u32,
+}
+
+// every tick regardless of distance
+pub const FULL_RATE: &[RateTier] = &[];
+
+// full rate up close, half rate further out, quarter rate towards the edge of interest
+pub const DISTANCE_TIERS: &[RateTier] = &[
+    RateTier {
+        within: 0.25,
+        interval: 1,
+    },
+    RateTier {
+        within: 0.5,
+        interval: 2,
+    },
+    RateTier {
+        within: 1.0,
+        interval: 4,
+    },
+];
+
+// anything past the last tier is sent at the last tier's rate
+pub fn interval(tiers: &[RateTier], distance: f64, radius: f64) -> u32 {
+    tiers
+        .iter()
+        .find(|tier| distance <= tier.within * radius)
+        .or(tiers.last())
+        .map_or(1, |tier| tier.interval.max(1))
+}
+
+// offsets each entity by its index, so a tier's entities are spread evenly over its ticks
+pub fn is_due(tiers: &[RateTier], tick: u64, id: NetId, distance: f64, radius: f64) -> bool {
+    (tick + id.index() as u64).is_multiple_of(interval(tiers, distance, radius) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_by_distance() {
+        assert_eq!(interval(DISTANCE_TIERS, 10.0, 100.0), 1);
+        assert_eq!(interval(DISTANCE_TIERS, 40.0, 100.0), 2);
+        assert_eq!(interval(DISTANCE_TIERS, 90.0, 100.0), 4);
+        assert_eq!(interval(DISTANCE_TIERS, 500.0, 100.0), 4);
+        assert_eq!(interval(FULL_RATE, 500.0, 100.0), 1);
+    }
+
+    #[test]
+    fn test_entities_are_staggered_across_ticks() {
+        let due_per_tick: Vec<usize> = (0..4)
+            .map(|tick| {
+                (0..100)
+                    .filter(|index| {
+                        is_due(DISTANCE_TIERS, tick, NetId::new(*index, 0), 90.0, 100.0)
+                    })
+                    .count()
+            })
+            .collect();
+
+        assert_eq!(due_per_tick, vec![25, 25, 25, 25]);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{entity::Entity, resource::Resource, world::World};
+
+use crate::server::{
+    error::Error, protocols::send::replicate_packet::ReplicatePacket,
+    replication::replicated::Replicated, systems::networked_index::NetworkedIndex,
+};
+
+type Applier = fn(&mut World, Entity, serde_json::Value) -> Result<(), serde_json::Error>;
+
+// receiving side of replication, knows how to turn a `ComponentUpdate` back into its component
+#[derive(Resource, Default)]
+pub struct ReplicationRegistry {
+    appliers: HashMap<&'static str, Applier>,
+}
+
+impl ReplicationRegistry {
+    pub fn new() -> Self {
+        ReplicationRegistry {
+            appliers: HashMap::new(),
+        }
+    }
+
+    pub fn register<T: Replicated>(&mut self) {
+        self.appliers.insert(T::NAME, apply_component::<T>);
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.appliers.contains_key(name)
+    }
+
+    pub fn apply(&self, world: &mut World, packet: &ReplicatePacket) -> Result<(), Error> {
+        let entity = world.resource::<NetworkedIndex>().entity(packet.id)?;
+
+        for update in &packet.components {
+            let applier = self
+                .appliers
+                .get(update.name.as_str())
+                .ok_or_else(|| Error::UnknownReplicatedComponent(update.name.clone()))?;
+
+            applier(world, entity, update.data.clone())
+                .map_err(|e| Error::InvalidReplicatedComponent(update.name.clone(), e))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn apply_component<T: Replicated>(
+    world: &mut World,
+    entity: Entity,
+    data: serde_json::Value,
+) -> Result<(), serde_json::Error> {
+    let component: T = serde_json::from_value(data)?;
+
+    world.entity_mut(entity).insert(component);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::component::Component;
+    use serde::{Deserialize, Serialize};
+
+    use crate::server::{
+        components::{networked::Networked, shared::net_id::NetId},
+        protocols::send::replicate_packet::ComponentUpdate,
+    };
+
+    use super::*;
+
+    #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Health(u32);
+
+    impl Replicated for Health {
+        const NAME: &'static str = "health";
+    }
+
+    fn packet(id: NetId, name: &str, data: serde_json::Value) -> ReplicatePacket {
+        ReplicatePacket {
+            id,
+            components: vec![ComponentUpdate {
+                name: name.to_string(),
+                data,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_applies_registered_component() {
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        let id = NetId::new(3, 1);
+        let entity = world.spawn(Networked { id }).id();
+
+        let mut registry = ReplicationRegistry::new();
+        registry.register::<Health>();
+
+        registry
+            .apply(&mut world, &packet(id, "health", serde_json::json!(42)))
+            .unwrap();
+
+        assert_eq!(world.get::<Health>(entity), Some(&Health(42)));
+    }
+
+    #[test]
+    fn test_rejects_unknown_component_and_entity() {
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        let id = NetId::new(0, 0);
+        world.spawn(Networked { id });
+
+        let registry = ReplicationRegistry::new();
+
+        assert!(matches!(
+            registry.apply(&mut world, &packet(id, "health", serde_json::json!(1))),
+            Err(Error::UnknownReplicatedComponent(_))
+        ));
+        assert!(matches!(
+            registry.apply(
+                &mut world,
+                &packet(NetId::new(9, 0), "health", serde_json::json!(1))
+            ),
+            Err(Error::EntityNotFound(_))
+        ));
+    }
+}
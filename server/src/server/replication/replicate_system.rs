@@ -0,0 +1,375 @@
+use std::{collections::BTreeSet, net::SocketAddr};
+
+use bevy_ecs::{
+    query::Changed,
+    schedule::{IntoScheduleConfigs, Schedule},
+    system::{Local, Query, Res, ResMut},
+};
+use log::warn;
+
+use crate::server::{
+    commands::{enter_interest_command::EnterInterestCommand, replicate_command::ReplicateCommand},
+    components::{networked::Networked, owner::Owner, shared::net_id::NetId},
+    opcode::Reliability,
+    packet_sender::TargetAddress,
+    protocols::send::replicate_packet::ComponentUpdate,
+    replication::{
+        interest::{interest_system, Interest},
+        rate_tiers::is_due,
+        replicated::{Replicated, Visibility},
+    },
+    systems::{
+        networked_index::NetworkedIndex, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+// adds the change detection system of `T` to the schedule, throttled to its update rate
+pub fn replicate<T: Replicated>(schedule: &mut Schedule) {
+    let interval_ticks = T::settings().interval_ticks.max(1);
+
+    schedule.add_systems((
+        replicate_changed::<T>
+            .run_if(every_n_ticks(interval_ticks))
+            .after(interest_system),
+        replicate_entered::<T>.after(interest_system),
+    ));
+}
+
+// skipped runs keep their change ticks, so nothing changed in between is lost
+fn every_n_ticks(interval_ticks: u32) -> impl FnMut(Local<u32>) -> bool {
+    move |mut elapsed: Local<u32>| {
+        *elapsed += 1;
+
+        if *elapsed >= interval_ticks {
+            *elapsed = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// changes waiting for their entity's rate tier to come up for a connection
+#[derive(Default)]
+pub struct PendingReplication {
+    tick: u64,
+    changed: BTreeSet<(NetId, SocketAddr)>,
+}
+
+pub fn replicate_changed<T: Replicated>(
+    changed: Query<(&Networked, &T, Option<&Owner>), Changed<T>>,
+    components: Query<&T>,
+    networked_index: Res<NetworkedIndex>,
+    interest: Res<Interest>,
+    mut pending: Local<PendingReplication>,
+    mut replicate_commands: ResMut<UntargetedCommandContainer<ReplicateCommand>>,
+) {
+    let settings = T::settings();
+
+    pending.tick += 1;
+
+    for (networked, component, owner) in changed.iter() {
+        match (settings.visibility, owner) {
+            (Visibility::Interested, _) => {
+                for addr in interest.subscribers(networked.id) {
+                    pending.changed.insert((networked.id, addr));
+                }
+            }
+            (Visibility::Owner, Some(owner)) => {
+                if let Some(command) = command(
+                    networked.id,
+                    component,
+                    settings.reliability,
+                    TargetAddress::Targeted(vec![owner.addr]),
+                ) {
+                    replicate_commands.entries.push_back(command);
+                }
+            }
+            (Visibility::Owner, None) => continue,
+        }
+    }
+
+    let PendingReplication { tick, changed } = &mut *pending;
+
+    changed.retain(|(id, addr)| {
+        // out of range by now, the connection no longer tracks the entity
+        let Some(distance) = interest.distance(*addr, *id) else {
+            return false;
+        };
+
+        if !is_due(settings.tiers, *tick, *id, distance, interest.radius()) {
+            return true;
+        }
+
+        let component = networked_index
+            .get(*id)
+            .and_then(|entity| components.get(entity).ok());
+
+        if let Some(command) = component.and_then(|component| {
+            command(
+                *id,
+                component,
+                settings.reliability,
+                TargetAddress::Targeted(vec![*addr]),
+            )
+        }) {
+            replicate_commands.entries.push_back(command);
+        }
+
+        false
+    });
+}
+
+// a connection that just started tracking an entity gets its full state of `T` right after the spawn
+pub fn replicate_entered<T: Replicated>(
+    entered: Res<UntargetedCommandContainer<EnterInterestCommand>>,
+    query: Query<(&T, Option<&Owner>)>,
+    networked_index: Res<NetworkedIndex>,
+    mut replicate_commands: ResMut<UntargetedCommandContainer<ReplicateCommand>>,
+) {
+    let settings = T::settings();
+
+    for entered in entered.entries.iter() {
+        let Some((component, owner)) = networked_index
+            .get(entered.id)
+            .and_then(|entity| query.get(entity).ok())
+        else {
+            continue;
+        };
+
+        if settings.visibility == Visibility::Owner
+            && owner.map(|owner| owner.addr) != Some(entered.target)
+        {
+            continue;
+        }
+
+        // the initial state must arrive even for components that are otherwise sent unreliably
+        if let Some(command) = command(
+            entered.id,
+            component,
+            Reliability::Reliable,
+            TargetAddress::Targeted(vec![entered.target]),
+        ) {
+            replicate_commands.entries.push_back(command);
+        }
+    }
+}
+
+fn command<T: Replicated>(
+    id: NetId,
+    component: &T,
+    reliability: Reliability,
+    target: TargetAddress,
+) -> Option<ReplicateCommand> {
+    let data = match serde_json::to_value(component) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!("Failed to serialize {} of {}: {}", T::NAME, id, e);
+            return None;
+        }
+    };
+
+    Some(ReplicateCommand::new(
+        id,
+        ComponentUpdate {
+            name: T::NAME.to_string(),
+            data,
+        },
+        reliability,
+        target,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use bevy_ecs::{component::Component, world::World};
+    use serde::{Deserialize, Serialize};
+
+    use crate::server::{
+        components::shared::net_id::NetId,
+        replication::{interest::InterestConfig, replicated::ReplicationSettings},
+        systems::{net_id_allocator::NetIdAllocator, networked_index::NetworkedIndex},
+    };
+
+    use super::*;
+
+    #[derive(Component, Serialize, Deserialize, Clone)]
+    struct Health(u32);
+
+    impl Replicated for Health {
+        const NAME: &'static str = "health";
+    }
+
+    #[derive(Component, Serialize, Deserialize, Clone)]
+    struct Secret(u32);
+
+    impl Replicated for Secret {
+        const NAME: &'static str = "secret";
+
+        fn settings() -> ReplicationSettings {
+            ReplicationSettings {
+                reliability: Reliability::Unreliable,
+                interval_ticks: 2,
+                visibility: Visibility::Owner,
+                ..ReplicationSettings::default()
+            }
+        }
+    }
+
+    fn setup() -> (World, Schedule) {
+        let mut world = World::default();
+        let mut interest = Interest::new(InterestConfig::default());
+        interest.update(watcher(), [(NetId::new(0, 0), 0.0)].into());
+        world.insert_resource(interest);
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(UntargetedCommandContainer::<ReplicateCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<EnterInterestCommand> {
+            entries: Default::default(),
+        });
+
+        let mut schedule = Schedule::default();
+        replicate::<Health>(&mut schedule);
+        replicate::<Secret>(&mut schedule);
+
+        (world, schedule)
+    }
+
+    fn watcher() -> SocketAddr {
+        "127.0.0.1:9999".parse().unwrap()
+    }
+
+    fn drain(world: &mut World) -> Vec<ReplicateCommand> {
+        world
+            .resource_mut::<UntargetedCommandContainer<ReplicateCommand>>()
+            .entries
+            .drain(..)
+            .collect()
+    }
+
+    #[test]
+    fn test_only_changed_components_are_replicated() {
+        let (mut world, mut schedule) = setup();
+
+        let entity = world
+            .spawn((
+                Networked {
+                    id: NetId::new(0, 0),
+                },
+                Health(10),
+            ))
+            .id();
+        // nobody is interested in this one
+        world.spawn((
+            Networked {
+                id: NetId::new(1, 0),
+            },
+            Health(10),
+        ));
+
+        schedule.run(&mut world);
+        let commands = drain(&mut world);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].component.name, "health");
+        assert_eq!(commands[0].component.data, serde_json::json!(10));
+        assert_eq!(commands[0].target, TargetAddress::Targeted(vec![watcher()]));
+
+        schedule.run(&mut world);
+        assert!(drain(&mut world).is_empty());
+
+        world.get_mut::<Health>(entity).unwrap().0 = 5;
+        schedule.run(&mut world);
+        assert_eq!(drain(&mut world).len(), 1);
+    }
+
+    #[test]
+    fn test_distant_changes_wait_for_their_tier() {
+        let (mut world, mut schedule) = setup();
+        let id = NetId::new(2, 0);
+
+        // quarter rate at the edge of the default radius, due when (tick + 2) % 4 == 0
+        world
+            .resource_mut::<Interest>()
+            .update(watcher(), [(id, 90.0)].into());
+        let entity = world.spawn((Networked { id }, Health(10))).id();
+
+        schedule.run(&mut world);
+        assert!(drain(&mut world).is_empty());
+        schedule.run(&mut world);
+        assert_eq!(drain(&mut world).len(), 1);
+
+        world.get_mut::<Health>(entity).unwrap().0 = 5;
+        for _ in 0..3 {
+            schedule.run(&mut world);
+            assert!(drain(&mut world).is_empty());
+        }
+
+        schedule.run(&mut world);
+        let commands = drain(&mut world);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].component.data, serde_json::json!(5));
+    }
+
+    #[test]
+    fn test_entering_interest_sends_current_state_reliably() {
+        let (mut world, mut schedule) = setup();
+        let owner = "127.0.0.1:7777".parse().unwrap();
+        let id = NetId::new(3, 0);
+
+        world.spawn((
+            Networked { id },
+            Owner { addr: owner },
+            Health(7),
+            Secret(1),
+        ));
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        drain(&mut world);
+
+        world
+            .resource_mut::<UntargetedCommandContainer<EnterInterestCommand>>()
+            .entries
+            .push_back(EnterInterestCommand::new(id, watcher()));
+        schedule.run(&mut world);
+
+        // the secret stays with its owner
+        let commands = drain(&mut world);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].component.name, "health");
+        assert_eq!(commands[0].reliability, Reliability::Reliable);
+        assert_eq!(commands[0].target, TargetAddress::Targeted(vec![watcher()]));
+    }
+
+    #[test]
+    fn test_owner_visibility_and_interval() {
+        let (mut world, mut schedule) = setup();
+        let addr = "127.0.0.1:7777".parse().unwrap();
+
+        world.spawn((
+            Networked {
+                id: NetId::new(0, 0),
+            },
+            Owner { addr },
+            Secret(1),
+        ));
+        world.spawn((
+            Networked {
+                id: NetId::new(1, 0),
+            },
+            Secret(2),
+        ));
+
+        schedule.run(&mut world);
+        assert!(drain(&mut world).is_empty());
+
+        schedule.run(&mut world);
+        let commands = drain(&mut world);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].target, TargetAddress::Targeted(vec![addr]));
+        assert_eq!(commands[0].reliability, Reliability::Unreliable);
+    }
+}
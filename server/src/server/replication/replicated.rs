@@ -0,0 +1,46 @@
+use bevy_ecs::component::Component;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::server::{
+    opcode::Reliability,
+    replication::rate_tiers::{RateTier, DISTANCE_TIERS},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    // every connection with the entity in its area of interest
+    Interested,
+    // only the connection that owns the entity, see `Owner`
+    Owner,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReplicationSettings {
+    pub reliability: Reliability,
+    // changes are collected every `interval_ticks` ticks, 1 replicates on every tick
+    pub interval_ticks: u32,
+    pub visibility: Visibility,
+    // how often interested connections get changes depending on their distance, owners always get them
+    pub tiers: &'static [RateTier],
+}
+
+impl Default for ReplicationSettings {
+    fn default() -> Self {
+        ReplicationSettings {
+            reliability: Reliability::Reliable,
+            interval_ticks: 1,
+            visibility: Visibility::Interested,
+            tiers: DISTANCE_TIERS,
+        }
+    }
+}
+
+// marks a component whose changes are sent to clients, the client registers the same type to apply them
+pub trait Replicated: Component + Serialize + DeserializeOwned + Clone {
+    // identifies the component on the wire, must be unique and never change once shipped
+    const NAME: &'static str;
+
+    fn settings() -> ReplicationSettings {
+        ReplicationSettings::default()
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+use crate::server::{
+    components::shared::{net_id::NetId, quantized_vec3::QuantizedVec3, vec3d::Vec3d},
+    error::Error,
+    protocols::send::snapshot_packet::{EntityPosition, SnapshotPacket},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldSnapshot {
+    pub sequence: u32,
+    pub precision: f64,
+    // shared between the snapshots and clients that saw the same thing
+    pub positions: Arc<BTreeMap<NetId, QuantizedVec3>>,
+}
+
+impl WorldSnapshot {
+    pub fn new(sequence: u32, precision: f64, positions: BTreeMap<NetId, QuantizedVec3>) -> Self {
+        Self::shared(sequence, precision, Arc::new(positions))
+    }
+
+    pub fn shared(
+        sequence: u32,
+        precision: f64,
+        positions: Arc<BTreeMap<NetId, QuantizedVec3>>,
+    ) -> Self {
+        WorldSnapshot {
+            sequence,
+            precision,
+            positions,
+        }
+    }
+
+    pub fn position(&self, id: NetId) -> Option<Vec3d> {
+        self.positions
+            .get(&id)
+            .map(|position| position.to_vec3d(self.precision))
+    }
+
+    // encodes this snapshot relative to what the client already acknowledged
+    pub fn diff(&self, baseline: Option<&WorldSnapshot>) -> SnapshotPacket {
+        let mut packet = SnapshotPacket {
+            sequence: self.sequence,
+            baseline: baseline.map(|baseline| baseline.sequence),
+            precision: self.precision,
+            added: vec![],
+            moved: vec![],
+            removed: vec![],
+        };
+
+        for (id, position) in self.positions.iter() {
+            match baseline.and_then(|baseline| baseline.positions.get(id)) {
+                None => packet.added.push(EntityPosition {
+                    id: *id,
+                    position: *position,
+                }),
+                Some(previous) if previous != position => packet.moved.push(EntityPosition {
+                    id: *id,
+                    position: *position - *previous,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        if let Some(baseline) = baseline {
+            packet.removed = baseline
+                .positions
+                .keys()
+                .filter(|id| !self.positions.contains_key(id))
+                .copied()
+                .collect();
+        }
+
+        packet
+    }
+
+    // the receiving end of `diff`, `baseline` has to be the snapshot the packet names
+    pub fn apply(
+        baseline: Option<&WorldSnapshot>,
+        packet: &SnapshotPacket,
+    ) -> Result<WorldSnapshot, Error> {
+        let mut positions = match (packet.baseline, baseline) {
+            (None, _) => BTreeMap::new(),
+            (Some(sequence), Some(baseline)) if baseline.sequence == sequence => {
+                baseline.positions.as_ref().clone()
+            }
+            (Some(sequence), _) => return Err(Error::MissingSnapshotBaseline(sequence)),
+        };
+
+        for id in &packet.removed {
+            positions.remove(id);
+        }
+
+        for added in &packet.added {
+            positions.insert(added.id, added.position);
+        }
+
+        for moved in &packet.moved {
+            let position = positions
+                .get_mut(&moved.id)
+                .ok_or(Error::EntityNotFound(moved.id))?;
+
+            *position = *position + moved.position;
+        }
+
+        Ok(WorldSnapshot::new(
+            packet.sequence,
+            packet.precision,
+            positions,
+        ))
+    }
+}
+
+// the last snapshots sent to (or received from) one peer
+pub struct SnapshotHistory {
+    snapshots: VecDeque<WorldSnapshot>,
+    capacity: usize,
+    acked: Option<u32>,
+}
+
+impl SnapshotHistory {
+    pub fn new(capacity: usize) -> Self {
+        SnapshotHistory {
+            snapshots: VecDeque::new(),
+            capacity: capacity.max(1),
+            acked: None,
+        }
+    }
+
+    pub fn push(&mut self, snapshot: WorldSnapshot) {
+        self.snapshots.push_back(snapshot);
+
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&WorldSnapshot> {
+        self.snapshots.back()
+    }
+
+    pub fn get(&self, sequence: u32) -> Option<&WorldSnapshot> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.sequence == sequence)
+    }
+
+    pub fn acknowledge(&mut self, sequence: u32) {
+        if self.get(sequence).is_none() || self.acked.is_some_and(|acked| acked >= sequence) {
+            return;
+        }
+
+        self.acked = Some(sequence);
+
+        // anything older than the ack can never be a baseline again
+        self.snapshots
+            .retain(|snapshot| snapshot.sequence >= sequence);
+    }
+
+    // falls back to a full snapshot once the acknowledged one has been evicted
+    pub fn baseline(&self) -> Option<&WorldSnapshot> {
+        self.acked.and_then(|sequence| self.get(sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(sequence: u32, positions: &[(u32, i32)]) -> WorldSnapshot {
+        WorldSnapshot::new(
+            sequence,
+            0.01,
+            positions
+                .iter()
+                .map(|(index, x)| (NetId::new(*index, 0), QuantizedVec3::new(*x, 0, 0)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diff_only_carries_changes() {
+        let baseline = snapshot(1, &[(0, 100), (1, 200), (2, 300)]);
+        let current = snapshot(2, &[(0, 100), (1, 250), (3, 400)]);
+
+        let packet = current.diff(Some(&baseline));
+
+        assert_eq!(packet.baseline, Some(1));
+        assert_eq!(
+            packet.added,
+            vec![EntityPosition {
+                id: NetId::new(3, 0),
+                position: QuantizedVec3::new(400, 0, 0)
+            }]
+        );
+        assert_eq!(
+            packet.moved,
+            vec![EntityPosition {
+                id: NetId::new(1, 0),
+                position: QuantizedVec3::new(50, 0, 0)
+            }]
+        );
+        assert_eq!(packet.removed, vec![NetId::new(2, 0)]);
+
+        assert_eq!(
+            WorldSnapshot::apply(Some(&baseline), &packet).unwrap(),
+            current
+        );
+    }
+
+    #[test]
+    fn test_diff_without_baseline_is_full() {
+        let current = snapshot(1, &[(0, 100), (1, 200)]);
+
+        let packet = current.diff(None);
+
+        assert_eq!(packet.baseline, None);
+        assert_eq!(packet.added.len(), 2);
+        assert_eq!(WorldSnapshot::apply(None, &packet).unwrap(), current);
+    }
+
+    #[test]
+    fn test_apply_requires_named_baseline() {
+        let packet = snapshot(3, &[(0, 1)]).diff(Some(&snapshot(2, &[])));
+
+        assert!(matches!(
+            WorldSnapshot::apply(Some(&snapshot(1, &[])), &packet),
+            Err(Error::MissingSnapshotBaseline(2))
+        ));
+    }
+
+    #[test]
+    fn test_quantized_position_round_trips() {
+        let position = Vec3d::new(1.234, -5.678, 0.0);
+
+        let restored = QuantizedVec3::quantize(&position, 0.01).to_vec3d(0.01);
+
+        assert!((restored.x - 1.23).abs() < 1e-9);
+        assert!((restored.y + 5.68).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_history_tracks_latest_ack() {
+        let mut history = SnapshotHistory::new(2);
+
+        history.push(snapshot(1, &[]));
+        history.push(snapshot(2, &[]));
+        assert!(history.baseline().is_none());
+
+        history.acknowledge(2);
+        history.acknowledge(1);
+        assert_eq!(history.baseline().map(|s| s.sequence), Some(2));
+
+        history.push(snapshot(3, &[]));
+        history.push(snapshot(4, &[]));
+        assert!(history.baseline().is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::server::packet_receiver::packet_receiver::PacketReceiver;
 use crate::server::packets::packet::Packet;
 use anyhow::Result;
-use log::{debug, info};
+use log::{debug, info, warn};
 use std::sync::Arc;
 use std::{net::SocketAddr, sync::Mutex};
 use tokio::net::UdpSocket;
@@ -52,9 +52,14 @@ impl Server {
 
             let (len, addr) = rec?;
 
-            let as_str = std::str::from_utf8(&buf[..len]).unwrap();
-
-            let packet: Packet = serde_json::from_str::<Packet>(as_str).unwrap();
+            // unknown opcodes or a foreign envelope mean an incompatible client, never a reason to stop the server
+            let packet = match serde_json::from_slice::<Packet>(&buf[..len]) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    warn!("Dropping malformed packet from {:?}: {:?}", addr, e);
+                    continue;
+                }
+            };
 
             self.packet_receiver.consume(packet, addr);
             self.packet_sender.lock().unwrap().try_register(addr);
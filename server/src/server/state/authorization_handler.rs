@@ -1,59 +1,24 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::net::SocketAddr;
 
-use uuid::Uuid;
+use crate::server::{components::shared::net_id::NetId, connections::session_token::SessionToken};
 
+// implemented by `ConnectionManager`, which keeps what each connection owns
 pub trait AuthorizationHandlerTrait: Send + Sync {
-    fn add_entity(&mut self, addr: SocketAddr, entity_id: Uuid);
-    fn get_character_id(&self, addr: SocketAddr) -> Option<Uuid>;
-    fn remove_entity(&mut self, addr: SocketAddr, entity_id: Uuid);
-    fn is_authorized(&self, addr: SocketAddr, entity_id: Uuid) -> bool;
-}
-
-pub struct SocketOwned {
-    pub player_character: Uuid,
-    // first one is always the player character
-    pub entities: Vec<Uuid>,
-}
-
-pub struct AuthorizationHandler {
-    owned: HashMap<SocketAddr, SocketOwned>,
-}
-
-impl AuthorizationHandler {
-    pub fn new() -> Self {
-        AuthorizationHandler {
-            owned: HashMap::new(),
-        }
-    }
-}
-
-impl AuthorizationHandlerTrait for AuthorizationHandler {
-    fn add_entity(&mut self, addr: SocketAddr, entity_id: Uuid) {
-        let entry = self.owned.entry(addr).or_insert_with(|| SocketOwned {
-            player_character: entity_id,
-            entities: vec![],
-        });
-
-        if !entry.entities.contains(&entity_id) {
-            entry.entities.push(entity_id);
-        }
-    }
-
-    fn get_character_id(&self, addr: SocketAddr) -> Option<Uuid> {
-        self.owned.get(&addr).map(|entry| entry.player_character)
-    }
-
-    fn remove_entity(&mut self, addr: SocketAddr, entity_id: Uuid) {
-        if let Some(entry) = self.owned.get_mut(&addr) {
-            entry.entities.retain(|&id| id != entity_id);
-        }
-    }
-
-    fn is_authorized(&self, addr: SocketAddr, entity_id: Uuid) -> bool {
-        if let Some(entry) = self.owned.get(&addr) {
-            entry.entities.contains(&entity_id)
-        } else {
-            false
-        }
-    }
+    fn add_entity(&mut self, addr: SocketAddr, entity_id: NetId);
+    fn get_character_id(&self, addr: SocketAddr) -> Option<NetId>;
+    fn remove_entity(&mut self, addr: SocketAddr, entity_id: NetId);
+    fn is_authorized(&self, addr: SocketAddr, entity_id: NetId) -> bool;
+    fn player_characters(&self) -> Vec<(SocketAddr, NetId)>;
+    // forgets a despawned entity, returns the connection that owned it
+    fn release_entity(&mut self, entity_id: NetId) -> Option<SocketAddr>;
+    // control of an entity other than the player character, exclusive to one connection at a time
+    fn grant_control(&mut self, addr: SocketAddr, entity_id: NetId) -> bool;
+    fn revoke_control(&mut self, addr: SocketAddr, entity_id: NetId) -> bool;
+    // counts an action `addr` was not authorized for, returns how many it has had rejected so far
+    fn record_rejection(&mut self, addr: SocketAddr) -> u64;
+    // the session that lets `addr` keep its entities across address changes and reconnects
+    fn issue_session(&mut self, addr: SocketAddr) -> SessionToken;
+    // logs `addr` in, fails while another connection is logged in to the same account
+    fn authenticate(&mut self, addr: SocketAddr, account: &str) -> bool;
+    fn account(&self, addr: SocketAddr) -> Option<String>;
 }
@@ -1,4 +1,3 @@
 pub mod authorization_handler;
-pub mod packet_id_generator;
 pub mod state_handler;
 pub mod ticker;
@@ -1,15 +1,35 @@
-use bevy_ecs::{schedule::Schedule, world::World};
+use bevy_ecs::{
+    schedule::{IntoScheduleConfigs, Schedule},
+    world::World,
+};
 use log::{debug, info, trace};
 use std::sync::{Arc, Mutex, RwLock};
 
 use crate::server::{
+    characters::persistence::DirtyCharacters,
     commands::{
-        move_command::MoveCommand, moved_command::MovedCommand, spawn_command::SpawnCommand,
-        StateMappedCommand,
+        authenticated_command::AuthenticatedCommand, character_list_command::CharacterListCommand,
+        control_command::ControlCommand, despawn_command::DespawnCommand,
+        enown_command::EnownCommand, enter_interest_command::EnterInterestCommand,
+        error_command::ErrorCommand, leave_interest_command::LeaveInterestCommand,
+        move_command::MoveCommand, replicate_command::ReplicateCommand,
+        spawn_command::SpawnCommand, StateMappedCommand,
     },
+    components::movement_state::MovementState,
     packet_sender::packet_sender::ServerPacketSender,
+    replication::{
+        client_snapshots::{ClientSnapshots, SnapshotConfig},
+        dead_reckoning::{dead_reckoning_system, DeadReckoningConfig},
+        interest::{interest_system, Interest, InterestConfig},
+        replicate_system::replicate,
+    },
     systems::{
-        self, command_container::CommandContainer, delta_time::DeltaTime,
+        self,
+        command_container::CommandContainer,
+        delta_time::DeltaTime,
+        net_id_allocator::NetIdAllocator,
+        networked_index::NetworkedIndex,
+        spatial_grid::{update_spatial_grid, SpatialGrid},
         untargeted_command_container::UntargetedCommandContainer,
     },
 };
@@ -26,6 +46,9 @@ pub struct ServerStateHandler {
     pub(super) schedule: Arc<Mutex<Schedule>>,
     pub(super) ticker: Arc<Mutex<dyn TickerTrait>>,
     pub(super) sender: Arc<Mutex<ServerPacketSender>>,
+    pub(super) snapshot_config: SnapshotConfig,
+    pub(super) interest_config: InterestConfig,
+    pub(super) dead_reckoning_config: DeadReckoningConfig,
 }
 
 impl ServerStateHandler {
@@ -40,9 +63,30 @@ impl ServerStateHandler {
             schedule: Arc::new(Mutex::new(schedule)),
             ticker,
             sender,
+            snapshot_config: SnapshotConfig::default(),
+            interest_config: InterestConfig::default(),
+            dead_reckoning_config: DeadReckoningConfig::default(),
         }
     }
 
+    pub fn with_snapshot_config(mut self, snapshot_config: SnapshotConfig) -> Self {
+        self.snapshot_config = snapshot_config;
+        self
+    }
+
+    pub fn with_interest_config(mut self, interest_config: InterestConfig) -> Self {
+        self.interest_config = interest_config;
+        self
+    }
+
+    pub fn with_dead_reckoning_config(
+        mut self,
+        dead_reckoning_config: DeadReckoningConfig,
+    ) -> Self {
+        self.dead_reckoning_config = dead_reckoning_config;
+        self
+    }
+
     fn register_resources(&mut self, world: Arc<RwLock<World>>) {
         world
             .write()
@@ -54,24 +98,121 @@ impl ServerStateHandler {
         world
             .write()
             .unwrap()
-            .insert_resource(CommandContainer::<MovedCommand> {
+            .insert_resource(UntargetedCommandContainer::<SpawnCommand> {
+                entries: Default::default(),
+            });
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(UntargetedCommandContainer::<EnownCommand> {
                 entries: Default::default(),
             });
 
         world
             .write()
             .unwrap()
-            .insert_resource(UntargetedCommandContainer::<SpawnCommand> {
+            .insert_resource(UntargetedCommandContainer::<ControlCommand> {
                 entries: Default::default(),
             });
 
+        world.write().unwrap().insert_resource(
+            UntargetedCommandContainer::<AuthenticatedCommand> {
+                entries: Default::default(),
+            },
+        );
+
+        world.write().unwrap().insert_resource(
+            UntargetedCommandContainer::<CharacterListCommand> {
+                entries: Default::default(),
+            },
+        );
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(UntargetedCommandContainer::<ErrorCommand> {
+                entries: Default::default(),
+            });
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(UntargetedCommandContainer::<ReplicateCommand> {
+                entries: Default::default(),
+            });
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(UntargetedCommandContainer::<DespawnCommand> {
+                entries: Default::default(),
+            });
+
+        world.write().unwrap().insert_resource(
+            UntargetedCommandContainer::<EnterInterestCommand> {
+                entries: Default::default(),
+            },
+        );
+
+        world.write().unwrap().insert_resource(
+            UntargetedCommandContainer::<LeaveInterestCommand> {
+                entries: Default::default(),
+            },
+        );
+
         world.write().unwrap().insert_resource(DeltaTime::default());
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(DirtyCharacters::default());
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(NetworkedIndex::new());
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(NetIdAllocator::new());
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(ClientSnapshots::new(self.snapshot_config));
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(Interest::new(self.interest_config));
+
+        // a radius query around a player then only touches the neighbouring cells
+        world
+            .write()
+            .unwrap()
+            .insert_resource(SpatialGrid::new(self.interest_config.radius));
+
+        world
+            .write()
+            .unwrap()
+            .insert_resource(self.dead_reckoning_config);
     }
 
     fn map_state(world: Arc<RwLock<World>>, sender: Arc<Mutex<ServerPacketSender>>) {
         MoveCommand::map(world.clone(), sender.clone());
-        MovedCommand::map(world.clone(), sender.clone());
         SpawnCommand::map(world.clone(), sender.clone());
+        EnownCommand::map(world.clone(), sender.clone());
+        AuthenticatedCommand::map(world.clone(), sender.clone());
+        CharacterListCommand::map(world.clone(), sender.clone());
+        ControlCommand::map(world.clone(), sender.clone());
+        EnterInterestCommand::map(world.clone(), sender.clone());
+        LeaveInterestCommand::map(world.clone(), sender.clone());
+        DespawnCommand::map(world.clone(), sender.clone());
+        ErrorCommand::map(world.clone(), sender.clone());
+        ReplicateCommand::map(world.clone(), sender.clone());
+        ClientSnapshots::map(world.clone(), sender.clone());
 
         trace!("Done enqueing packets");
     }
@@ -96,16 +237,38 @@ impl StateHandler for ServerStateHandler {
         let movement_system = systems::movement::movement_system;
 
         let enter_world_system = systems::enter_world::enter_world_system;
+        let despawn_system = systems::despawn::despawn_system;
+        let connection_timeout_system = systems::connection_timeout::connection_timeout_system;
+        let session_migration_system = systems::session_migration::session_migration_system;
+        let control_system = systems::control::control_system;
+        let authentication_system = systems::authentication::authentication_system;
+        let character_system = systems::characters::character_system;
+        let character_persistence_system = systems::persistence::character_persistence_system;
 
         let delta_time_system = systems::delta_time::update_delta_time;
 
         schedule.lock().unwrap().add_systems((
             enter_world_system,
+            authentication_system,
+            character_system.before(enter_world_system),
+            connection_timeout_system.before(despawn_system),
+            character_persistence_system
+                .after(movement_system)
+                .after(connection_timeout_system)
+                .before(despawn_system),
+            session_migration_system.before(interest_system),
+            control_system.before(move_handling_system),
+            despawn_system.before(interest_system),
             move_handling_system,
             movement_system,
             delta_time_system,
+            update_spatial_grid.after(movement_system),
+            dead_reckoning_system.after(movement_system),
+            interest_system.after(update_spatial_grid),
         ));
 
+        replicate::<MovementState>(&mut schedule.lock().unwrap());
+
         let shared_world = self.world.clone();
         let shared_sender = self.sender.clone();
 
@@ -138,7 +301,7 @@ impl StateHandler for ServerStateHandler {
 
 #[cfg(test)]
 mod tests {
-    use crate::server::state::packet_id_generator::PacketIdGenerator;
+    use crate::server::connections::connection_manager::ConnectionManager;
 
     use super::*;
     use bevy_ecs::world::World;
@@ -153,12 +316,12 @@ mod tests {
     fn test_register_resources_only_registers_expected_resources() {
         let world = Arc::new(RwLock::new(World::default()));
         let mock_ticker = Arc::new(Mutex::new(MockTicker));
-        let mock_packet_id_generator = Arc::new(Mutex::new(PacketIdGenerator::new()));
+        let mock_connection_manager = Arc::new(RwLock::new(ConnectionManager::default()));
         let mut handler = ServerStateHandler::new(
             mock_ticker.clone(),
             Arc::new(Mutex::new(ServerPacketSender::new(
                 mock_ticker,
-                mock_packet_id_generator,
+                mock_connection_manager,
             ))),
         );
 
@@ -170,18 +333,35 @@ mod tests {
         assert!(world_read.contains_resource::<UntargetedCommandContainer<SpawnCommand>>());
         assert!(!world_read.contains_resource::<CommandContainer<SpawnCommand>>());
         assert!(!world_read.contains_resource::<UntargetedCommandContainer<MoveCommand>>());
+        assert!(world_read.contains_resource::<NetworkedIndex>());
+        assert!(world_read.contains_resource::<NetIdAllocator>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<ReplicateCommand>>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<DespawnCommand>>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<EnownCommand>>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<ControlCommand>>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<AuthenticatedCommand>>());
+        assert!(world_read.contains_resource::<ClientSnapshots>());
+        assert!(world_read.contains_resource::<Interest>());
+        assert!(world_read.contains_resource::<SpatialGrid>());
+        assert!(world_read.contains_resource::<DeadReckoningConfig>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<EnterInterestCommand>>());
+        assert!(world_read.contains_resource::<UntargetedCommandContainer<LeaveInterestCommand>>());
+        assert!(world_read.contains_resource::<DirtyCharacters>());
     }
 
     struct MockSender;
     impl crate::server::packet_sender::packet_sender::PacketSender for MockSender {
         fn try_register(&mut self, _addr: std::net::SocketAddr) {}
         fn enqueue(&self, _send_packet: crate::server::packet_sender::send_packet::SendPacket) {}
+        fn connections(&self) -> HashSet<SocketAddr> {
+            HashSet::new()
+        }
         fn initialise(&mut self, _socket: std::sync::Arc<tokio::net::UdpSocket>) {}
         fn emit_packets(
             _packet_datas: Vec<crate::server::packet_sender::send_packet::SendPacket>,
             _connections: HashSet<SocketAddr>,
             _socket: Arc<UdpSocket>,
-            _packet_id_generator: Arc<Mutex<PacketIdGenerator>>,
+            _connection_manager: Arc<RwLock<ConnectionManager>>,
         ) {
         }
     }
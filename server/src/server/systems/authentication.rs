@@ -0,0 +1,165 @@
+use bevy_ecs::system::{Res, ResMut};
+use log::{info, warn};
+
+use crate::server::{
+    accounts::account_service::Accounts,
+    commands::{authenticated_command::AuthenticatedCommand, error_command::ErrorCommand},
+    connections::connection_manager::Connections,
+    error::Error,
+    packet_sender::TargetAddress,
+    protocols::send::error_packet::ErrorCode,
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// applies finished registrations and logins to their connections and answers the client
+pub fn authentication_system(
+    accounts: Res<Accounts>,
+    connections: Res<Connections>,
+    mut authenticated: ResMut<UntargetedCommandContainer<AuthenticatedCommand>>,
+    mut errors: ResMut<UntargetedCommandContainer<ErrorCommand>>,
+) {
+    let outcomes = accounts.0.take_outcomes();
+
+    if outcomes.is_empty() {
+        return;
+    }
+
+    let mut connections = connections
+        .0
+        .write()
+        .expect("Failed to get write lock on connection manager");
+
+    for outcome in outcomes {
+        let result = outcome.result.and_then(|account| {
+            if connections.authenticate(outcome.addr, &account.name) {
+                Ok(account)
+            } else {
+                Err(Error::AccountInUse(account.name))
+            }
+        });
+
+        match result {
+            Ok(account) => {
+                info!("{:?} logged in as {}", outcome.addr, account.name);
+
+                authenticated
+                    .entries
+                    .push_back(AuthenticatedCommand::new(account.name, outcome.addr));
+            }
+            Err(e) => {
+                warn!("Rejecting authentication of {:?}: {}", outcome.addr, e);
+
+                let (code, message) = rejection(e);
+
+                errors.entries.push_back(ErrorCommand::new(
+                    code,
+                    message,
+                    TargetAddress::Targeted(vec![outcome.addr]),
+                ));
+            }
+        }
+    }
+}
+
+fn rejection(error: Error) -> (ErrorCode, String) {
+    let code = match error {
+        Error::AccountExists(_) => ErrorCode::AccountExists,
+        Error::InvalidAccountDetails(_) => ErrorCode::InvalidAccountDetails,
+        Error::InvalidCredentials(_) => ErrorCode::InvalidCredentials,
+        Error::AccountBanned(_) => ErrorCode::AccountBanned,
+        Error::LoginThrottled(_) => ErrorCode::LoginThrottled,
+        Error::RegistrationThrottled(_) => ErrorCode::RegistrationThrottled,
+        Error::AccountInUse(_) => ErrorCode::AccountInUse,
+        // storage details stay in the server log
+        _ => {
+            return (
+                ErrorCode::AccountUnavailable,
+                "Accounts are unavailable, try again later".to_string(),
+            )
+        }
+    };
+
+    (code, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+    use common::{
+        account_store::InMemoryAccountStore,
+        model::{Account, AccountStatus},
+    };
+
+    use crate::server::{
+        accounts::{account_service::AccountService, login_throttle::ThrottleConfig},
+        connections::connection_manager::ConnectionManager,
+    };
+
+    use super::*;
+
+    fn account(name: &str) -> Account {
+        Account {
+            name: name.to_string(),
+            password_hash: String::new(),
+            status: AccountStatus::Active,
+        }
+    }
+
+    #[test]
+    fn test_outcomes_authenticate_or_reject_connections() {
+        let first: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let second: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+        let third: SocketAddr = "127.0.0.1:9999".parse().unwrap();
+
+        let accounts = Arc::new(AccountService::new(
+            Arc::new(InMemoryAccountStore::new()),
+            ThrottleConfig::default(),
+        ));
+        accounts.complete(first, Ok(account("player")));
+        accounts.complete(second, Ok(account("player")));
+        accounts.complete(third, Err(Error::InvalidCredentials("player".to_string())));
+
+        let connections = Arc::new(RwLock::new(ConnectionManager::default()));
+
+        let mut world = World::default();
+        world.insert_resource(Accounts(accounts));
+        world.insert_resource(Connections(connections.clone()));
+        world.insert_resource(UntargetedCommandContainer::<AuthenticatedCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<ErrorCommand> {
+            entries: Default::default(),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(authentication_system);
+        schedule.run(&mut world);
+
+        let authenticated = &world
+            .resource::<UntargetedCommandContainer<AuthenticatedCommand>>()
+            .entries;
+        assert_eq!(authenticated.len(), 1);
+        assert_eq!(authenticated[0].target, first);
+
+        let codes: Vec<_> = world
+            .resource::<UntargetedCommandContainer<ErrorCommand>>()
+            .entries
+            .iter()
+            .map(|error| error.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![ErrorCode::AccountInUse, ErrorCode::InvalidCredentials]
+        );
+
+        let connections = connections.read().unwrap();
+        assert_eq!(connections.account(first).as_deref(), Some("player"));
+        assert_eq!(connections.account(second), None);
+    }
+}
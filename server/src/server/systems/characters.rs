@@ -0,0 +1,269 @@
+use bevy_ecs::system::{Res, ResMut};
+use log::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::server::{
+    characters::character_service::{CharacterOutcome, Characters},
+    commands::{
+        character_list_command::CharacterListCommand,
+        enown_command::EnownCommand,
+        error_command::ErrorCommand,
+        spawn_command::{EntityComponent, SpawnCommand},
+    },
+    components::movement_state::MovementStateType,
+    connections::connection_manager::Connections,
+    error::Error,
+    packet_sender::TargetAddress,
+    protocols::send::{character_list_packet::CharacterSummary, error_packet::ErrorCode},
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::{
+        net_id_allocator::NetIdAllocator, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+// answers finished character requests and spawns selected characters where they were left
+pub fn character_system(
+    characters: Res<Characters>,
+    connections: Res<Connections>,
+    mut net_id_allocator: ResMut<NetIdAllocator>,
+    mut spawn_commands: ResMut<UntargetedCommandContainer<SpawnCommand>>,
+    mut enown_commands: ResMut<UntargetedCommandContainer<EnownCommand>>,
+    mut character_lists: ResMut<UntargetedCommandContainer<CharacterListCommand>>,
+    mut errors: ResMut<UntargetedCommandContainer<ErrorCommand>>,
+) {
+    let outcomes = characters.0.take_outcomes();
+
+    if outcomes.is_empty() {
+        return;
+    }
+
+    let mut connections = connections
+        .0
+        .write()
+        .expect("Failed to get write lock on connection manager");
+
+    for outcome in outcomes {
+        let (addr, error) = match outcome {
+            CharacterOutcome::Listed {
+                addr,
+                result: Ok(listed),
+            } => {
+                character_lists.entries.push_back(CharacterListCommand::new(
+                    listed.iter().map(CharacterSummary::from).collect(),
+                    addr,
+                ));
+                continue;
+            }
+            CharacterOutcome::Selected {
+                addr,
+                result: Ok(character),
+            } => {
+                // logged out or over to another account while the character was loading
+                if connections.account(addr).as_deref() != Some(character.account.as_str()) {
+                    debug!("Dropping selection of {} for {:?}", character.name, addr);
+                    continue;
+                }
+
+                // the enter was repeated while loading
+                if let Some(existing) = connections.get_character_id(addr) {
+                    let session = connections.issue_session(addr);
+
+                    enown_commands
+                        .entries
+                        .push_back(EnownCommand::new(existing, session, addr));
+                    continue;
+                }
+
+                let id = match Uuid::parse_str(&character.uuid) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        error!("Character {} has a malformed id: {}", character.name, e);
+                        reject(&mut errors, addr, Error::CharacterStorage(e.to_string()));
+                        continue;
+                    }
+                };
+
+                let net_id = match net_id_allocator.allocate() {
+                    Ok(net_id) => net_id,
+                    Err(e) => {
+                        error!("Dropping enter from {:?}: {}", addr, e);
+                        continue;
+                    }
+                };
+
+                connections.add_entity(addr, net_id);
+                let session = connections.issue_session(addr);
+
+                info!("{:?} entered the world as {}", addr, character.name);
+
+                let position = &character.position;
+
+                spawn_commands.entries.push_back(
+                    SpawnCommand::new(
+                        vec![
+                            EntityComponent::Position(position.x, position.y, position.z),
+                            EntityComponent::Networked(net_id),
+                            EntityComponent::Character(id),
+                            EntityComponent::MovementState(
+                                MovementStateType::Stopped,
+                                character.movement.velocity,
+                                (&character.movement.direction).into(),
+                            ),
+                        ],
+                        TargetAddress::Targeted(vec![addr]),
+                    )
+                    .with_session(session),
+                );
+                continue;
+            }
+            CharacterOutcome::Listed {
+                addr,
+                result: Err(e),
+            }
+            | CharacterOutcome::Selected {
+                addr,
+                result: Err(e),
+            } => (addr, e),
+        };
+
+        reject(&mut errors, addr, error);
+    }
+}
+
+fn reject(
+    errors: &mut UntargetedCommandContainer<ErrorCommand>,
+    addr: std::net::SocketAddr,
+    error: Error,
+) {
+    warn!("Rejecting character request of {:?}: {}", addr, error);
+
+    let (code, message) = match error {
+        Error::CharacterNameTaken(_) => (ErrorCode::CharacterNameTaken, error.to_string()),
+        Error::InvalidCharacterDetails(_) => {
+            (ErrorCode::InvalidCharacterDetails, error.to_string())
+        }
+        Error::CharacterNotFound(_) => (ErrorCode::CharacterNotFound, error.to_string()),
+        Error::CharacterLimitReached(_) => (ErrorCode::CharacterLimitReached, error.to_string()),
+        // storage details stay in the server log
+        _ => (
+            ErrorCode::CharacterUnavailable,
+            "Characters are unavailable, try again later".to_string(),
+        ),
+    };
+
+    errors.entries.push_back(ErrorCommand::new(
+        code,
+        message,
+        TargetAddress::Targeted(vec![addr]),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+    use common::{
+        character_store::InMemoryCharacterStore,
+        model::{Appearance, Character, CharacterClass, Movement, Vec3d},
+    };
+
+    use crate::server::{
+        characters::character_service::CharacterService,
+        connections::connection_manager::ConnectionManager,
+    };
+
+    use super::*;
+
+    fn character(account: &str) -> Character {
+        Character {
+            uuid: Uuid::new_v4().to_string(),
+            name: "Arthas".to_string(),
+            name_key: "arthas".to_string(),
+            account: account.to_string(),
+            class: CharacterClass::Warrior,
+            appearance: Appearance::default(),
+            level: 10,
+            position: Vec3d {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0,
+            },
+            movement: Movement::default(),
+            zone: "start".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_selected_characters_spawn_where_they_were_left() {
+        let player: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let other: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+
+        let mut connections = ConnectionManager::default();
+        connections.authenticate(player, "player");
+        connections.authenticate(other, "other");
+        let connections = Arc::new(RwLock::new(connections));
+
+        let characters = Arc::new(CharacterService::new(
+            Arc::new(InMemoryCharacterStore::new()),
+            1,
+        ));
+        characters.complete(CharacterOutcome::Selected {
+            addr: player,
+            result: Ok(character("player")),
+        });
+        // selected before `other` switched accounts, it is not theirs to play
+        characters.complete(CharacterOutcome::Selected {
+            addr: other,
+            result: Ok(character("player")),
+        });
+        characters.complete(CharacterOutcome::Listed {
+            addr: other,
+            result: Err(Error::CharacterLimitReached(1)),
+        });
+
+        let mut world = World::default();
+        world.insert_resource(Characters(characters));
+        world.insert_resource(Connections(connections.clone()));
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(UntargetedCommandContainer::<SpawnCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<EnownCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<CharacterListCommand> {
+            entries: Default::default(),
+        });
+        world.insert_resource(UntargetedCommandContainer::<ErrorCommand> {
+            entries: Default::default(),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(character_system);
+        schedule.run(&mut world);
+
+        let spawns = &world
+            .resource::<UntargetedCommandContainer<SpawnCommand>>()
+            .entries;
+        assert_eq!(spawns.len(), 1);
+        assert!(matches!(
+            spawns[0].components[0],
+            EntityComponent::Position(x, y, z) if (x, y, z) == (1.0, 2.0, 3.0)
+        ));
+        assert!(spawns[0].session.is_some());
+
+        let errors = &world
+            .resource::<UntargetedCommandContainer<ErrorCommand>>()
+            .entries;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, ErrorCode::CharacterLimitReached);
+
+        let connections = connections.read().unwrap();
+        assert!(connections.get_character_id(player).is_some());
+        assert!(connections.get_character_id(other).is_none());
+    }
+}
@@ -2,9 +2,9 @@ use std::collections::{HashMap, VecDeque};
 
 use bevy_ecs::resource::Resource;
 
-use crate::server::commands::MapableCommand;
+use crate::server::{commands::MapableCommand, components::shared::net_id::NetId};
 
 #[derive(Resource)]
 pub struct CommandContainer<T: MapableCommand> {
-    pub entries: HashMap<String, VecDeque<T>>,
+    pub entries: HashMap<NetId, VecDeque<T>>,
 }
@@ -0,0 +1,89 @@
+use std::time::Instant;
+
+use bevy_ecs::system::{Res, ResMut};
+use log::info;
+
+use crate::server::{
+    commands::despawn_command::DespawnCommand, connections::connection_manager::Connections,
+    protocols::send::despawn_packet::DespawnReason,
+    systems::untargeted_command_container::UntargetedCommandContainer,
+};
+
+// drops connections that went silent and takes everything they owned out of the world
+pub fn connection_timeout_system(
+    connections: Res<Connections>,
+    mut despawn_commands: ResMut<UntargetedCommandContainer<DespawnCommand>>,
+) {
+    let expired = connections
+        .0
+        .write()
+        .expect("Failed to get write lock on connection manager")
+        .expire(Instant::now());
+
+    for connection in expired {
+        info!(
+            "{:?} timed out, despawning {:?}",
+            connection.addr, connection.entities
+        );
+
+        despawn_commands.entries.extend(
+            connection
+                .entities
+                .into_iter()
+                .map(|id| DespawnCommand::new(id, DespawnReason::Disconnect)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+        time::Duration,
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use crate::server::{
+        components::shared::net_id::NetId,
+        connections::connection_manager::{ConnectionConfig, ConnectionManager},
+        state::authorization_handler::AuthorizationHandlerTrait,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_timed_out_connections_despawn_their_entities() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let id = NetId::new(0, 0);
+
+        let mut connections = ConnectionManager::new(ConnectionConfig {
+            timeout: Duration::ZERO,
+            ..ConnectionConfig::default()
+        });
+        connections.add_entity(addr, id);
+        let connections = Arc::new(RwLock::new(connections));
+
+        let mut world = World::default();
+        world.insert_resource(Connections(connections.clone()));
+        world.insert_resource(UntargetedCommandContainer::<DespawnCommand> {
+            entries: Default::default(),
+        });
+
+        std::thread::sleep(Duration::from_millis(1));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(connection_timeout_system);
+        schedule.run(&mut world);
+
+        assert!(connections.read().unwrap().is_empty());
+
+        let despawns = &world
+            .resource::<UntargetedCommandContainer<DespawnCommand>>()
+            .entries;
+        assert_eq!(despawns.len(), 1);
+        assert_eq!(despawns[0].id, id);
+        assert_eq!(despawns[0].reason, DespawnReason::Disconnect);
+    }
+}
@@ -0,0 +1,137 @@
+use bevy_ecs::system::{Commands, Res, ResMut};
+use log::{debug, warn};
+
+use crate::server::{
+    commands::control_command::ControlCommand,
+    components::owner::Owner,
+    connections::connection_manager::Connections,
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::{
+        networked_index::NetworkedIndex, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+// applies runtime grants and revocations to both the connection's ownership and the entity's `Owner`
+pub fn control_system(
+    mut commands: Commands,
+    mut control_commands: ResMut<UntargetedCommandContainer<ControlCommand>>,
+    networked_index: Res<NetworkedIndex>,
+    connections: Res<Connections>,
+) {
+    let mut connections = connections
+        .0
+        .write()
+        .expect("Failed to get write lock on connection manager");
+
+    for control_command in control_commands
+        .entries
+        .iter_mut()
+        .filter(|command| command.applied.is_none())
+    {
+        let ControlCommand {
+            id,
+            target,
+            granted,
+            ..
+        } = *control_command;
+
+        let Some(entity) = networked_index.get(id) else {
+            warn!("Cannot change control of {}, it does not exist", id);
+            control_command.applied = Some(false);
+            continue;
+        };
+
+        let applied = if granted {
+            connections.grant_control(target, id)
+        } else {
+            connections.revoke_control(target, id)
+        };
+
+        debug!(
+            "{} control of {} for {:?}: {}",
+            if granted { "Granting" } else { "Revoking" },
+            id,
+            target,
+            if applied { "done" } else { "refused" }
+        );
+
+        control_command.applied = Some(applied);
+
+        match (applied, granted) {
+            (true, true) => {
+                commands.entity(entity).insert(Owner { addr: target });
+            }
+            (true, false) => {
+                commands.entity(entity).remove::<Owner>();
+            }
+            (false, _) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use crate::server::{
+        components::networked::Networked, connections::connection_manager::ConnectionManager,
+        systems::net_id_allocator::NetIdAllocator,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_grant_and_revoke_update_owner_and_authorization() {
+        let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(UntargetedCommandContainer::<ControlCommand> {
+            entries: Default::default(),
+        });
+
+        let character = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        let pet = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        let pet_entity = world.spawn(Networked { id: pet }).id();
+
+        let mut connections = ConnectionManager::default();
+        connections.add_entity(addr, character);
+        let connections = Arc::new(RwLock::new(connections));
+        world.insert_resource(Connections(connections.clone()));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(control_system);
+
+        world
+            .resource_mut::<UntargetedCommandContainer<ControlCommand>>()
+            .entries
+            .push_back(ControlCommand::grant(pet, addr));
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Owner>(pet_entity).unwrap().addr, addr);
+        assert!(connections.read().unwrap().is_authorized(addr, pet));
+
+        world
+            .resource_mut::<UntargetedCommandContainer<ControlCommand>>()
+            .entries
+            .push_back(ControlCommand::revoke(pet, addr));
+        schedule.run(&mut world);
+
+        assert!(world.get::<Owner>(pet_entity).is_none());
+        assert!(!connections.read().unwrap().is_authorized(addr, pet));
+
+        let applied: Vec<_> = world
+            .resource::<UntargetedCommandContainer<ControlCommand>>()
+            .entries
+            .iter()
+            .map(|command| command.applied)
+            .collect();
+        assert_eq!(applied, vec![Some(true), Some(true)]);
+    }
+}
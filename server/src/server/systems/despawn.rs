@@ -0,0 +1,116 @@
+use bevy_ecs::system::{Commands, Res, ResMut};
+use log::{debug, warn};
+
+use crate::server::{
+    commands::despawn_command::DespawnCommand,
+    connections::connection_manager::Connections,
+    replication::interest::Interest,
+    state::authorization_handler::AuthorizationHandlerTrait,
+    systems::{
+        networked_index::NetworkedIndex, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+pub fn despawn_system(
+    mut commands: Commands,
+    mut despawn_commands: ResMut<UntargetedCommandContainer<DespawnCommand>>,
+    networked_index: Res<NetworkedIndex>,
+    connections: Res<Connections>,
+    mut interest: ResMut<Interest>,
+) {
+    for despawn_command in despawn_commands
+        .entries
+        .iter_mut()
+        .filter(|command| command.targets.is_none())
+    {
+        let Some(entity) = networked_index.get(despawn_command.id) else {
+            warn!("Cannot despawn {}, it does not exist", despawn_command.id);
+            despawn_command.targets = Some(vec![]);
+            continue;
+        };
+
+        debug!(
+            "Despawning {} ({:?})",
+            despawn_command.id, despawn_command.reason
+        );
+
+        // forgotten here, so the next interest update does not report it as out of range as well
+        despawn_command.targets = Some(interest.forget(despawn_command.id));
+
+        connections
+            .0
+            .write()
+            .expect("Failed to get write lock on connection manager")
+            .release_entity(despawn_command.id);
+
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use crate::server::{
+        components::networked::Networked, connections::connection_manager::ConnectionManager,
+        protocols::send::despawn_packet::DespawnReason, replication::interest::InterestConfig,
+        systems::net_id_allocator::NetIdAllocator,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_despawn_removes_entity_and_ownership() {
+        let owner: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let watcher: SocketAddr = "127.0.0.1:9999".parse().unwrap();
+
+        let mut world = World::default();
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(UntargetedCommandContainer::<DespawnCommand> {
+            entries: Default::default(),
+        });
+
+        let id = world.resource_mut::<NetIdAllocator>().allocate().unwrap();
+        let entity = world.spawn(Networked { id }).id();
+
+        let mut connections = ConnectionManager::default();
+        connections.add_entity(owner, id);
+        let connections = Arc::new(RwLock::new(connections));
+        world.insert_resource(Connections(connections.clone()));
+
+        let mut interest = Interest::new(InterestConfig::default());
+        interest.update(watcher, [(id, 1.0)].into());
+        world.insert_resource(interest);
+
+        world
+            .resource_mut::<UntargetedCommandContainer<DespawnCommand>>()
+            .entries
+            .push_back(DespawnCommand::new(id, DespawnReason::Death));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(despawn_system);
+        schedule.run(&mut world);
+
+        assert!(world.get_entity(entity).is_err());
+        assert!(!world.resource::<NetIdAllocator>().is_alive(id));
+        assert!(!world.resource::<Interest>().is_interested(watcher, id));
+        assert!(connections
+            .read()
+            .unwrap()
+            .get_character_id(owner)
+            .is_none());
+        assert_eq!(
+            world
+                .resource::<UntargetedCommandContainer<DespawnCommand>>()
+                .entries[0]
+                .targets,
+            Some(vec![watcher])
+        );
+    }
+}
@@ -3,9 +3,10 @@ use bevy_ecs::system::{Commands, ResMut};
 use crate::server::{
     commands::spawn_command::{EntityComponent, SpawnCommand},
     components::{
-        movement_state::MovementState, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
+        character::Character, movement_state::MovementState, networked::Networked, owner::Owner,
+        position::Position, shared::vec3d::Vec3d,
     },
+    packet_sender::TargetAddress,
     systems::untargeted_command_container::UntargetedCommandContainer,
 };
 
@@ -16,6 +17,12 @@ pub fn enter_world_system(
     for spawn_command in spawn_commands.entries.iter() {
         let mut entity = commands.spawn_empty();
 
+        if let TargetAddress::Targeted(addrs) = &spawn_command.owning_connection {
+            if let [addr] = addrs.as_slice() {
+                entity.insert(Owner { addr: *addr });
+            }
+        }
+
         for component in &spawn_command.components {
             match component {
                 EntityComponent::Position(x, y, z) => {
@@ -24,13 +31,16 @@ pub fn enter_world_system(
                     });
                 }
                 EntityComponent::Networked(id) => {
-                    entity.insert(Networked { id: id.clone() });
+                    entity.insert(Networked { id: *id });
+                }
+                EntityComponent::Character(id) => {
+                    entity.insert(Character { id: *id });
                 }
-                EntityComponent::MovementState(state, velocity) => {
+                EntityComponent::MovementState(state, velocity, direction) => {
                     entity.insert(MovementState {
                         current_state: state.clone(),
                         velocity: *velocity,
-                        direction: Vec3d::zero(),
+                        direction: direction.clone(),
                     });
                 }
             }
@@ -1,7 +1,17 @@
+pub mod authentication;
+pub mod characters;
 pub mod command_container;
+pub mod connection_timeout;
+pub mod control;
 pub mod delta_time;
+pub mod despawn;
 pub mod enter_world;
 pub mod move_handling;
 pub mod movement;
+pub mod net_id_allocator;
+pub mod networked_index;
+pub mod persistence;
+pub mod session_migration;
+pub mod spatial_grid;
 pub mod trivial_move;
 pub mod untargeted_command_container;
@@ -1,39 +1,36 @@
-use bevy_ecs::{
-    entity::Entity,
-    query::With,
-    system::{Query, ResMut},
-};
-use log::debug;
+use bevy_ecs::system::{Query, Res, ResMut};
+use log::{debug, warn};
 
 use crate::server::{
     commands::move_command::MoveCommand,
-    components::{
-        movement_state::MovementState, networked::Networked, position::Position,
-        shared::vec3d::Vec3d,
-    },
+    components::{movement_state::MovementState, shared::vec3d::Vec3d},
 };
 
-use super::command_container::CommandContainer;
+use super::{command_container::CommandContainer, networked_index::NetworkedIndex};
 
 pub fn move_handling_system(
-    mut query: Query<
-        (Entity, &mut Networked, &mut MovementState),
-        (With<Position>, With<Networked>, With<MovementState>),
-    >,
+    mut query: Query<&mut MovementState>,
     mut move_command: ResMut<CommandContainer<MoveCommand>>,
+    networked_index: Res<NetworkedIndex>,
 ) {
-    for (_, networked, mut movement_state) in query.iter_mut() {
-        if let Some(commands) = move_command.entries.get_mut(&networked.id) {
-            for command in commands {
-                debug!(
-                    "Entity {} movement state is {:?}",
-                    networked.id, command.state
-                );
+    for (id, commands) in move_command.entries.iter_mut() {
+        let Some(mut movement_state) = networked_index
+            .get(*id)
+            .and_then(|entity| query.get_mut(entity).ok())
+        else {
+            if !commands.is_empty() {
+                warn!("Entity {} not found, dropping its move commands", id);
+                commands.clear();
+            }
+            continue;
+        };
 
-                movement_state.current_state = command.state.clone();
+        for command in commands.iter() {
+            debug!("Entity {} movement state is {:?}", id, command.state);
 
-                movement_state.direction = Vec3d::new(command.x, command.y, command.z);
-            }
+            movement_state.current_state = command.state.clone();
+
+            movement_state.direction = Vec3d::new(command.x, command.y, command.z);
         }
     }
 }
@@ -3,12 +3,11 @@ use std::time::Instant;
 use bevy_ecs::{
     entity::Entity,
     query::With,
-    system::{Query, Res, ResMut},
+    system::{Query, Res},
 };
 use log::{debug, trace};
 
 use crate::server::{
-    commands::moved_command::MovedCommand,
     components::{
         movement_state::{MovementState, MovementStateType},
         networked::Networked,
@@ -17,14 +16,11 @@ use crate::server::{
     systems::delta_time::DeltaTime,
 };
 
-use super::command_container::CommandContainer;
-
 pub fn movement_system(
     mut query: Query<
         (Entity, &mut Position, &mut Networked, &mut MovementState),
         (With<Position>, With<Networked>, With<MovementState>),
     >,
-    mut moved_commands: ResMut<CommandContainer<MovedCommand>>,
     delta_time: Res<DeltaTime>,
 ) {
     let delta = delta_time.current();
@@ -45,20 +41,6 @@ pub fn movement_system(
             continue;
         }
 
-        position.position.x += movement_state.direction.x * movement_state.velocity * elapsed_secs;
-        position.position.y += movement_state.direction.y * movement_state.velocity * elapsed_secs;
-        position.position.z += movement_state.direction.z * movement_state.velocity * elapsed_secs;
-
-        // Add to moved_commands or create a new one if it doesn't exist
-        moved_commands
-            .entries
-            .entry(networked.id.clone())
-            .or_default()
-            .push_back(MovedCommand::new(
-                networked.id.clone(),
-                position.position.x,
-                position.position.y,
-                position.position.z,
-            ));
+        position.position = movement_state.extrapolate(&position.position, elapsed_secs);
     }
 }
@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use bevy_ecs::resource::Resource;
+
+use crate::server::{components::shared::net_id::NetId, error::Error};
+
+struct Slot {
+    generation: u8,
+    alive: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct NetIdAllocator {
+    slots: Vec<Slot>,
+    // freed slots are reused oldest first, so a stale id has to survive many cycles to collide
+    free: VecDeque<u32>,
+}
+
+impl NetIdAllocator {
+    pub fn new() -> Self {
+        NetIdAllocator {
+            slots: vec![],
+            free: VecDeque::new(),
+        }
+    }
+
+    pub fn allocate(&mut self) -> Result<NetId, Error> {
+        if let Some(index) = self.free.pop_front() {
+            let slot = &mut self.slots[index as usize];
+            slot.alive = true;
+
+            return Ok(NetId::new(index, slot.generation));
+        }
+
+        let index = self.slots.len() as u32;
+
+        if index > NetId::MAX_INDEX {
+            return Err(Error::NetIdsExhausted);
+        }
+
+        self.slots.push(Slot {
+            generation: 0,
+            alive: true,
+        });
+
+        Ok(NetId::new(index, 0))
+    }
+
+    pub fn free(&mut self, id: NetId) {
+        if !self.is_alive(id) {
+            return;
+        }
+
+        let slot = &mut self.slots[id.index() as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.alive = false;
+
+        self.free.push_back(id.index());
+    }
+
+    pub fn is_alive(&self, id: NetId) -> bool {
+        self.slots
+            .get(id.index() as usize)
+            .is_some_and(|slot| slot.alive && slot.generation == id.generation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocates_unique_ids() {
+        let mut allocator = NetIdAllocator::new();
+
+        let a = allocator.allocate().unwrap();
+        let b = allocator.allocate().unwrap();
+
+        assert_ne!(a, b);
+        assert!(allocator.is_alive(a));
+        assert!(allocator.is_alive(b));
+    }
+
+    #[test]
+    fn test_reused_slot_gets_new_generation() {
+        let mut allocator = NetIdAllocator::new();
+
+        let first = allocator.allocate().unwrap();
+        allocator.free(first);
+        let second = allocator.allocate().unwrap();
+
+        assert_eq!(first.index(), second.index());
+        assert_ne!(first, second);
+        assert!(!allocator.is_alive(first));
+        assert!(allocator.is_alive(second));
+    }
+
+    #[test]
+    fn test_double_free_is_ignored() {
+        let mut allocator = NetIdAllocator::new();
+
+        let id = allocator.allocate().unwrap();
+        allocator.free(id);
+        allocator.free(id);
+
+        let a = allocator.allocate().unwrap();
+        let b = allocator.allocate().unwrap();
+
+        assert_ne!(a.index(), b.index());
+    }
+}
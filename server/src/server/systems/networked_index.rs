@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{entity::Entity, resource::Resource};
+
+use crate::server::{components::shared::net_id::NetId, error::Error};
+
+// kept in sync by the `Networked` component hooks, never mutate it by hand
+#[derive(Resource, Default)]
+pub struct NetworkedIndex {
+    entities: HashMap<NetId, Entity>,
+}
+
+impl NetworkedIndex {
+    pub fn new() -> Self {
+        NetworkedIndex {
+            entities: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, id: NetId, entity: Entity) {
+        self.entities.insert(id, entity);
+    }
+
+    pub(crate) fn remove(&mut self, id: NetId, entity: Entity) {
+        // a stale entity must not evict the one that took over the id
+        if self.entities.get(&id) == Some(&entity) {
+            self.entities.remove(&id);
+        }
+    }
+
+    pub fn get(&self, id: NetId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    pub fn entity(&self, id: NetId) -> Result<Entity, Error> {
+        self.get(id).ok_or(Error::EntityNotFound(id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
@@ -0,0 +1,266 @@
+use std::time::Instant;
+
+use bevy_ecs::{
+    query::{Changed, Or},
+    system::{Query, Res, ResMut},
+};
+use common::model::{CharacterState, Movement};
+
+use crate::server::{
+    characters::{character_service::Characters, persistence::DirtyCharacters},
+    commands::despawn_command::DespawnCommand,
+    components::{character::Character, movement_state::MovementState, position::Position},
+    systems::{
+        networked_index::NetworkedIndex, untargeted_command_container::UntargetedCommandContainer,
+    },
+};
+
+type PersistedCharacter = (
+    &'static Character,
+    &'static Position,
+    &'static MovementState,
+);
+
+type StateChanged = Or<(Changed<Position>, Changed<MovementState>)>;
+
+// collects characters that changed, writing them on the interval, as they leave and on shutdown
+pub fn character_persistence_system(
+    characters: Res<Characters>,
+    mut dirty: ResMut<DirtyCharacters>,
+    despawn_commands: Res<UntargetedCommandContainer<DespawnCommand>>,
+    networked_index: Res<NetworkedIndex>,
+    changed: Query<PersistedCharacter, StateChanged>,
+    in_world: Query<PersistedCharacter>,
+) {
+    let writer = characters.0.writer();
+
+    // everything was written on shutdown, later ticks must not race it
+    if writer.is_flushed() {
+        return;
+    }
+
+    if writer.shutdown_requested() {
+        dirty.states.clear();
+        writer.spawn_save(
+            in_world
+                .iter()
+                .map(|(character, position, movement)| (character.id, state(position, movement)))
+                .collect(),
+        );
+        writer.mark_flushed();
+        return;
+    }
+
+    for (character, position, movement) in &changed {
+        dirty.states.insert(character.id, state(position, movement));
+    }
+
+    // still in the world until `despawn_system` runs
+    let departed: Vec<_> = despawn_commands
+        .entries
+        .iter()
+        .filter(|command| command.targets.is_none())
+        .filter_map(|command| networked_index.get(command.id))
+        .filter_map(|entity| in_world.get(entity).ok())
+        .map(|(character, position, movement)| (character.id, state(position, movement)))
+        .collect();
+
+    if !departed.is_empty() {
+        for (id, _) in &departed {
+            dirty.states.remove(id);
+        }
+
+        writer.spawn_save(departed);
+    }
+
+    if dirty.last_flush.elapsed() >= writer.config().interval {
+        dirty.last_flush = Instant::now();
+
+        let batch: Vec<_> = dirty.states.drain().collect();
+
+        if !batch.is_empty() {
+            writer.spawn_save(batch);
+        }
+    }
+}
+
+fn state(position: &Position, movement: &MovementState) -> CharacterState {
+    CharacterState {
+        position: (&position.position).into(),
+        movement: Movement {
+            velocity: movement.velocity,
+            direction: (&movement.direction).into(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+    use common::{
+        character_store::{CharacterStore, InMemoryCharacterStore},
+        model::{Appearance, CharacterClass},
+    };
+    use uuid::Uuid;
+
+    use crate::server::{
+        characters::{character_service::CharacterService, persistence::PersistenceConfig},
+        components::{
+            movement_state::MovementStateType,
+            networked::Networked,
+            shared::{net_id::NetId, vec3d::Vec3d},
+        },
+        protocols::send::despawn_packet::DespawnReason,
+        systems::net_id_allocator::NetIdAllocator,
+    };
+
+    use super::*;
+
+    struct Fixture {
+        world: World,
+        schedule: Schedule,
+        store: Arc<InMemoryCharacterStore>,
+        service: Arc<CharacterService>,
+    }
+
+    fn fixture(interval: Duration) -> Fixture {
+        let store = Arc::new(InMemoryCharacterStore::new());
+        let service = Arc::new(
+            CharacterService::new(store.clone(), 8).with_persistence_config(PersistenceConfig {
+                interval,
+                batch_size: 10,
+            }),
+        );
+
+        let mut world = World::default();
+        world.insert_resource(Characters(service.clone()));
+        world.insert_resource(DirtyCharacters::default());
+        world.insert_resource(NetworkedIndex::new());
+        world.insert_resource(NetIdAllocator::new());
+        world.insert_resource(UntargetedCommandContainer::<DespawnCommand> {
+            entries: Default::default(),
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(character_persistence_system);
+
+        Fixture {
+            world,
+            schedule,
+            store,
+            service,
+        }
+    }
+
+    async fn spawn(fixture: &mut Fixture, x: f64) -> (Uuid, NetId) {
+        let created = fixture
+            .service
+            .create(
+                "player",
+                &format!("Name{}", x as u32),
+                CharacterClass::Mage,
+                Appearance::default(),
+            )
+            .await
+            .unwrap();
+        let id = Uuid::parse_str(&created.uuid).unwrap();
+        let net_id = fixture
+            .world
+            .resource_mut::<NetIdAllocator>()
+            .allocate()
+            .unwrap();
+
+        fixture.world.spawn((
+            Networked { id: net_id },
+            Character { id },
+            Position {
+                position: Vec3d::new(x, 0.0, 0.0),
+            },
+            MovementState {
+                current_state: MovementStateType::Moving,
+                velocity: 2.0,
+                direction: Vec3d::new(1.0, 0.0, 0.0),
+            },
+        ));
+
+        (id, net_id)
+    }
+
+    async fn saved_x(fixture: &Fixture, id: Uuid) -> f64 {
+        fixture.service.writer().settled().await;
+
+        fixture
+            .store
+            .find(&id.to_string())
+            .await
+            .unwrap()
+            .unwrap()
+            .position
+            .x
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_written_once_the_interval_passed() {
+        let mut fixture = fixture(Duration::ZERO);
+        let (id, _) = spawn(&mut fixture, 5.0).await;
+
+        fixture.schedule.run(&mut fixture.world);
+
+        assert_eq!(saved_x(&fixture, id).await, 5.0);
+
+        let saved = fixture.store.find(&id.to_string()).await.unwrap().unwrap();
+        assert_eq!(saved.movement.velocity, 2.0);
+        assert_eq!(saved.movement.direction.x, 1.0);
+        assert!(fixture
+            .world
+            .resource::<DirtyCharacters>()
+            .states
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_leaving_characters_are_written_right_away() {
+        let mut fixture = fixture(Duration::from_secs(3600));
+        let (id, net_id) = spawn(&mut fixture, 5.0).await;
+        let (other, _) = spawn(&mut fixture, 7.0).await;
+
+        fixture
+            .world
+            .resource_mut::<UntargetedCommandContainer<DespawnCommand>>()
+            .entries
+            .push_back(DespawnCommand::new(net_id, DespawnReason::Disconnect));
+
+        fixture.schedule.run(&mut fixture.world);
+
+        assert_eq!(saved_x(&fixture, id).await, 5.0);
+        // the other waits for the interval
+        assert_eq!(saved_x(&fixture, other).await, 0.0);
+        assert!(fixture
+            .world
+            .resource::<DirtyCharacters>()
+            .states
+            .contains_key(&other));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_writes_every_character() {
+        let mut fixture = fixture(Duration::from_secs(3600));
+        let (id, _) = spawn(&mut fixture, 5.0).await;
+
+        // nothing changed since the last tick, it is written anyway
+        fixture.schedule.run(&mut fixture.world);
+        fixture
+            .world
+            .resource_mut::<DirtyCharacters>()
+            .states
+            .clear();
+
+        fixture.service.writer().request_shutdown();
+        fixture.schedule.run(&mut fixture.world);
+
+        fixture.service.writer().flushed().await;
+        assert_eq!(saved_x(&fixture, id).await, 5.0);
+    }
+}
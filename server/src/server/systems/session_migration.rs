@@ -0,0 +1,65 @@
+use bevy_ecs::system::{Query, Res};
+use log::debug;
+
+use crate::server::{components::owner::Owner, connections::connection_manager::Connections};
+
+// entities follow their owner's session to its new address
+pub fn session_migration_system(connections: Res<Connections>, mut owners: Query<&mut Owner>) {
+    let migrations = connections
+        .0
+        .write()
+        .expect("Failed to get write lock on connection manager")
+        .take_migrations();
+
+    for (from, to) in migrations {
+        debug!("Moving entities owned by {:?} over to {:?}", from, to);
+
+        for mut owner in owners.iter_mut().filter(|owner| owner.addr == from) {
+            owner.addr = to;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+    };
+
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use crate::server::{
+        components::shared::net_id::NetId, connections::connection_manager::ConnectionManager,
+        state::authorization_handler::AuthorizationHandlerTrait,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_owners_follow_migrated_sessions() {
+        let from: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:8888".parse().unwrap();
+
+        let mut connections = ConnectionManager::default();
+        connections.add_entity(from, NetId::new(0, 0));
+        let session = connections.issue_session(from);
+        connections.receive(to, 0, 0, Some(session)).unwrap();
+
+        let mut world = World::default();
+        world.insert_resource(Connections(Arc::new(RwLock::new(connections))));
+        let owned = world.spawn(Owner { addr: from }).id();
+        let other = world
+            .spawn(Owner {
+                addr: "127.0.0.1:9999".parse().unwrap(),
+            })
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(session_migration_system);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Owner>(owned).unwrap().addr, to);
+        assert_ne!(world.get::<Owner>(other).unwrap().addr, to);
+    }
+}
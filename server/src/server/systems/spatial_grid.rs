@@ -0,0 +1,288 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use bevy_ecs::{
+    entity::Entity,
+    query::Changed,
+    resource::Resource,
+    system::{Query, Res, ResMut, SystemParam},
+};
+
+use crate::server::components::{position::Position, shared::vec3d::Vec3d};
+
+type Cell = (i64, i64, i64);
+
+// uniform grid over every entity with a `Position`, updated incrementally by `update_spatial_grid`
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    cells: HashMap<Cell, Vec<Entity>>,
+    entries: HashMap<Entity, (Cell, Vec3d)>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f64) -> Self {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<&Vec3d> {
+        self.entries.get(&entity).map(|(_, position)| position)
+    }
+
+    fn cell(&self, position: &Vec3d) -> Cell {
+        (
+            (position.x / self.cell_size).floor() as i64,
+            (position.y / self.cell_size).floor() as i64,
+            (position.z / self.cell_size).floor() as i64,
+        )
+    }
+
+    pub(crate) fn insert(&mut self, entity: Entity, position: Vec3d) {
+        let cell = self.cell(&position);
+
+        match self.entries.insert(entity, (cell, position)) {
+            Some((previous, _)) if previous == cell => return,
+            Some((previous, _)) => self.remove_from_cell(previous, entity),
+            None => {}
+        }
+
+        self.cells.entry(cell).or_default().push(entity);
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        if let Some((cell, _)) = self.entries.remove(&entity) {
+            self.remove_from_cell(cell, entity);
+        }
+    }
+
+    fn remove_from_cell(&mut self, cell: Cell, entity: Entity) {
+        let Some(entities) = self.cells.get_mut(&cell) else {
+            return;
+        };
+
+        if let Some(index) = entities.iter().position(|e| *e == entity) {
+            entities.swap_remove(index);
+        }
+
+        if entities.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+
+    fn cells_between(&self, min: &Vec3d, max: &Vec3d) -> impl Iterator<Item = &Entity> {
+        let (min, max) = (self.cell(min), self.cell(max));
+
+        (min.0..=max.0)
+            .flat_map(move |x| {
+                (min.1..=max.1).flat_map(move |y| (min.2..=max.2).map(move |z| (x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+
+    pub fn within_radius(&self, center: &Vec3d, radius: f64) -> Vec<Entity> {
+        let min = Vec3d::new(center.x - radius, center.y - radius, center.z - radius);
+        let max = Vec3d::new(center.x + radius, center.y + radius, center.z + radius);
+
+        self.cells_between(&min, &max)
+            .filter(|entity| self.entries[*entity].1.distance(center) <= radius)
+            .copied()
+            .collect()
+    }
+
+    pub fn within_aabb(&self, min: &Vec3d, max: &Vec3d) -> Vec<Entity> {
+        self.cells_between(min, max)
+            .filter(|entity| {
+                let position = &self.entries[*entity].1;
+
+                (min.x..=max.x).contains(&position.x)
+                    && (min.y..=max.y).contains(&position.y)
+                    && (min.z..=max.z).contains(&position.z)
+            })
+            .copied()
+            .collect()
+    }
+
+    // closest first, searches outwards ring by ring until nothing closer can remain
+    pub fn nearest(&self, center: &Vec3d, k: usize) -> Vec<Entity> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let origin = self.cell(center);
+        let mut candidates: Vec<(f64, Entity)> = vec![];
+        let mut ring: i64 = 0;
+
+        loop {
+            let side = (2 * ring + 1) as usize;
+
+            // once a ring costs more lookups than there are entities, scanning them all is cheaper
+            if side.pow(3) > self.entries.len() {
+                candidates = self
+                    .entries
+                    .iter()
+                    .map(|(entity, (_, position))| (position.distance(center), *entity))
+                    .collect();
+                break;
+            }
+
+            for x in -ring..=ring {
+                for y in -ring..=ring {
+                    for z in -ring..=ring {
+                        if x.abs().max(y.abs()).max(z.abs()) != ring {
+                            continue;
+                        }
+
+                        let cell = (origin.0 + x, origin.1 + y, origin.2 + z);
+
+                        for entity in self.cells.get(&cell).into_iter().flatten() {
+                            candidates.push((self.entries[entity].1.distance(center), *entity));
+                        }
+                    }
+                }
+            }
+
+            candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+            // anything outside the rings searched so far is at least this far away
+            let searched = ring as f64 * self.cell_size;
+
+            if candidates.len() == self.entries.len()
+                || candidates
+                    .get(k - 1)
+                    .is_some_and(|(distance, _)| *distance <= searched)
+            {
+                break;
+            }
+
+            ring += 1;
+        }
+
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        candidates
+            .into_iter()
+            .take(k)
+            .map(|(_, entity)| entity)
+            .collect()
+    }
+}
+
+// read-only proximity queries for systems
+#[derive(SystemParam)]
+pub struct Proximity<'w> {
+    grid: Res<'w, SpatialGrid>,
+}
+
+impl Proximity<'_> {
+    pub fn within_radius(&self, center: &Vec3d, radius: f64) -> Vec<Entity> {
+        self.grid.within_radius(center, radius)
+    }
+
+    pub fn within_aabb(&self, min: &Vec3d, max: &Vec3d) -> Vec<Entity> {
+        self.grid.within_aabb(min, max)
+    }
+
+    pub fn nearest(&self, center: &Vec3d, k: usize) -> Vec<Entity> {
+        self.grid.nearest(center, k)
+    }
+}
+
+// removals are handled by the `Position` component hook
+pub fn update_spatial_grid(
+    query: Query<(Entity, &Position), Changed<Position>>,
+    mut grid: ResMut<SpatialGrid>,
+) {
+    for (entity, position) in query.iter() {
+        grid.insert(entity, position.position.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{schedule::Schedule, world::World};
+
+    use super::*;
+
+    fn setup() -> (World, Schedule) {
+        let mut world = World::default();
+        world.insert_resource(SpatialGrid::new(10.0));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_spatial_grid);
+
+        (world, schedule)
+    }
+
+    fn spawn(world: &mut World, x: f64, y: f64, z: f64) -> Entity {
+        world
+            .spawn(Position {
+                position: Vec3d::new(x, y, z),
+            })
+            .id()
+    }
+
+    #[test]
+    fn test_radius_and_aabb_queries() {
+        let (mut world, mut schedule) = setup();
+        let near = spawn(&mut world, 3.0, 0.0, 0.0);
+        let across_cell = spawn(&mut world, -4.0, 0.0, 0.0);
+        let far = spawn(&mut world, 40.0, 0.0, 0.0);
+        schedule.run(&mut world);
+
+        let grid = world.resource::<SpatialGrid>();
+        let mut found = grid.within_radius(&Vec3d::zero(), 5.0);
+        found.sort();
+        let mut expected = vec![near, across_cell];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let found = grid.within_aabb(&Vec3d::new(0.0, -1.0, -1.0), &Vec3d::new(50.0, 1.0, 1.0));
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&near) && found.contains(&far));
+    }
+
+    #[test]
+    fn test_nearest_orders_by_distance() {
+        let (mut world, mut schedule) = setup();
+        let a = spawn(&mut world, 1.0, 0.0, 0.0);
+        let b = spawn(&mut world, 0.0, 25.0, 0.0);
+        let c = spawn(&mut world, 0.0, 0.0, -90.0);
+        schedule.run(&mut world);
+
+        let grid = world.resource::<SpatialGrid>();
+        assert_eq!(grid.nearest(&Vec3d::zero(), 2), vec![a, b]);
+        assert_eq!(grid.nearest(&Vec3d::zero(), 5), vec![a, b, c]);
+        assert!(grid.nearest(&Vec3d::zero(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_moves_and_despawns_update_the_grid() {
+        let (mut world, mut schedule) = setup();
+        let entity = spawn(&mut world, 0.0, 0.0, 0.0);
+        schedule.run(&mut world);
+
+        world.get_mut::<Position>(entity).unwrap().position.x = 35.0;
+        schedule.run(&mut world);
+
+        let grid = world.resource::<SpatialGrid>();
+        assert!(grid.within_radius(&Vec3d::zero(), 5.0).is_empty());
+        assert_eq!(
+            grid.within_radius(&Vec3d::new(35.0, 0.0, 0.0), 1.0),
+            vec![entity]
+        );
+
+        world.despawn(entity);
+        assert!(world.resource::<SpatialGrid>().is_empty());
+    }
+}
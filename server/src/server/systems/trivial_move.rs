@@ -24,7 +24,7 @@ pub fn trivival_move_system(
     for (_, _position, networked) in query.iter_mut() {
         if let Some(commands) = movement_commands.entries.get_mut(&networked.id) {
             commands.push_back(MoveCommand::new(
-                networked.id.clone(),
+                networked.id,
                 1.0,
                 0.0,
                 0.0,